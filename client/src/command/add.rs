@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+
+use failure;
+
+use pm_lib::package::PackageName;
+use pm_lib::version::Version;
+use pm_manifest::manifest::{get_dependencies, parse_and_check_manifest, Source};
+
+use manifest_edit::add_dependency;
+use project::{find_manifest_path, read_manifest_source};
+use resolve::fetch_releases;
+
+pub const USAGE: &'static str = "Add a dependency to the manifest.
+
+Adds the package to the `dependencies` block of the manifest, or changes its
+version constraint if it's there already, leaving the rest of the manifest as
+it was. Without a constraint, the package's latest release is looked up, and
+anything compatible with it is allowed.
+
+An existing dependency keeps its options, such as its `registry`, unless
+--registry is given, and its latest release is looked up in its registry.
+
+Usage:
+    pm add [options] <package> [<constraint>]
+
+Options:
+    --registry=<name>  Depend on the package from the named registry from the
+                       config, instead of the default one.
+    -h, --help         Display this message.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    arg_package: String,
+    arg_constraint: Option<String>,
+    flag_registry: Option<String>,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let package = PackageName::from_str(&args.arg_package)
+        .ok_or_else(|| format_err!("Invalid package name"))?;
+    let manifest_path = find_manifest_path()?;
+    let manifest_source = read_manifest_source(&manifest_path)?;
+
+    let registry = match args.flag_registry {
+        Some(ref registry) => Some(registry.clone()),
+        None => existing_registry(manifest_source.clone(), &package)?,
+    };
+    let constraint = match args.arg_constraint {
+        Some(ref constraint) => constraint.clone(),
+        None => format!("^{}", latest_release(&package, &registry)?),
+    };
+    let options = args
+        .flag_registry
+        .as_ref()
+        .map(|registry| format!("registry=\"{}\"", registry));
+    let edited = add_dependency(
+        manifest_source,
+        &package,
+        &constraint,
+        options.as_ref().map(|options| options.as_str()),
+    )?;
+    File::create(&manifest_path)?.write_all(edited.as_bytes())?;
+
+    println!("Added {} {} to the dependencies.", package, constraint);
+    Ok(())
+}
+
+/// The registry named by the `registry` option of an existing dependency on
+/// `package`, if there is one.
+fn existing_registry(
+    manifest_source: String,
+    package: &PackageName,
+) -> Result<Option<String>, failure::Error> {
+    let (_, sources, _) = get_dependencies(parse_and_check_manifest(manifest_source)?)?;
+    Ok(match sources.get(package) {
+        Some(&Source::Registry(ref registry)) => registry.clone(),
+        _ => None,
+    })
+}
+
+/// The newest release of `package` which hasn't been yanked, preferring
+/// stable releases to prereleases.
+fn latest_release(
+    package: &PackageName,
+    registry: &Option<String>,
+) -> Result<Version, failure::Error> {
+    let fetched = fetch_releases(&mut HashMap::new(), package, vec![registry.clone()])?;
+    let (releases, _) =
+        fetched.ok_or_else(|| format_err!("Can't find {} in the registry", package))?;
+    releases
+        .iter()
+        .filter(|&(_, release)| !release.yanked)
+        .map(|(version, _)| version)
+        .max_by_key(|version| (!version.has_pre(), *version))
+        .cloned()
+        .ok_or_else(|| format_err!("{} has no releases", package))
+}
@@ -0,0 +1,63 @@
+use failure;
+use serde_json;
+
+use pm_manifest::manifest::check_manifest;
+
+use git::GitScm;
+use project::{find_manifest_path, read_manifest_source};
+
+pub const USAGE: &'static str = "Check the manifest for errors.
+
+Reports every problem with the manifest at once, with the line and column it's
+at. Unknown fields and options come with a suggestion if they look like a typo
+of a known one.
+
+Usage:
+    pm check [options]
+
+Options:
+    --json             Print the problems as JSON, with a code for each kind.
+    -h, --help         Display this message.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    flag_json: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let manifest_path = find_manifest_path()?;
+    let root = manifest_path.parent().expect("manifests are in a directory");
+    let manifest_source = read_manifest_source(&manifest_path)?;
+    let diagnostics = match check_manifest(manifest_source, root, &GitScm) {
+        Ok(()) => vec![],
+        Err(error) => error.diagnostics(&manifest_path),
+    };
+
+    if args.flag_json {
+        println!("{}", serde_json::to_string_pretty(&diagnostics)?);
+    } else {
+        for diagnostic in &diagnostics {
+            let location = match (diagnostic.line, diagnostic.column) {
+                (Some(line), Some(column)) => format!(":{}:{}", line, column),
+                _ => String::new(),
+            };
+            println!(
+                "{}{}: {} [{}]",
+                diagnostic.file.display(),
+                location,
+                diagnostic.message,
+                diagnostic.code
+            );
+        }
+    }
+
+    match diagnostics.len() {
+        0 => Ok(()),
+        count => Err(format_err!(
+            "Found {} problem(s) in {}",
+            count,
+            manifest_path.display()
+        )),
+    }
+}
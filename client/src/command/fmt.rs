@@ -0,0 +1,44 @@
+use std::fs::File;
+use std::io::Write;
+
+use failure;
+
+use pm_manifest::manifest_fmt::format_manifest;
+
+use project::{find_manifest_path, read_manifest_source};
+
+pub const USAGE: &'static str = "Format the manifest.
+
+Rewrites the manifest in the canonical format: indented by four spaces per
+level, with the entries of `dependencies` and `overrides` blocks sorted and
+their constraints lined up. Comments stay with the lines they belong to.
+
+Usage:
+    pm fmt [options]
+
+Options:
+    --check            Don't write the manifest; fail if it isn't formatted.
+    -h, --help         Display this message.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    flag_check: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let manifest_path = find_manifest_path()?;
+    let manifest_source = read_manifest_source(&manifest_path)?;
+    let formatted = format_manifest(manifest_source.clone())?;
+    if formatted == manifest_source {
+        return Ok(());
+    }
+    if args.flag_check {
+        return Err(format_err!(
+            "{} is not formatted; run `pm fmt` to format it.",
+            manifest_path.display()
+        ));
+    }
+    File::create(&manifest_path)?.write_all(formatted.as_bytes())?;
+    Ok(())
+}
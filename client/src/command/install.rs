@@ -0,0 +1,65 @@
+use failure;
+
+use pm_lib::index::TargetSet;
+
+use install::{install, PACKAGES_DIR};
+use lockfile::{read_lockfile, write_lockfile};
+use project::{find_project_dir, read_dependencies};
+use resolve::{resolve_project, target_tags};
+
+pub const USAGE: &'static str = "Install dependencies.
+
+Resolves dependencies like `pm lock`, writes the lockfile, and installs every
+package into the `pm_packages` directory of the project. Path dependencies are
+linked to rather than copied, and Git dependencies get the files a release of
+them would contain.
+
+The lockfile is resolved for the same targets as before, which is every target
+unless `pm lock --target` narrowed them, but only the packages needed on this
+machine are installed: those without a `target` option, and those whose
+`target` names its OS, architecture or OS family, or one of the tags given to
+--target.
+
+Usage:
+    pm install [options]
+
+Options:
+    --registry=<name>  Look up dependencies without a `registry` option in the
+                       named registry from the config, not the default one.
+    --target=<tags>    Install for targets with these tags too, separated by
+                       commas.
+    -q, --quiet        Don't print any descriptive messages.
+    -h, --help         Display this message.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    flag_registry: Option<String>,
+    flag_target: Option<String>,
+    flag_quiet: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let requirements = read_dependencies()?;
+    let project_dir = find_project_dir()?;
+    let lockfile = read_lockfile(&project_dir)?;
+    let targets = lockfile
+        .as_ref()
+        .map_or(TargetSet::All, |lockfile| lockfile.targets().clone());
+
+    let resolution = resolve_project(
+        &project_dir,
+        &requirements,
+        &args.flag_registry,
+        lockfile.as_ref(),
+        &targets,
+    )?;
+    write_lockfile(&project_dir, &resolution.lockfile)?;
+    let tags = args.flag_target.as_ref().map_or(vec![], |option| target_tags(option));
+    let versions = resolution.versions_on(&TargetSet::host(&tags))?;
+
+    if !args.flag_quiet {
+        println!("Installing into {}:", PACKAGES_DIR);
+    }
+    install(&project_dir, &versions, &resolution.origins, args.flag_quiet)
+}
@@ -0,0 +1,56 @@
+use failure;
+
+use pm_lib::index::TargetSet;
+
+use lockfile::{read_lockfile, write_lockfile};
+use project::{find_project_dir, read_dependencies};
+use resolve::{resolve_project, target_tags, Resolution};
+
+pub const USAGE: &'static str = "Resolve dependencies and write the lockfile.
+
+Dependencies with a `target` option are resolved for every target they name,
+so the lockfile holds wherever the project is used, unless --target is given.
+
+Usage:
+    pm lock [options]
+
+Options:
+    --registry=<name>  Look up dependencies without a `registry` option in the
+                       named registry from the config, not the default one.
+    --target=<tags>    Only resolve for the targets with one of these tags,
+                       separated by commas.
+    -q, --quiet        Don't print any descriptive messages.
+    -h, --help         Display this message.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    flag_registry: Option<String>,
+    flag_target: Option<String>,
+    flag_quiet: bool,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let requirements = read_dependencies()?;
+    let project_dir = find_project_dir()?;
+    let lockfile = read_lockfile(&project_dir)?;
+    let targets = match args.flag_target {
+        Some(ref option) => TargetSet::Only(target_tags(option).into_iter().collect()),
+        None => TargetSet::All,
+    };
+
+    let Resolution { lockfile, .. } = resolve_project(
+        &project_dir,
+        &requirements,
+        &args.flag_registry,
+        lockfile.as_ref(),
+        &targets,
+    )?;
+
+    if !args.flag_quiet {
+        for (package, version) in lockfile.versions() {
+            println!("    {} {}", package, version);
+        }
+    }
+    write_lockfile(&project_dir, &lockfile)
+}
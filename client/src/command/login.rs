@@ -1,7 +1,10 @@
+use std::env;
+use std::io;
 use std::iter::FromIterator;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Duration;
 
 use data_encoding::HEXUPPER;
 use im::OrdMap as Map;
@@ -17,19 +20,42 @@ use hyper::header::{ContentLength, ContentType};
 use hyper::server::{Http, Request, Response, Service};
 use hyper::{self, StatusCode};
 
-use config::{get_config, write_config, Auth, Config};
+use config::{get_config, write_config};
+use registry::{self, Registry};
 
 pub const USAGE: &'static str = "Login.
 
+By default, this opens a browser to log in with, and waits for it to report
+back to a temporary local web server.
+
+Where that isn't possible, such as over SSH, use `--device`: it shows a short
+code to enter on the registry's website from any other device, and waits for
+you to do so.
+
+On CI, use `--token` to store an existing token, such as an API token created
+with `pm token create`. It is read from the PM_TOKEN environment variable if
+set, or from standard input otherwise.
+
+With `--registry`, you log in to one of the alternate registries named in the
+config rather than the default registry, and the token is stored for that
+registry only.
+
 Usage:
     pm login [options]
 
 Options:
-    -h, --help     Display this message.
+    --device           Log in by entering a code on another device.
+    --token            Store a token read from PM_TOKEN or standard input.
+    --registry=<name>  Log in to the named registry from the config.
+    -h, --help         Display this message.
 ";
 
 #[derive(Debug, Deserialize)]
-pub struct Args {}
+pub struct Args {
+    flag_device: bool,
+    flag_token: bool,
+    flag_registry: Option<String>,
+}
 
 const AUTHENTICATED_DOC: &'static str = "
 <html>
@@ -172,11 +198,23 @@ pub fn generate_secret() -> String {
     HEXUPPER.encode(&data)
 }
 
-pub fn execute(_: Args) -> Result<(), failure::Error> {
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let registry = Registry::from_config(&args.flag_registry)?;
+    let token = if args.flag_token {
+        read_token()?
+    } else if args.flag_device {
+        device_login(&registry)?
+    } else {
+        browser_login(&registry)?
+    };
+    save_token(registry.name(), token)
+}
+
+fn browser_login(registry: &Registry) -> Result<String, failure::Error> {
     let done = Done::new();
     let callback_done = done.clone();
     let secret = generate_secret();
-    let mut url = Url::parse("http://localhost:8000/login_client").unwrap();
+    let mut url = Url::parse(&registry.url("login_client"))?;
     url.query_pairs_mut().append_pair("token", &secret);
     let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 0);
     let server = Http::new()
@@ -196,18 +234,64 @@ pub fn execute(_: Args) -> Result<(), failure::Error> {
 
     server.run_until(done.clone()).unwrap();
 
-    let token = done
-        .get()
-        .expect("unable to get auth token from web server");
-
-    let config = get_config()?;
-    write_config(&Config {
-        auth: Auth {
-            token: Some(token),
-            ..config.auth
-        },
-        ..config
-    })?;
+    Ok(done.get().expect("unable to get auth token from web server"))
+}
+
+#[derive(Deserialize)]
+struct DeviceLogin {
+    device_code: String,
+    user_code: String,
+    verification_url: String,
+    interval: u64,
+}
+
+#[derive(Deserialize)]
+struct DevicePoll {
+    token: Option<String>,
+}
+
+fn device_login(registry: &Registry) -> Result<String, failure::Error> {
+    let login = match registry::post_anon::<DeviceLogin>(registry, "login/device", ordmap!{})? {
+        Ok(login) => login,
+        Err(msg) => return Err(format_err!("{}", msg)),
+    };
+    println!("To log in, visit {}", login.verification_url);
+    println!("and enter the code {}", login.user_code);
+    loop {
+        thread::sleep(Duration::from_secs(login.interval));
+        match registry::get::<DevicePoll>(
+            registry,
+            "login/device/poll",
+            ordmap!{"device_code".to_string() => login.device_code.clone()},
+        )? {
+            Ok(DevicePoll { token: Some(token) }) => return Ok(token),
+            Ok(DevicePoll { token: None }) => continue,
+            Err(msg) => return Err(format_err!("{}", msg)),
+        }
+    }
+}
+
+fn read_token() -> Result<String, failure::Error> {
+    let token = match env::var("PM_TOKEN") {
+        Ok(token) => token,
+        Err(_) => {
+            let mut token = String::new();
+            io::stdin().read_line(&mut token)?;
+            token
+        }
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        Err(format_err!("No token given in PM_TOKEN or on standard input."))
+    } else {
+        Ok(token.to_string())
+    }
+}
+
+fn save_token(registry: &Option<String>, token: String) -> Result<(), failure::Error> {
+    let mut config = get_config()?;
+    config.set_token(registry, token)?;
+    write_config(&config)?;
 
     Ok(())
 }
@@ -2,3 +2,16 @@ pub mod login;
 pub mod test;
 pub mod search;
 pub mod publish;
+pub mod lock;
+pub mod yank;
+pub mod owner;
+pub mod namespace;
+pub mod token;
+pub mod install;
+pub mod tree;
+pub mod why;
+pub mod outdated;
+pub mod add;
+pub mod remove;
+pub mod fmt;
+pub mod check;
@@ -0,0 +1,102 @@
+use failure;
+
+use registry::{self, Receipt, Registry};
+
+pub const USAGE: &'static str = "Manage namespaces in the registry.
+
+Packages can only be published into namespaces which exist. Creating and
+configuring namespaces is restricted to registry administrators.
+
+A namespace can be owned by GitHub organisations or GitLab groups, given by
+name. Every member of an owning organisation may publish and manage all of the
+namespace's packages. Administrators and members of an owning organisation can
+add and remove owning organisations, but only ones they are members of.
+
+Usage:
+    pm namespace list [options]
+    pm namespace create [options] <namespace>
+    pm namespace set [options] <namespace>
+    pm namespace add-org [options] <namespace> <org>
+    pm namespace remove-org [options] <namespace> <org>
+
+Options:
+    --version-scheme=<scheme>    Which versions may be published: `any`, or
+                                 `semver` for `major.minor.patch` only.
+    --package-creation=<policy>  Who may create new packages: `anyone`, or
+                                 `members` of the organisations owning the
+                                 namespace.
+    --registry=<name>            Use the named registry from the config.
+    -h, --help                   Display this message.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    cmd_list: bool,
+    cmd_create: bool,
+    cmd_set: bool,
+    cmd_add_org: bool,
+    cmd_remove_org: bool,
+    arg_namespace: String,
+    arg_org: String,
+    flag_version_scheme: Option<String>,
+    flag_package_creation: Option<String>,
+    flag_registry: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NamespaceInfo {
+    name: String,
+    version_scheme: String,
+    package_creation: String,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let registry = Registry::from_config(&args.flag_registry)?;
+    if args.cmd_list {
+        match registry::get::<Vec<NamespaceInfo>>(&registry, "namespaces", ordmap!{})? {
+            Ok(namespaces) => for namespace in namespaces {
+                println!(
+                    "{} (versions: {}, package creation: {})",
+                    namespace.name, namespace.version_scheme, namespace.package_creation
+                )
+            },
+            Err(msg) => return Err(format_err!("{}", msg)),
+        }
+        return Ok(());
+    }
+
+    if args.cmd_add_org || args.cmd_remove_org {
+        let (url, done) = if args.cmd_add_org {
+            ("namespaces/orgs/add", "is now an owner of")
+        } else {
+            ("namespaces/orgs/remove", "is no longer an owner of")
+        };
+        let query = ordmap!{
+            "namespace".to_string() => args.arg_namespace.clone(),
+            "org".to_string() => args.arg_org.clone()
+        };
+        match registry::post::<Receipt, _>(&registry, url, query, &b""[..])? {
+            Ok(_) => println!("{} {} namespace {}.", args.arg_org, done, args.arg_namespace),
+            Err(msg) => return Err(format_err!("{}", msg)),
+        }
+        return Ok(());
+    }
+
+    let mut query = ordmap!{"name".to_string() => args.arg_namespace.clone()};
+    if let Some(ref scheme) = args.flag_version_scheme {
+        query.insert("version_scheme".to_string(), scheme.clone());
+    }
+    if let Some(ref policy) = args.flag_package_creation {
+        query.insert("package_creation".to_string(), policy.clone());
+    }
+    let (url, done) = if args.cmd_create {
+        ("namespaces/create", "created")
+    } else {
+        ("namespaces/update", "updated")
+    };
+    match registry::post::<Receipt, _>(&registry, url, query, &b""[..])? {
+        Ok(_) => println!("Namespace {} has been {}.", args.arg_namespace, done),
+        Err(msg) => return Err(format_err!("{}", msg)),
+    }
+    Ok(())
+}
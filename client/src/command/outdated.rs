@@ -0,0 +1,128 @@
+use console::Style;
+use failure;
+use serde_json;
+
+use pm_lib::index::TargetSet;
+use pm_lib::version::Version;
+
+use graph::Graph;
+use lockfile::read_lockfile;
+use outdated::{check, Outdated};
+use project::{find_project_dir, read_dependencies};
+use resolve::{resolve_project, Resolution};
+use table::{print_table, Column, Width};
+
+pub const USAGE: &'static str = "List locked packages with newer releases.
+
+For each package in the lockfile from a registry, shows the newest release the
+project's version constraints allow, and the newest release of all. Locked
+releases which have since been yanked or deprecated are flagged too.
+
+Usage:
+    pm outdated [options]
+
+Options:
+    --json             Print the report as JSON.
+    --registry=<name>  Look up dependencies without a `registry` option in the
+                       named registry from the config, not the default one.
+    -h, --help         Display this message.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    flag_json: bool,
+    flag_registry: Option<String>,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let requirements = read_dependencies()?;
+    let project_dir = find_project_dir()?;
+    let lockfile = read_lockfile(&project_dir)?
+        .ok_or_else(|| format_err!("There is no lockfile yet; run `pm lock` first."))?;
+    let Resolution {
+        lockfile,
+        index,
+        dependencies,
+        registry_releases,
+        ..
+    } = resolve_project(
+        &project_dir,
+        &requirements,
+        &args.flag_registry,
+        Some(&lockfile),
+        &TargetSet::All,
+    )?;
+    let graph = Graph::new(&index, &dependencies, lockfile.versions());
+
+    let mut results = vec![];
+    for package in lockfile.versions().keys() {
+        if let Some(releases) = registry_releases.get(package) {
+            let outdated = check(&graph, package, releases);
+            if outdated.needs_attention() {
+                results.push(outdated);
+            }
+        }
+    }
+
+    if args.flag_json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+    } else if results.is_empty() {
+        println!("Everything is up to date!");
+    } else {
+        print_results(&results);
+    }
+    Ok(())
+}
+
+fn print_results(results: &[Outdated]) {
+    fn show(version: &Option<Version>) -> String {
+        match version {
+            &Some(ref version) => format!("{}", version),
+            &None => "-".to_string(),
+        }
+    }
+    print_table(
+        &[
+            Column {
+                title: "Package",
+                width: Width::Fit,
+                style: Style::new().bold(),
+                cell: |result: &Outdated| result.package.to_string(),
+            },
+            Column {
+                title: "Locked",
+                width: Width::Fit,
+                style: Style::new(),
+                cell: |result: &Outdated| result.locked.to_string(),
+            },
+            Column {
+                title: "Compatible",
+                width: Width::Fit,
+                style: Style::new(),
+                cell: |result: &Outdated| show(&result.compatible),
+            },
+            Column {
+                title: "Latest",
+                width: Width::Fit,
+                style: Style::new(),
+                cell: |result: &Outdated| show(&result.latest),
+            },
+            Column {
+                title: "Notes",
+                width: Width::Fit,
+                style: Style::new().red(),
+                cell: |result: &Outdated| {
+                    let mut notes = vec![];
+                    if result.yanked {
+                        notes.push("yanked");
+                    }
+                    if result.deprecated {
+                        notes.push("deprecated");
+                    }
+                    notes.join(", ")
+                },
+            },
+        ],
+        results,
+    )
+}
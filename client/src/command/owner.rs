@@ -0,0 +1,99 @@
+use failure;
+
+use pm_lib::package::PackageName;
+
+use registry::{self, Receipt, Registry};
+
+pub const USAGE: &'static str = "Manage the owners of a package.
+
+Usage:
+    pm owner list [options] <package>
+    pm owner add [options] <package> <owner>
+    pm owner remove [options] <package> <owner>
+
+Users are identified as `<provider>:<id>`, using the numeric account ID of the
+provider, as shown by `pm owner list`. A user must have logged in to the
+registry at least once before they can be added as an owner, and a package
+can't be left without owners.
+
+With `--org`, the owner is a GitHub organisation or GitLab group, given by its
+name. Every member of an owning organisation may publish and manage the
+package. You can only add organisations you are a member of yourself.
+
+Options:
+    --org              Add or remove an organisation rather than a user.
+    --registry=<name>  Use the named registry from the config.
+    -h, --help         Display this message.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    cmd_list: bool,
+    cmd_add: bool,
+    cmd_remove: bool,
+    arg_package: String,
+    arg_owner: String,
+    flag_org: bool,
+    flag_registry: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OrgOwner {
+    id: String,
+    name: String,
+    namespace: bool,
+}
+
+#[derive(Deserialize)]
+struct Owners {
+    users: Vec<String>,
+    orgs: Vec<OrgOwner>,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let package = PackageName::from_str(&args.arg_package)
+        .ok_or_else(|| format_err!("Invalid package name: {:?}", args.arg_package))?;
+    let registry = Registry::from_config(&args.flag_registry)?;
+    let mut query = ordmap!{
+        "namespace".to_string() => package.namespace.clone(),
+        "name".to_string() => package.name.clone()
+    };
+
+    if args.cmd_list {
+        match registry::get::<Owners>(&registry, "owners", query)? {
+            Ok(owners) => print_owners(&owners),
+            Err(msg) => return Err(format_err!("{}", msg)),
+        }
+        return Ok(());
+    }
+
+    let (url, done) = match (args.cmd_add, args.flag_org) {
+        (true, false) => ("owners/add", "is now an owner of"),
+        (false, false) => ("owners/remove", "is no longer an owner of"),
+        (true, true) => ("owners/orgs/add", "is now an owner of"),
+        (false, true) => ("owners/orgs/remove", "is no longer an owner of"),
+    };
+    query.insert(
+        if args.flag_org { "org" } else { "user" }.to_string(),
+        args.arg_owner.clone(),
+    );
+    match registry::post::<Receipt, _>(&registry, url, query, &b""[..])? {
+        Ok(_) => println!("{} {} {}.", args.arg_owner, done, package),
+        Err(msg) => return Err(format_err!("{}", msg)),
+    }
+    Ok(())
+}
+
+fn print_owners(owners: &Owners) {
+    for user in &owners.users {
+        println!("{}", user);
+    }
+    for org in &owners.orgs {
+        println!(
+            "{} (organisation {}{})",
+            org.name,
+            org.id,
+            if org.namespace { ", owns the namespace" } else { "" }
+        );
+    }
+}
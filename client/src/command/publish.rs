@@ -1,19 +1,19 @@
 use std::sync::Arc;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use indicatif::{ProgressBar, ProgressStyle};
-use console::Style;
 use tar;
 use brotli;
 use rmp_serde::encode;
 
 use pm_lib::manifest::Manifest;
+use pm_manifest::manifest::{get_published_dependencies, parse_and_check_manifest, Source};
 
 use failure;
 use io::ProgressIO;
-use project::{read_manifest, find_project_dir};
-use registry::post;
+use project::{find_member, find_package_dir, read_manifest_in, read_manifest_source};
+use registry::{post, Receipt, Registry};
 
 pub const USAGE: &'static str = "Publish a package to the registry.
 
@@ -21,10 +21,14 @@ Usage:
     pm publish [options]
 
 Options:
-    -v, --verbose  List files being added to the release.
-    -q, --quiet    Don't print any descriptive messages.
-    --dry-run      Run through the procedure, but don't actually publish.
-    -h, --help     Display this message.
+    -v, --verbose      List files being added to the release.
+    -q, --quiet        Don't print any descriptive messages.
+    --dry-run          Run through the procedure, but don't actually publish.
+    --registry=<name>  Publish to the named registry from the config.
+    -p, --package=<member>
+                       Publish the given member of the workspace, by package
+                       name or directory, instead of the current package.
+    -h, --help         Display this message.
 ";
 
 #[derive(Debug, Deserialize)]
@@ -32,6 +36,8 @@ pub struct Args {
     flag_verbose: bool,
     flag_dry_run: bool,
     flag_quiet: bool,
+    flag_registry: Option<String>,
+    flag_package: Option<String>,
 }
 
 fn make_progress(msg: &str, len: usize, quiet: bool) -> ProgressBar {
@@ -48,13 +54,38 @@ fn make_progress(msg: &str, len: usize, quiet: bool) -> ProgressBar {
 }
 
 pub fn execute(args: Args) -> Result<(), failure::Error> {
-    let manifest = read_manifest()?;
+    let package_dir = match args.flag_package {
+        Some(ref member) => find_member(member)?,
+        None => find_package_dir()?,
+    };
+    let manifest = read_manifest_in(&package_dir)?;
+    let manifest_source = read_manifest_source(&package_dir.join("manifest"))?;
+    // Whoever installs the release may be on another platform, so the
+    // dependencies for every platform are published, not just those for this
+    // one.
+    let (dependencies, sources) =
+        get_published_dependencies(parse_and_check_manifest(manifest_source.clone())?)?;
+    let registry = Registry::from_config(&args.flag_registry)?;
+
+    for (package, source) in &sources {
+        let kind = match source {
+            &Source::Registry(_) => continue,
+            &Source::Path(_) => "path",
+            &Source::Git { .. } => "Git",
+        };
+        return Err(format_err!(
+            "Can't publish with a {} dependency on {}; depend on a release instead.",
+            kind,
+            package
+        ));
+    }
 
     if !args.flag_quiet {
         println!("Building release {}-{}...", manifest.name, manifest.version);
     }
 
     let tar = build_archive(
+        &package_dir,
         manifest.files.iter().map(|f| PathBuf::from(f)).collect(),
         &args,
     )?;
@@ -79,8 +110,13 @@ pub fn execute(args: Args) -> Result<(), failure::Error> {
         description: manifest.description.clone(),
         license: manifest.license.clone(),
         readme: manifest.readme.clone(),
+        authors: manifest.authors.clone(),
+        homepage: manifest.homepage.clone(),
+        repository: manifest.repository.clone(),
+        bugs: manifest.bugs.clone(),
         keywords: manifest.keywords.clone(),
-        manifest: String::new(),
+        dependencies,
+        manifest: manifest_source,
         data: artifact,
     };
 
@@ -90,34 +126,34 @@ pub fn execute(args: Args) -> Result<(), failure::Error> {
     let body = ProgressIO::reader_from(payload, move |c, _| up.set_position(c as u64));
 
     let res = if !args.flag_dry_run {
-        post::<(), _>("publish", ordmap![], body)?
+        post::<Receipt, _>(&registry, "publish", ordmap![], body)?
     } else {
-        Ok(())
+        Ok(Receipt { ok: true })
     };
     upload_progress.finish_and_clear();
 
+    if let Err(msg) = res {
+        return Err(format_err!("{}", msg));
+    }
     if !args.flag_quiet {
         if args.flag_dry_run {
             println!("Seems to work!")
         } else {
-            match res {
-                Ok(_) => {
-                    println!(
-                        "Package {} version {} has been published!",
-                        manifest.name,
-                        manifest.version
-                    )
-                }
-                Err(msg) => println!("{}: {}", Style::new().red().bold().apply_to("ERROR"), msg),
-            }
+            println!(
+                "Package {} version {} has been published!",
+                manifest.name, manifest.version
+            )
         }
     }
 
     Ok(())
 }
 
-fn build_archive(files: Vec<PathBuf>, args: &Args) -> Result<Vec<u8>, failure::Error> {
-    let project_path = find_project_dir()?;
+fn build_archive(
+    package_dir: &Path,
+    files: Vec<PathBuf>,
+    args: &Args,
+) -> Result<Vec<u8>, failure::Error> {
     let mut tar = tar::Builder::new(Vec::new());
     for local_path in files {
         if args.flag_verbose {
@@ -131,9 +167,7 @@ fn build_archive(files: Vec<PathBuf>, args: &Args) -> Result<Vec<u8>, failure::E
                 println!("    {}", repr)
             }
         }
-        let mut path = project_path.clone();
-        path.push(local_path.clone());
-        let mut file = File::open(path)?;
+        let mut file = File::open(package_dir.join(&local_path))?;
         tar.append_file(local_path, &mut file)?;
     }
     tar.finish()?;
@@ -0,0 +1,39 @@
+use std::fs::File;
+use std::io::Write;
+
+use failure;
+
+use pm_lib::package::PackageName;
+
+use manifest_edit::remove_dependency;
+use project::{find_manifest_path, read_manifest_source};
+
+pub const USAGE: &'static str = "Remove a dependency from the manifest.
+
+Removes the package from the `dependencies` block of the manifest, leaving the
+rest of the manifest as it was.
+
+Usage:
+    pm remove [options] <package>
+
+Options:
+    -h, --help         Display this message.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    arg_package: String,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let package = PackageName::from_str(&args.arg_package)
+        .ok_or_else(|| format_err!("Invalid package name"))?;
+
+    let manifest_path = find_manifest_path()?;
+    let manifest_source = read_manifest_source(&manifest_path)?;
+    let edited = remove_dependency(manifest_source, &package)?;
+    File::create(&manifest_path)?.write_all(edited.as_bytes())?;
+
+    println!("Removed {} from the dependencies.", package);
+    Ok(())
+}
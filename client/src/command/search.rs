@@ -1,9 +1,8 @@
-use std::cmp::{min, max};
-
 use failure;
-use console::{Term, Style};
+use console::Style;
 
-use registry;
+use registry::{self, Registry};
+use table::{print_table, Column, Width};
 
 pub const USAGE: &'static str = "Search for packages by keyword.
 
@@ -11,13 +10,15 @@ Usage:
     pm search [options] <namespace> <keyword>...
 
 Options:
-    -h, --help     Display this message.
+    --registry=<name>  Search the named registry from the config.
+    -h, --help         Display this message.
 ";
 
 #[derive(Debug, Deserialize)]
 pub struct Args {
     arg_namespace: String,
     arg_keyword: Vec<String>,
+    flag_registry: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -30,7 +31,9 @@ pub struct SearchResult {
 }
 
 pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let registry = Registry::from_config(&args.flag_registry)?;
     match registry::get::<Vec<SearchResult>>(
+        &registry,
         "search",
         ordmap!{"ns".to_string() => args.arg_namespace, "q".to_string() => args.arg_keyword.join(" ")},
     )? {
@@ -41,46 +44,28 @@ pub fn execute(args: Args) -> Result<(), failure::Error> {
     Ok(())
 }
 
-fn print_results(results: &Vec<SearchResult>) {
-    let border = Style::new().dim();
-    let header = Style::new().green();
-    let package = Style::new().bold();
-
-    let term = Term::stdout();
-    let width = term.size().1 as usize;
-    let max_avail = width - 2;
-    let bar = border.apply_to("|");
-    let w_pkg = max(
-        7,
-        min(24, results.iter().map(|r| r.name.len()).max().unwrap()),
-    );
-    let h_pkg = header.apply_to(format!("{:1$}", "Package", w_pkg));
-    let w_ver = max(
-        7,
-        min(16, results.iter().map(|r| r.version.len()).max().unwrap()),
-    );
-    let h_ver = header.apply_to(format!("{:1$}", "Version", w_ver));
-    let w_desc = max_avail - w_pkg - w_ver;
-    let h_desc = header.apply_to(format!("{:1$}", "Description", w_desc));
-    let sep = border.apply_to(format!(
-        "{}|{}|{}",
-        "-".repeat(w_pkg),
-        "-".repeat(w_ver),
-        "-".repeat(w_desc)
-    ));
-    println!("{}", sep);
-    println!("{}{}{}{}{}", h_pkg, bar, h_ver, bar, h_desc);
-    println!("{}", sep);
-    for result in results {
-        println!(
-            "{:4$}{3}{:5$}{3}{}",
-            package.apply_to(&result.name),
-            result.version,
-            result.description,
-            bar,
-            w_pkg,
-            w_ver
-        )
-    }
-    println!("{}", sep);
+fn print_results(results: &[SearchResult]) {
+    print_table(
+        &[
+            Column {
+                title: "Package",
+                width: Width::AtMost(24),
+                style: Style::new().bold(),
+                cell: |result: &SearchResult| result.name.clone(),
+            },
+            Column {
+                title: "Version",
+                width: Width::AtMost(16),
+                style: Style::new(),
+                cell: |result: &SearchResult| result.version.clone(),
+            },
+            Column {
+                title: "Description",
+                width: Width::Fill,
+                style: Style::new(),
+                cell: |result: &SearchResult| result.description.clone(),
+            },
+        ],
+        results,
+    )
 }
@@ -1,5 +1,5 @@
 use failure;
-use registry;
+use registry::{self, Registry};
 
 pub const USAGE: &'static str = "Test page.
 
@@ -7,16 +7,20 @@ Usage:
     pm test [options]
 
 Options:
-    -h, --help     Display this message.
+    --registry=<name>  Use the named registry from the config.
+    -h, --help         Display this message.
 ";
 
 #[derive(Debug, Deserialize)]
-pub struct Args {}
+pub struct Args {
+    flag_registry: Option<String>,
+}
 
 
 
-pub fn execute(_args: Args) -> Result<(), failure::Error> {
-    match registry::get_auth::<String>("test", ordmap!{})? {
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let registry = Registry::from_config(&args.flag_registry)?;
+    match registry::get_auth::<String>(&registry, "test", ordmap!{})? {
         Ok(_) => println!("You are logged in with a valid auth token."),
         Err(msg) => println!("Registry response: {}", msg),
     };
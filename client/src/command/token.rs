@@ -0,0 +1,125 @@
+use console::Style;
+use failure;
+
+use registry::{self, Receipt, Registry};
+
+pub const USAGE: &'static str = "Manage API tokens.
+
+API tokens let tools such as CI jobs act on your behalf without a full login.
+Each token is limited to the scopes it was created with, and optionally to
+specific packages. The scopes are:
+
+    publish       Publish new releases.
+    yank          Yank and un-yank releases.
+    owner-admin   Add and remove package owners.
+
+Creating tokens requires logging in with `pm login` rather than with another
+API token. A new token is only shown once, so store it somewhere safe.
+
+Usage:
+    pm token list [options]
+    pm token create [options] <name>
+    pm token revoke [options] <id>
+
+Options:
+    --scopes=<scopes>      Comma separated list of scopes. [default: publish]
+    --packages=<packages>  Comma separated list of packages the token is
+                           limited to, eg. `js/left-pad,js/right-pad`.
+    --expires=<days>       Let the token expire after this many days.
+    --registry=<name>      Use the named registry from the config.
+    -h, --help             Display this message.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    cmd_list: bool,
+    cmd_create: bool,
+    cmd_revoke: bool,
+    arg_name: String,
+    arg_id: String,
+    flag_scopes: String,
+    flag_packages: Option<String>,
+    flag_expires: Option<u64>,
+    flag_registry: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NewToken {
+    id: String,
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct TokenInfo {
+    id: String,
+    name: String,
+    scopes: Vec<String>,
+    packages: Vec<String>,
+    created_on: String,
+    expires_on: Option<String>,
+    revoked_on: Option<String>,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let registry = Registry::from_config(&args.flag_registry)?;
+    if args.cmd_list {
+        match registry::get_auth::<Vec<TokenInfo>>(&registry, "tokens", ordmap!{})? {
+            Ok(ref tokens) if tokens.is_empty() => println!("You have no API tokens."),
+            Ok(tokens) => for token in tokens {
+                print_token(&token)
+            },
+            Err(msg) => return Err(format_err!("{}", msg)),
+        }
+    } else if args.cmd_create {
+        let mut query = ordmap!{
+            "name".to_string() => args.arg_name.clone(),
+            "scopes".to_string() => args.flag_scopes.clone()
+        };
+        if let Some(ref packages) = args.flag_packages {
+            query.insert("packages".to_string(), packages.clone());
+        }
+        if let Some(days) = args.flag_expires {
+            query.insert("expires_in_days".to_string(), days.to_string());
+        }
+        match registry::post::<NewToken, _>(&registry, "tokens/create", query, &b""[..])? {
+            Ok(token) => {
+                println!("Created API token {}:", token.id);
+                println!();
+                println!("    {}", token.token);
+                println!();
+                println!("This is the only time it will be shown.");
+            }
+            Err(msg) => return Err(format_err!("{}", msg)),
+        }
+    } else {
+        match registry::post::<Receipt, _>(
+            &registry,
+            "tokens/revoke",
+            ordmap!{"id".to_string() => args.arg_id.clone()},
+            &b""[..],
+        )? {
+            Ok(_) => println!("API token {} has been revoked.", args.arg_id),
+            Err(msg) => return Err(format_err!("{}", msg)),
+        }
+    }
+    Ok(())
+}
+
+fn print_token(token: &TokenInfo) {
+    let status = match (&token.revoked_on, &token.expires_on) {
+        (&Some(ref revoked), _) => format!("revoked {}", revoked),
+        (&None, &Some(ref expires)) => format!("expires {}", expires),
+        (&None, &None) => "never expires".to_string(),
+    };
+    println!(
+        "{} {} ({})",
+        Style::new().bold().apply_to(&token.id),
+        token.name,
+        status
+    );
+    println!("    created {}", token.created_on);
+    println!("    scopes: {}", token.scopes.join(", "));
+    if !token.packages.is_empty() {
+        println!("    packages: {}", token.packages.join(", "));
+    }
+}
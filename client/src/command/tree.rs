@@ -0,0 +1,78 @@
+use failure;
+
+use pm_lib::index::TargetSet;
+use pm_lib::package::PackageName;
+
+use graph::Graph;
+use lockfile::read_lockfile;
+use project::{find_project_dir, read_dependencies};
+use resolve::{resolve_project, Resolution};
+
+pub const USAGE: &'static str = "Show the resolved dependency graph.
+
+Resolves dependencies like `pm lock`, without writing the lockfile, and draws
+them as a tree. Packages which appear more than once are only expanded the
+first time, and marked with (*) after that.
+
+Usage:
+    pm tree [options]
+
+Options:
+    -i, --invert=<package>  Show what depends on the given package instead.
+    -d, --depth=<n>         Only show dependencies this many levels deep.
+    --format=<format>       Either `tree`, `dot` for Graphviz, or `json`. The
+                            last two print the whole graph, ignoring --invert
+                            and --depth. [default: tree]
+    --registry=<name>       Look up dependencies without a `registry` option
+                            in the named registry from the config, not the
+                            default one.
+    -h, --help              Display this message.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    flag_invert: Option<String>,
+    flag_depth: Option<usize>,
+    flag_format: String,
+    flag_registry: Option<String>,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let invert = match args.flag_invert {
+        Some(ref name) => Some(
+            PackageName::from_str(name).ok_or_else(|| format_err!("Invalid package name"))?,
+        ),
+        None => None,
+    };
+
+    let requirements = read_dependencies()?;
+    let project_dir = find_project_dir()?;
+    let lockfile = read_lockfile(&project_dir)?;
+    let Resolution {
+        lockfile,
+        index,
+        dependencies,
+        ..
+    } = resolve_project(
+        &project_dir,
+        &requirements,
+        &args.flag_registry,
+        lockfile.as_ref(),
+        &TargetSet::All,
+    )?;
+    let graph = Graph::new(&index, &dependencies, lockfile.versions());
+
+    if let Some(ref package) = invert {
+        if !graph.versions.contains_key(package) {
+            return Err(format_err!("{} is not a dependency of the project", package));
+        }
+    }
+
+    match args.flag_format.as_str() {
+        "tree" => print!("{}", graph.tree(invert.as_ref(), args.flag_depth)),
+        "dot" => print!("{}", graph.dot()),
+        "json" => println!("{}", graph.json()?),
+        format => return Err(format_err!("Unknown format: {}", format)),
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use failure;
+
+use pm_lib::index::TargetSet;
+use pm_lib::package::PackageName;
+
+use graph::{Chain, Graph};
+use lockfile::read_lockfile;
+use project::{find_project_dir, read_dependencies};
+use resolve::{resolve_project, Resolution};
+
+pub const USAGE: &'static str = "Explain why a package is a dependency of the project.
+
+Resolves dependencies like `pm lock`, without writing the lockfile, and lists
+every chain of dependencies leading from the project to the package, with the
+version constraint each step imposes. The chain the resolver followed when it
+picked the package's version comes first.
+
+Usage:
+    pm why [options] <package>
+
+Options:
+    --registry=<name>  Look up dependencies without a `registry` option in the
+                       named registry from the config, not the default one.
+    -h, --help         Display this message.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    arg_package: String,
+    flag_registry: Option<String>,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let package = PackageName::from_str(&args.arg_package)
+        .ok_or_else(|| format_err!("Invalid package name"))?;
+
+    let requirements = read_dependencies()?;
+    let project_dir = find_project_dir()?;
+    let lockfile = read_lockfile(&project_dir)?;
+    let Resolution {
+        lockfile,
+        index,
+        dependencies,
+        solution,
+        ..
+    } = resolve_project(
+        &project_dir,
+        &requirements,
+        &args.flag_registry,
+        lockfile.as_ref(),
+        &TargetSet::All,
+    )?;
+    let graph = Graph::new(&index, &dependencies, lockfile.versions());
+    let version = graph
+        .versions
+        .get(&package)
+        .ok_or_else(|| format_err!("{} is not a dependency of the project", package))?;
+
+    let justification: Option<Vec<PackageName>> = solution.path(&package).map(|path| {
+        path.iter()
+            .map(|&(ref dependent, _)| PackageName::clone(dependent))
+            .chain(Some(package.clone()))
+            .collect()
+    });
+    let mut chains = graph.chains_to(&package);
+    chains.sort_by_key(|chain| {
+        let packages: Vec<PackageName> = chain.iter().map(|&(ref p, _)| p.clone()).collect();
+        Some(packages) != justification
+    });
+
+    println!("{} {} is needed by the project through:", package, version);
+    for chain in chains {
+        println!("    {}", describe_chain(&graph, &chain));
+    }
+    Ok(())
+}
+
+/// Show a chain as `js/a ^1 (1.2.0) -> js/b ^0.1`, with the version picked for
+/// each package except the last.
+fn describe_chain(graph: &Graph, chain: &Chain) -> String {
+    chain
+        .iter()
+        .enumerate()
+        .map(|(i, &(ref package, ref constraint))| {
+            if i + 1 == chain.len() {
+                format!("{} {}", package, constraint)
+            } else {
+                format!("{} {} ({})", package, constraint, graph.versions[package])
+            }
+        }).collect::<Vec<_>>()
+        .join(" -> ")
+}
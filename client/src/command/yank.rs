@@ -0,0 +1,54 @@
+use failure;
+
+use pm_lib::package::PackageName;
+use pm_lib::version::Version;
+
+use registry::{post, Receipt, Registry};
+
+pub const USAGE: &'static str = "Yank a release from the registry.
+
+A yanked release stays available to projects whose lockfile already refers to
+it, but will not be picked for new dependency resolutions.
+
+Usage:
+    pm yank [options] <package> <version>
+
+Options:
+    --undo             Un-yank the release, making it available again.
+    --registry=<name>  Use the named registry from the config.
+    -h, --help         Display this message.
+";
+
+#[derive(Debug, Deserialize)]
+pub struct Args {
+    arg_package: String,
+    arg_version: String,
+    flag_undo: bool,
+    flag_registry: Option<String>,
+}
+
+pub fn execute(args: Args) -> Result<(), failure::Error> {
+    let package = PackageName::from_str(&args.arg_package)
+        .ok_or_else(|| format_err!("Invalid package name: {:?}", args.arg_package))?;
+    let version = Version::from_str(&args.arg_version)
+        .ok_or_else(|| format_err!("Invalid version: {:?}", args.arg_version))?;
+
+    let registry = Registry::from_config(&args.flag_registry)?;
+    let res = post::<Receipt, _>(
+        &registry,
+        if args.flag_undo { "unyank" } else { "yank" },
+        ordmap!{
+            "namespace".to_string() => package.namespace.clone(),
+            "name".to_string() => package.name.clone(),
+            "version".to_string() => version.to_string()
+        },
+        &b""[..],
+    )?;
+
+    match res {
+        Ok(_) if args.flag_undo => println!("Release {} {} has been un-yanked.", package, version),
+        Ok(_) => println!("Release {} {} has been yanked.", package, version),
+        Err(msg) => return Err(format_err!("{}", msg)),
+    }
+    Ok(())
+}
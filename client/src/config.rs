@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::io::{Read, Write};
 use std::fs::{File, create_dir_all};
 use failure;
@@ -5,26 +6,98 @@ use toml;
 
 use path::config_path;
 
-#[derive(Serialize, Deserialize)]
+/// The registry used when the config doesn't name one.
+pub const DEFAULT_REGISTRY_URL: &'static str = "http://localhost:8000";
+
+/// The user's configuration, stored in `~/.package-manager/config.toml`:
+///
+/// ```toml
+/// [auth]
+/// token = "..."                # token for the default registry
+///
+/// [registry]
+/// url = "https://registry.example.com"
+///
+/// [registries.work]
+/// url = "https://pm.example.com"
+/// token = "..."
+/// ```
+#[derive(Serialize, Deserialize, Default)]
 pub struct Config {
+    #[serde(default)]
     pub auth: Auth,
+    #[serde(default)]
+    pub registry: DefaultRegistry,
+    /// Alternate registries, selected by name with `--registry` or a
+    /// dependency's `registry` option.
+    #[serde(default)]
+    pub registries: BTreeMap<String, NamedRegistry>,
 }
 
 impl Config {
-    fn new() -> Config {
-        Config {
-            auth: Auth {
-                token: None
+    /// Look up the URL and auth token of the registry called `name`, or of the
+    /// default registry if `name` is `None`.
+    pub fn registry(
+        &self,
+        name: &Option<String>,
+    ) -> Result<(String, Option<String>), failure::Error> {
+        match name {
+            &None => Ok((self.registry.url.clone(), self.auth.token.clone())),
+            &Some(ref name) => self
+                .registries
+                .get(name)
+                .map(|registry| (registry.url.clone(), registry.token.clone()))
+                .ok_or_else(|| format_err!("No registry called `{}` in the config.", name)),
+        }
+    }
+
+    pub fn set_token(
+        &mut self,
+        name: &Option<String>,
+        token: String,
+    ) -> Result<(), failure::Error> {
+        match name {
+            &None => self.auth.token = Some(token),
+            &Some(ref name) => {
+                self.registries
+                    .get_mut(name)
+                    .ok_or_else(|| format_err!("No registry called `{}` in the config.", name))?
+                    .token = Some(token)
             }
         }
+        Ok(())
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
 pub struct Auth {
     pub token: Option<String>,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct DefaultRegistry {
+    #[serde(default = "default_registry_url")]
+    pub url: String,
+}
+
+impl Default for DefaultRegistry {
+    fn default() -> Self {
+        DefaultRegistry {
+            url: default_registry_url(),
+        }
+    }
+}
+
+fn default_registry_url() -> String {
+    DEFAULT_REGISTRY_URL.to_string()
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NamedRegistry {
+    pub url: String,
+    pub token: Option<String>,
+}
+
 fn read_config<R>(r: &mut R) -> Result<Config, failure::Error>
 where
     R: Read,
@@ -38,7 +111,7 @@ pub fn get_config() -> Result<Config, failure::Error> {
     let mut path = config_path()?;
     path.push("config.toml");
     match File::open(path) {
-        Err(_) => Ok(Config::new()),
+        Err(_) => Ok(Config::default()),
         Ok(mut file) => read_config(&mut file)
     }
 }
@@ -52,3 +125,41 @@ pub fn write_config(config: &Config) -> Result<(), failure::Error> {
     file.write(data.as_bytes())?;
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_old_configs() {
+        let config = read_config(&mut &b"[auth]\ntoken = \"secret\"\n"[..]).unwrap();
+        assert_eq!(
+            config.registry(&None).unwrap(),
+            (DEFAULT_REGISTRY_URL.to_string(), Some("secret".to_string()))
+        );
+        assert!(config.registry(&Some("work".to_string())).is_err());
+    }
+
+    #[test]
+    fn named_registries() {
+        let mut config = read_config(
+            &mut &br#"
+[registry]
+url = "https://public.example.com"
+
+[registries.work]
+url = "https://pm.example.com"
+"#[..],
+        ).unwrap();
+        let work = Some("work".to_string());
+        config.set_token(&work, "secret".to_string()).unwrap();
+        assert_eq!(
+            config.registry(&work).unwrap(),
+            ("https://pm.example.com".to_string(), Some("secret".to_string()))
+        );
+        assert_eq!(
+            config.registry(&None).unwrap(),
+            ("https://public.example.com".to_string(), None)
+        );
+    }
+}
@@ -1,11 +1,17 @@
 #![allow(dead_code)]
 
 use std::path::{Path,PathBuf};
-use std::fs::canonicalize;
+use std::fs::{canonicalize, create_dir_all, remove_dir_all, rename};
 use failure;
-use git2::{Repository,RepositoryState,StatusOptions,Tree};
+use git2::{Direction,ObjectType,Oid,Repository,RepositoryState,StatusOptions,Tree};
+use git2::build::CheckoutBuilder;
 use git2;
 
+use pm_manifest::files::Scm;
+use pm_manifest::manifest::GitReference;
+
+use path::config_path;
+
 pub struct GitScmProvider {
     pub relative_package_root: PathBuf, // relative to repo.workdir()
     pub repo: Repository,
@@ -123,6 +129,126 @@ impl GitScmProvider {
     }
 }
 
+/// A commit of a Git dependency, checked out in the cache.
+pub struct GitCheckout {
+    pub dir: PathBuf,
+    pub commit: String,
+}
+
+/// Where Git dependencies are fetched to.
+pub fn cache_dir() -> Result<PathBuf, failure::Error> {
+    Ok(config_path()?.join("git"))
+}
+
+/// Fetch the repository at `url` into `cache_dir`, and check out the commit
+/// `reference` points to, or `locked_commit` if given.
+///
+/// Every commit gets a checkout of its own, which is itself a pristine Git
+/// repository, so its manifest's `files` block is evaluated just as it would
+/// be when publishing it.
+pub fn checkout(
+    cache_dir: &Path,
+    url: &str,
+    reference: &GitReference,
+    locked_commit: Option<&str>,
+) -> Result<GitCheckout, failure::Error> {
+    let name = cache_name(url);
+    let db_dir = cache_dir.join("db").join(&name);
+    let db = if db_dir.exists() {
+        Repository::open_bare(&db_dir)?
+    } else {
+        create_dir_all(&db_dir)?;
+        let db = Repository::init_bare(&db_dir)?;
+        db.remote("origin", url)?;
+        db
+    };
+
+    let oid = match locked_commit {
+        Some(commit) => {
+            let oid = Oid::from_str(commit)?;
+            if db.find_commit(oid).is_err() {
+                fetch(&db)?;
+            }
+            oid
+        }
+        None => {
+            let head = fetch(&db)?;
+            resolve_reference(&db, reference, head).ok_or_else(|| {
+                GitError::UnknownReference(reference.to_string(), url.to_string())
+            })?
+        }
+    };
+    let commit = oid.to_string();
+
+    let dir = cache_dir.join("checkouts").join(&name).join(&commit);
+    if !dir.exists() {
+        // Check out into a temporary directory first, so an interrupted
+        // checkout doesn't leave a broken one behind.
+        let tmp_dir = dir.with_file_name(format!("{}.tmp", commit));
+        if tmp_dir.exists() {
+            remove_dir_all(&tmp_dir)?;
+        }
+        create_dir_all(&tmp_dir)?;
+        {
+            let repo = Repository::init(&tmp_dir)?;
+            let db_url = db_dir.to_str().ok_or(GitError::Utf8)?;
+            repo.remote("origin", db_url)?.fetch(
+                &["+refs/remotes/origin/*:refs/remotes/origin/*", "+refs/tags/*:refs/tags/*"],
+                None,
+                None,
+            )?;
+            repo.set_head_detached(oid)?;
+            repo.checkout_head(Some(CheckoutBuilder::new().force()))?;
+        }
+        rename(&tmp_dir, &dir)?;
+    }
+
+    Ok(GitCheckout { dir, commit })
+}
+
+/// Fetch all branches and tags of `origin`, returning the commit its HEAD
+/// points to.
+fn fetch(db: &Repository) -> Result<Option<Oid>, failure::Error> {
+    let mut remote = db.find_remote("origin")?;
+    remote.fetch(
+        &["+refs/heads/*:refs/remotes/origin/*", "+refs/tags/*:refs/tags/*"],
+        None,
+        None,
+    )?;
+    remote.connect(Direction::Fetch)?;
+    let head = remote
+        .list()?
+        .iter()
+        .find(|head| head.name() == "HEAD")
+        .map(|head| head.oid());
+    remote.disconnect();
+    Ok(head)
+}
+
+fn resolve_reference(
+    db: &Repository,
+    reference: &GitReference,
+    head: Option<Oid>,
+) -> Option<Oid> {
+    let spec = match reference {
+        &GitReference::Rev(ref rev) => rev.clone(),
+        &GitReference::Branch(ref branch) => format!("refs/remotes/origin/{}", branch),
+        &GitReference::Tag(ref tag) => format!("refs/tags/{}", tag),
+        &GitReference::DefaultBranch => return head,
+    };
+    db.revparse_single(&spec)
+        .and_then(|object| object.peel(ObjectType::Commit))
+        .map(|commit| commit.id())
+        .ok()
+}
+
+/// A directory name for the repository at `url`.
+fn cache_name(url: &str) -> String {
+    url.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect()
+}
+
 
 quick_error! {
     #[derive(Debug)]
@@ -149,10 +275,96 @@ quick_error! {
         SubmodulesPresent {
             description("Git repositories with submodules are currently unsupported")
         }
+        UnknownReference(reference: String, url: String) {
+            display("Can't find {} in {}", reference, url)
+        }
     }
 }
 
+/// Lists the files committed to Git, for `add_committed` in manifests.
+pub struct GitScm;
+
+impl Scm for GitScm {
+    fn committed_files(&self, root: &Path) -> Result<Vec<String>, failure::Error> {
+        let git_scm_provider = GitScmProvider::new(root)?;
+        git_scm_provider.check_repo_is_pristine()?;
+        git_scm_provider.ls_files()
+    }
+}
 
 pub fn test_git() {
     println!("{:?}", GitScmProvider::new(&Path::new(".")).unwrap().ls_files());
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use git2::Signature;
+    use pm_lib::test_helpers::TempDir;
+    use std::fs::File;
+    use std::io::{Read, Write};
+
+    fn commit_file(repo: &Repository, name: &str, contents: &str) -> Oid {
+        File::create(repo.workdir().unwrap().join(name))
+            .unwrap()
+            .write_all(contents.as_bytes())
+            .unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let parent = repo
+            .refname_to_id("HEAD")
+            .ok()
+            .map(|oid| repo.find_commit(oid).unwrap());
+        let parents = parent.iter().collect::<Vec<_>>();
+        repo.commit(Some("HEAD"), &signature, &signature, "Commit", &tree, &parents)
+            .unwrap()
+    }
+
+    fn read_file(dir: &Path, name: &str) -> String {
+        let mut contents = String::new();
+        File::open(dir.join(name))
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        contents
+    }
+
+    #[test]
+    fn checks_out_references() {
+        let root = TempDir::new();
+        let repo = Repository::init(root.join("src")).unwrap();
+        let first = commit_file(&repo, "a.txt", "1");
+        repo.tag_lightweight("v1", &repo.find_object(first, None).unwrap(), false)
+            .unwrap();
+        let second = commit_file(&repo, "a.txt", "2");
+        let url = format!("file://{}", root.join("src").display());
+        let cache = root.join("cache");
+
+        let tagged = checkout(&cache, &url, &GitReference::Tag("v1".to_string()), None).unwrap();
+        let head = checkout(&cache, &url, &GitReference::DefaultBranch, None).unwrap();
+        let locked = checkout(
+            &cache,
+            &url,
+            &GitReference::DefaultBranch,
+            Some(&first.to_string()),
+        ).unwrap();
+        let missing = checkout(&cache, &url, &GitReference::Branch("nope".to_string()), None);
+        let contents = (
+            read_file(&tagged.dir, "a.txt"),
+            read_file(&head.dir, "a.txt"),
+            read_file(&locked.dir, "a.txt"),
+        );
+
+        assert_eq!(tagged.commit, first.to_string());
+        assert_eq!(head.commit, second.to_string());
+        assert_eq!(locked.commit, first.to_string());
+        assert_eq!(
+            contents,
+            ("1".to_string(), "2".to_string(), "1".to_string())
+        );
+        assert!(missing.is_err());
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde_json;
+
+use pm_lib::constraint::VersionConstraint;
+use pm_lib::index::{Dependencies, Index};
+use pm_lib::package::PackageName;
+use pm_lib::version::Version;
+
+use lockfile::VersionSet;
+
+/// A package in the graph, or `None` for the project itself.
+pub type Node = Option<PackageName>;
+
+/// A chain of dependencies: packages, each with the constraint the previous
+/// one (or the project, for the first) imposed on it.
+pub type Chain = Vec<(PackageName, VersionConstraint)>;
+
+/// The resolved dependency graph: which release depends on which, and with
+/// what constraint.
+pub struct Graph {
+    /// The project's own dependencies.
+    pub dependencies: Dependencies,
+    pub versions: VersionSet,
+    /// The dependencies of each package in `versions`.
+    pub edges: BTreeMap<PackageName, Dependencies>,
+}
+
+impl Graph {
+    /// Build the graph of a resolution of `dependencies` against `index`,
+    /// which must contain every release in `versions`.
+    pub fn new(index: &Index, dependencies: &Dependencies, versions: &VersionSet) -> Graph {
+        let edges = versions
+            .iter()
+            .map(|(package, version)| {
+                let release = index
+                    .get(package)
+                    .and_then(|releases| releases.get(version))
+                    .expect("resolved release is in the index");
+                (package.clone(), release.clone())
+            }).collect();
+        Graph {
+            dependencies: dependencies.clone(),
+            versions: versions.clone(),
+            edges,
+        }
+    }
+
+    pub fn dependencies_of(&self, node: &Node) -> &Dependencies {
+        match node {
+            &None => &self.dependencies,
+            &Some(ref package) => &self.edges[package],
+        }
+    }
+
+    /// Everything depending on `package`, with the constraint it imposes.
+    pub fn dependents_of(&self, package: &PackageName) -> Vec<(Node, &VersionConstraint)> {
+        let project = self.dependencies.get(package).map(|d| (None, &d.constraint));
+        let packages = self.edges.iter().filter_map(|(dependent, dependencies)| {
+            dependencies
+                .get(package)
+                .map(|d| (Some(dependent.clone()), &d.constraint))
+        });
+        project.into_iter().chain(packages).collect()
+    }
+
+    /// Every chain of dependencies leading from the project to `package`. Each
+    /// chain lists the packages along it, starting with one of the project's
+    /// own dependencies, along with the constraint imposed on each by the
+    /// one before.
+    pub fn chains_to(&self, package: &PackageName) -> Vec<Chain> {
+        let mut chains = vec![];
+        self.collect_chains(package, &mut vec![], &mut chains);
+        chains
+    }
+
+    /// Add the chains leading to `package` and continuing with `tail`, which
+    /// is in reverse order, to `chains`.
+    fn collect_chains(&self, package: &PackageName, tail: &mut Chain, chains: &mut Vec<Chain>) {
+        for (dependent, constraint) in self.dependents_of(package) {
+            tail.push((package.clone(), constraint.clone()));
+            match dependent {
+                None => chains.push(tail.iter().rev().cloned().collect()),
+                Some(ref dependent) => {
+                    // Don't go round dependency cycles.
+                    if !tail.iter().any(|&(ref p, _)| p == dependent) {
+                        self.collect_chains(dependent, tail, chains);
+                    }
+                }
+            }
+            tail.pop();
+        }
+    }
+
+    pub fn label(&self, node: &Node) -> String {
+        match node {
+            &None => "(project)".to_string(),
+            &Some(ref package) => format!("{} {}", package, self.versions[package]),
+        }
+    }
+
+    /// Draw the graph as a tree, starting from the project's dependencies, or
+    /// from `invert` and following dependents instead. Packages which appear
+    /// more than once are only expanded the first time, and marked with `(*)`
+    /// after that. Nothing deeper than `depth` levels below the top is shown.
+    pub fn tree(&self, invert: Option<&PackageName>, depth: Option<usize>) -> String {
+        let mut writer = TreeWriter {
+            graph: self,
+            inverted: invert.is_some(),
+            depth,
+            expanded: HashSet::new(),
+            out: String::new(),
+        };
+        match invert {
+            Some(package) => writer.write(&Some(package.clone()), "", "", 0),
+            None => {
+                for package in self.dependencies.keys() {
+                    writer.write(&Some(package.clone()), "", "", 0);
+                }
+            }
+        }
+        writer.out
+    }
+
+    /// The graph in Graphviz format, with an edge for each dependency,
+    /// labelled with its constraint.
+    pub fn dot(&self) -> String {
+        let mut out = "digraph dependencies {\n".to_string();
+        let nodes = Some(None)
+            .into_iter()
+            .chain(self.versions.keys().map(|package| Some(package.clone())));
+        for node in nodes {
+            for (package, dependency) in self.dependencies_of(&node) {
+                out.push_str(&format!(
+                    "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                    self.label(&node),
+                    self.label(&Some(package.clone())),
+                    dependency.constraint
+                ));
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// The graph as JSON:
+    ///
+    /// ```json
+    /// {
+    ///   "dependencies": { "js/a": "^1" },
+    ///   "packages": {
+    ///     "js/a": { "version": "1.2.0", "dependencies": { "js/b": "^0.1" } },
+    ///     "js/b": { "version": "0.1.0", "dependencies": {} }
+    ///   }
+    /// }
+    /// ```
+    pub fn json(&self) -> Result<String, serde_json::Error> {
+        #[derive(Serialize)]
+        struct JsonGraph<'a> {
+            dependencies: &'a Dependencies,
+            packages: BTreeMap<&'a PackageName, JsonPackage<'a>>,
+        }
+        #[derive(Serialize)]
+        struct JsonPackage<'a> {
+            version: &'a Version,
+            dependencies: &'a Dependencies,
+        }
+        serde_json::to_string_pretty(&JsonGraph {
+            dependencies: &self.dependencies,
+            packages: self
+                .versions
+                .iter()
+                .map(|(package, version)| {
+                    (
+                        package,
+                        JsonPackage {
+                            version,
+                            dependencies: &self.edges[package],
+                        },
+                    )
+                }).collect(),
+        })
+    }
+}
+
+struct TreeWriter<'a> {
+    graph: &'a Graph,
+    inverted: bool,
+    depth: Option<usize>,
+    expanded: HashSet<Node>,
+    out: String,
+}
+
+impl<'a> TreeWriter<'a> {
+    /// Write `node` on a line starting with `first_prefix`, and its children
+    /// below it, indented by `prefix`.
+    fn write(&mut self, node: &Node, first_prefix: &str, prefix: &str, level: usize) {
+        let children: Vec<Node> = if self.inverted {
+            match node {
+                &Some(ref package) => self
+                    .graph
+                    .dependents_of(package)
+                    .into_iter()
+                    .map(|(dependent, _)| dependent)
+                    .collect(),
+                &None => vec![],
+            }
+        } else {
+            self.graph
+                .dependencies_of(node)
+                .keys()
+                .map(|package| Some(package.clone()))
+                .collect()
+        };
+        let collapsed = !children.is_empty() && !self.expanded.insert(node.clone());
+        self.out.push_str(&format!(
+            "{}{}{}\n",
+            first_prefix,
+            self.graph.label(node),
+            if collapsed { " (*)" } else { "" }
+        ));
+        if collapsed || self.depth.map_or(false, |depth| level >= depth) {
+            return;
+        }
+        for (i, child) in children.iter().enumerate() {
+            let (branch, indent) = if i + 1 == children.len() {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            self.write(
+                child,
+                &format!("{}{}", prefix, branch),
+                &format!("{}{}", prefix, indent),
+                level + 1,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pm_lib::test_helpers::{pkg, ver};
+
+    fn sample_graph() -> Graph {
+        let index = gen_registry!(
+            a => (
+                "1.0.0" => deps!(b => "^1", c => "^2")
+            ),
+            b => (
+                "1.1.0" => deps!(c => "^2")
+            ),
+            c => (
+                "2.0.0" => deps!()
+            )
+        );
+        let versions = vec![
+            (pkg("a"), ver("1.0.0")),
+            (pkg("b"), ver("1.1.0")),
+            (pkg("c"), ver("2.0.0")),
+        ].into_iter()
+            .collect();
+        Graph::new(&index, &deps!(a => "^1", c => "^2"), &versions)
+    }
+
+    #[test]
+    fn finds_chains() {
+        let graph = sample_graph();
+        let constraint = |s: &str| VersionConstraint::from_str(s).unwrap();
+        assert_eq!(
+            graph.chains_to(&pkg("c")),
+            vec![
+                vec![(pkg("c"), constraint("^2"))],
+                vec![(pkg("a"), constraint("^1")), (pkg("c"), constraint("^2"))],
+                vec![
+                    (pkg("a"), constraint("^1")),
+                    (pkg("b"), constraint("^1")),
+                    (pkg("c"), constraint("^2")),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn draws_trees() {
+        let graph = sample_graph();
+        assert_eq!(
+            graph.tree(None, None),
+            "test/a 1.0.0
+├── test/b 1.1.0
+│   └── test/c 2.0.0
+└── test/c 2.0.0
+test/c 2.0.0
+"
+        );
+        assert_eq!(graph.tree(None, Some(0)), "test/a 1.0.0\ntest/c 2.0.0\n");
+        assert_eq!(
+            graph.tree(Some(&pkg("c")), None),
+            "test/c 2.0.0
+├── (project)
+├── test/a 1.0.0
+│   └── (project)
+└── test/b 1.1.0
+    └── test/a 1.0.0 (*)
+"
+        );
+    }
+}
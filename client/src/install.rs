@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::fs::{copy, create_dir_all, remove_dir_all};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use brotli;
+use failure;
+use tar;
+
+use pm_lib::package::PackageName;
+use pm_lib::version::Version;
+
+use lockfile::VersionSet;
+use registry::{self, Registry};
+use resolve::{Origin, Origins};
+
+/// The directory in the project that packages are installed into, as
+/// `<namespace>/<name>`.
+pub const PACKAGES_DIR: &'static str = "pm_packages";
+
+pub fn package_dir(project_dir: &Path, package: &PackageName) -> PathBuf {
+    project_dir
+        .join(PACKAGES_DIR)
+        .join(&package.namespace)
+        .join(&package.name)
+}
+
+/// Install every package in `versions` into the project, replacing whatever
+/// was installed before. Registry packages are downloaded and unpacked, and
+/// path dependencies are linked to, so changes to them are picked up without
+/// reinstalling. Git dependencies get the files a release of them would
+/// contain.
+pub fn install(
+    project_dir: &Path,
+    versions: &VersionSet,
+    origins: &Origins,
+    quiet: bool,
+) -> Result<(), failure::Error> {
+    let packages_dir = project_dir.join(PACKAGES_DIR);
+    if packages_dir.exists() {
+        remove_dir_all(&packages_dir)?;
+    }
+    let mut registries = HashMap::new();
+    for (package, version) in versions {
+        let target = package_dir(project_dir, package);
+        create_dir_all(target.parent().expect("package dir has a parent"))?;
+        match origins.get(package) {
+            Some(&Origin::Path(ref dir)) => {
+                if !quiet {
+                    println!("    {} {} (linked to {})", package, version, dir.display());
+                }
+                link_dir(dir, &target)?;
+            }
+            Some(&Origin::Git {
+                ref url,
+                ref commit,
+                ref dir,
+                ref files,
+                ..
+            }) => {
+                if !quiet {
+                    println!("    {} {} ({} at {})", package, version, url, &commit[..7]);
+                }
+                for file in files {
+                    let file_target = target.join(file);
+                    create_dir_all(file_target.parent().expect("file has a parent"))?;
+                    copy(dir.join(file), file_target)?;
+                }
+            }
+            Some(&Origin::Registry(ref name)) => {
+                if !quiet {
+                    println!("    {} {}", package, version);
+                }
+                if !registries.contains_key(name) {
+                    let registry = Registry::from_config(name)?;
+                    registries.insert(name.clone(), registry);
+                }
+                install_release(&registries[name], package, version, &target)?;
+            }
+            None => return Err(format_err!("Don't know where to install {} from", package)),
+        }
+    }
+    Ok(())
+}
+
+/// Download a release artifact, a Brotli compressed tarball, and unpack it
+/// into `target`.
+fn install_release(
+    registry: &Registry,
+    package: &PackageName,
+    version: &Version,
+    target: &Path,
+) -> Result<(), failure::Error> {
+    let artifact = registry::download(
+        registry,
+        &format!("files/{}/{}/{}", package.namespace, package.name, version),
+    )?;
+    create_dir_all(target)?;
+    tar::Archive::new(brotli::Decompressor::new(artifact, 4096)).unpack(target)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn link_dir(source: &Path, target: &Path) -> io::Result<()> {
+    ::std::os::unix::fs::symlink(source, target)
+}
+
+#[cfg(windows)]
+fn link_dir(source: &Path, target: &Path) -> io::Result<()> {
+    ::std::os::windows::fs::symlink_dir(source, target)
+}
@@ -1,7 +1,49 @@
-use manifest::VersionSet;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 
+use failure;
+use serde_json;
+
+use pm_lib::index::TargetSet;
+use pm_lib::package::PackageName;
+use pm_lib::version::Version;
+use pm_manifest::manifest::GitReference;
+
+pub type VersionSet = BTreeMap<PackageName, Version>;
+
+/// Where a package was installed from: the commit of a Git package, whose
+/// version alone doesn't pin down its contents, the directory of a package
+/// overridden with a path, or the registry of any other package, so that
+/// relocking can't quietly take it from a different registry.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
-pub struct Lockfile(LockfileVersion, VersionSet);
+#[serde(rename_all = "lowercase")]
+pub enum LockedSource {
+    Git {
+        url: String,
+        reference: GitReference,
+        commit: String,
+    },
+    Path {
+        path: PathBuf,
+    },
+    Registry {
+        registry: Option<String>,
+    },
+}
+
+pub type LockedSources = BTreeMap<PackageName, LockedSource>;
+
+/// The versions and sources of every package, and the targets they were
+/// resolved for. The targets are left out when they're all of them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Lockfile(
+    LockfileVersion,
+    VersionSet,
+    #[serde(default)] LockedSources,
+    #[serde(default, skip_serializing_if = "TargetSet::is_all")] TargetSet,
+);
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum LockfileVersion {
@@ -9,10 +51,53 @@ pub enum LockfileVersion {
     Zero, // the only variant
 }
 
+impl Lockfile {
+    pub fn new(versions: VersionSet, sources: LockedSources, targets: TargetSet) -> Lockfile {
+        Lockfile(LockfileVersion::Zero, versions, sources, targets)
+    }
+
+    pub fn versions(&self) -> &VersionSet {
+        &self.1
+    }
+
+    pub fn get(&self, package: &PackageName) -> Option<&Version> {
+        self.1.get(package)
+    }
+
+    pub fn source(&self, package: &PackageName) -> Option<&LockedSource> {
+        self.2.get(package)
+    }
+
+    pub fn targets(&self) -> &TargetSet {
+        &self.3
+    }
+}
+
+pub fn lockfile_path(project_dir: &Path) -> PathBuf {
+    project_dir.join("manifest.lock")
+}
+
+pub fn read_lockfile(project_dir: &Path) -> Result<Option<Lockfile>, failure::Error> {
+    let path = lockfile_path(project_dir);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut data = String::new();
+    File::open(path)?.read_to_string(&mut data)?;
+    Ok(Some(serde_json::from_str(&data)?))
+}
+
+pub fn write_lockfile(project_dir: &Path, lockfile: &Lockfile) -> Result<(), failure::Error> {
+    let mut file = File::create(lockfile_path(project_dir))?;
+    file.write_all(serde_json::to_string_pretty(lockfile)?.as_bytes())?;
+    file.write_all(b"\n")?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use test_helpers::*;
+    use pm_lib::test_helpers::*;
 
     #[test]
     fn serialize() {
@@ -21,14 +106,51 @@ mod test {
             vec![(pkg("y"), ver("1.0.0")), (pkg("x"), ver("2.0.0"))]
                 .into_iter()
                 .collect(),
+            LockedSources::new(),
+            TargetSet::All,
         );
         let json = r#"[
   "0.0.0",
   {
     "test/x": "2.0.0",
     "test/y": "1.0.0"
-  }
+  },
+  {}
 ]"#;
         assert_eq!(::serde_json::to_string_pretty(lockfile).unwrap(), json);
     }
+
+    #[test]
+    fn git_sources() {
+        let lockfile = Lockfile::new(
+            vec![(pkg("x"), ver("2.0.0"))].into_iter().collect(),
+            vec![(
+                pkg("x"),
+                LockedSource::Git {
+                    url: "file:///src/x".to_string(),
+                    reference: GitReference::Branch("master".to_string()),
+                    commit: "0123456789abcdef0123456789abcdef01234567".to_string(),
+                },
+            )].into_iter()
+            .collect(),
+            TargetSet::All,
+        );
+        let json = r#"["0.0.0",{"test/x":"2.0.0"},{"test/x":{"git":{"url":"file:///src/x","reference":{"branch":"master"},"commit":"0123456789abcdef0123456789abcdef01234567"}}}]"#;
+        assert_eq!(::serde_json::to_string(&lockfile).unwrap(), json);
+        assert_eq!(::serde_json::from_str::<Lockfile>(json).unwrap(), lockfile);
+
+        // Lockfiles without sources still read fine.
+        let old: Lockfile = ::serde_json::from_str(r#"["0.0.0",{"test/x":"2.0.0"}]"#).unwrap();
+        assert_eq!(old.source(&pkg("x")), None);
+        assert_eq!(old.targets(), &TargetSet::All);
+    }
+
+    #[test]
+    fn targets() {
+        let windows = TargetSet::Only(vec!["windows".to_string()].into_iter().collect());
+        let lockfile = Lockfile::new(VersionSet::new(), LockedSources::new(), windows.clone());
+        let json = r#"["0.0.0",{},{},{"only":["windows"]}]"#;
+        assert_eq!(::serde_json::to_string(&lockfile).unwrap(), json);
+        assert_eq!(::serde_json::from_str::<Lockfile>(json).unwrap().targets(), &windows);
+    }
 }
@@ -4,13 +4,12 @@
 extern crate dirs;
 #[macro_use]
 extern crate failure;
-#[macro_use]
-extern crate failure_derive;
 extern crate docopt;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate pm_lib;
+extern crate pm_manifest;
 extern crate rmp_serde;
 extern crate serde_json;
 extern crate toml;
@@ -21,27 +20,19 @@ extern crate im;
 extern crate data_encoding;
 extern crate futures;
 extern crate hyper;
-extern crate pest;
 extern crate rand;
 extern crate reqwest;
 extern crate url;
 extern crate webbrowser;
-#[macro_use]
-extern crate pest_derive;
 extern crate brotli;
 extern crate console;
 extern crate git2;
-extern crate glob;
 extern crate indicatif;
 extern crate tar;
 #[cfg(test)]
 extern crate test;
-#[cfg(test)]
-#[macro_use]
-extern crate matches;
 
 mod config;
-mod manifest;
 mod path;
 mod project;
 mod registry;
@@ -49,12 +40,17 @@ mod registry;
 // TODO please remove this when the solver is actually being used
 #[macro_use]
 mod solver;
-mod files;
 mod git;
 mod io;
-#[allow(dead_code)]
-mod manifest_parser;
-mod manifest_parser_error;
+mod manifest_edit;
+mod lockfile;
+mod resolve;
+mod install;
+mod graph;
+mod outdated;
+mod table;
+#[cfg(test)]
+mod test_helpers;
 
 use docopt::Docopt;
 use serde::de::Deserialize;
@@ -85,6 +81,19 @@ macro_rules! each_subcommand {
         $mac!(test);
         $mac!(search);
         $mac!(publish);
+        $mac!(lock);
+        $mac!(yank);
+        $mac!(owner);
+        $mac!(namespace);
+        $mac!(token);
+        $mac!(install);
+        $mac!(tree);
+        $mac!(why);
+        $mac!(outdated);
+        $mac!(add);
+        $mac!(remove);
+        $mac!(fmt);
+        $mac!(check);
     };
 }
 
@@ -126,7 +135,6 @@ fn run_shell_command(cmd: &str, args: &Vec<String>) -> Result {
 
 
 fn main() {
-    // manifest::test_reader();
     // git::test_git();
     // process::exit(0);
 
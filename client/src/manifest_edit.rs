@@ -0,0 +1,301 @@
+use failure;
+
+use pm_lib::package::PackageName;
+use pm_manifest::manifest::{get_dependencies, parse_and_check_manifest};
+use pm_manifest::manifest_cst::Node;
+use pm_manifest::manifest_parser::Rule;
+
+/// Add a dependency on `package` with the version `constraint`, and `options`
+/// if given, to the manifest in `manifest_source`. If it's already a
+/// dependency, its constraint is changed, and its options are only replaced
+/// if new ones are given. Everything else in the manifest is left as it was.
+///
+/// New dependencies go before the first existing one which sorts after them,
+/// so sorted dependencies stay sorted, and are indented like the others.
+pub fn add_dependency(
+    manifest_source: String,
+    package: &PackageName,
+    constraint: &str,
+    options: Option<&str>,
+) -> Result<String, failure::Error> {
+    check(manifest_source.clone())?;
+    let mut manifest = Node::parse(manifest_source)?;
+    let newline = manifest
+        .find(Rule::newline)
+        .map_or("\n".to_string(), |newline| newline.to_string());
+    let name = package.to_string();
+    let line = match options {
+        Some(options) => format!("{} {} {}", name, constraint, options),
+        None => format!("{} {}", name, constraint),
+    };
+
+    let has_dependencies = find_field(top_fields(&mut manifest).children(), "dependencies");
+    if has_dependencies.is_some() {
+        let entries = dependency_entries(&mut manifest);
+        let existing = find_field(entries.children(), &name);
+        match existing {
+            Some(position) => {
+                set_arguments(&mut entries.children_mut()[position], constraint, options)
+            }
+            None => insert_entry(entries, &name, &line, &newline),
+        }
+    } else {
+        let fields = top_fields(&mut manifest);
+        let mut block = String::new();
+        let text = fields.to_string();
+        if !text.trim().is_empty() {
+            if !text.ends_with('\n') {
+                block.push_str(&newline);
+            }
+            block.push_str(&newline);
+        }
+        block.push_str(&format!(
+            "dependencies {{{0}    {1}{0}}}{0}",
+            newline, line
+        ));
+        fields.children_mut().push(Node::Text(block));
+    }
+
+    let edited = manifest.to_string();
+    check(edited.clone())?;
+    Ok(edited)
+}
+
+/// Remove the dependency on `package` from the manifest in `manifest_source`,
+/// along with any comment on the same line.
+pub fn remove_dependency(
+    manifest_source: String,
+    package: &PackageName,
+) -> Result<String, failure::Error> {
+    check(manifest_source.clone())?;
+    let mut manifest = Node::parse(manifest_source)?;
+    let name = package.to_string();
+    let not_found = || format_err!("{} is not a dependency", name);
+
+    find_field(top_fields(&mut manifest).children(), "dependencies").ok_or_else(&not_found)?;
+    {
+        let entries = dependency_entries(&mut manifest);
+        let position = find_field(entries.children(), &name).ok_or_else(&not_found)?;
+        // Each entry is followed by the newline ending its line.
+        entries.children_mut().drain(position..position + 2);
+    }
+    Ok(manifest.to_string())
+}
+
+/// Check that the manifest parses and has valid dependencies, so the tree is
+/// shaped the way the edits expect, and that edits leave it valid.
+fn check(manifest_source: String) -> Result<(), failure::Error> {
+    get_dependencies(parse_and_check_manifest(manifest_source)?)?;
+    Ok(())
+}
+
+fn top_fields(manifest: &mut Node) -> &mut Node {
+    manifest
+        .child_mut(Rule::fields_not_newline_terminated)
+        .expect("manifests consist of fields")
+}
+
+/// The node holding the entries of the `dependencies` block, which must exist.
+fn dependency_entries(manifest: &mut Node) -> &mut Node {
+    let fields = top_fields(manifest);
+    let position = find_field(fields.children(), "dependencies").expect("has dependencies");
+    fields.children_mut()[position]
+        .child_mut(Rule::arguments)
+        .and_then(|arguments| arguments.child_mut(Rule::block))
+        .and_then(|block| block.child_mut(Rule::fields_newline_terminated))
+        .expect("validated block presence")
+}
+
+fn field_name(field: &Node) -> String {
+    field
+        .child(Rule::symbol)
+        .expect("fields have a symbol")
+        .to_string()
+}
+
+/// The position of the field called `name` among `nodes`.
+fn find_field(nodes: &[Node], name: &str) -> Option<usize> {
+    nodes
+        .iter()
+        .position(|node| node.rule() == Some(Rule::field) && field_name(node) == name)
+}
+
+/// Replace the positional arguments of `field` with `constraint`, and its
+/// options with `options` if given, keeping any comment after them.
+fn set_arguments(field: &mut Node, constraint: &str, options: Option<&str>) {
+    let children = field.children_mut();
+    let position = children
+        .iter()
+        .position(|child| child.rule() == Some(Rule::arguments))
+        .expect("fields have arguments");
+    if children[position - 1].to_string().is_empty() {
+        // There were no arguments, so nothing separated them from the name.
+        children[position - 1] = Node::Text(" ".to_string());
+    }
+    let arguments = &mut children[position];
+    let old = arguments.to_string();
+    let trailing_whitespace = old[old.trim_right().len()..].to_string();
+    let options = match options {
+        Some(options) => options.to_string(),
+        None => arguments
+            .child(Rule::options)
+            .expect("arguments have options")
+            .to_string()
+            .trim_right()
+            .to_string(),
+    };
+    let (positional_text, options_text) = if options.is_empty() {
+        (format!("{}{}", constraint, trailing_whitespace), options)
+    } else {
+        (format!("{} ", constraint), format!("{}{}", options, trailing_whitespace))
+    };
+    *arguments
+        .child_mut(Rule::positional_arguments)
+        .expect("arguments have positional arguments") =
+        Node::Rule(Rule::positional_arguments, vec![Node::Text(positional_text)]);
+    *arguments.child_mut(Rule::options).expect("arguments have options") =
+        Node::Rule(Rule::options, vec![Node::Text(options_text)]);
+}
+
+/// Add `line` to the block `entries`, before the first entry which sorts after
+/// `name` (and any comment lines directly above it), or else after the last
+/// entry.
+fn insert_entry(entries: &mut Node, name: &str, line: &str, newline: &str) {
+    let position = {
+        let children = entries.children();
+        let is_field = |node: &Node| node.rule() == Some(Rule::field);
+        let later = children
+            .iter()
+            .position(|node| is_field(node) && field_name(node).as_str() > name);
+        match later {
+            Some(mut position) => {
+                let is_comment = |node: &Node| {
+                    node.rule() == Some(Rule::blank_line)
+                        && node.to_string().trim_left().starts_with("//")
+                };
+                while position >= 2 && is_comment(&children[position - 2]) {
+                    position -= 2;
+                }
+                position
+            }
+            None => children
+                .iter()
+                .rposition(|node| is_field(node))
+                .map_or(0, |last| last + 2),
+        }
+    };
+    let indent = entries
+        .child(Rule::field)
+        .map_or("    ".to_string(), |field| field.children()[0].to_string());
+    entries
+        .children_mut()
+        .insert(position, Node::Text(format!("{}{}{}", indent, line, newline)));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pm_lib::test_helpers::pkg;
+
+    const MANIFEST: &'static str = r#"// Our dependencies.
+dependencies {
+  // Padding.
+  test/left-pad ^1.0.0
+
+  // More padding.
+  test/right-pad ^2.0.0 registry="work"  // private fork
+}
+"#;
+
+    #[test]
+    fn adds_dependencies_in_order() {
+        assert_eq!(
+            add_dependency(MANIFEST.to_string(), &pkg("middle-pad"), "^0.3.1", None).unwrap(),
+            r#"// Our dependencies.
+dependencies {
+  // Padding.
+  test/left-pad ^1.0.0
+
+  test/middle-pad ^0.3.1
+  // More padding.
+  test/right-pad ^2.0.0 registry="work"  // private fork
+}
+"#
+        );
+        assert_eq!(
+            add_dependency(MANIFEST.to_string(), &pkg("up-pad"), "^1.0.0", None).unwrap(),
+            r#"// Our dependencies.
+dependencies {
+  // Padding.
+  test/left-pad ^1.0.0
+
+  // More padding.
+  test/right-pad ^2.0.0 registry="work"  // private fork
+  test/up-pad ^1.0.0
+}
+"#
+        );
+    }
+
+    #[test]
+    fn updates_existing_dependencies() {
+        // The options stay unless new ones are given.
+        assert_eq!(
+            add_dependency(MANIFEST.to_string(), &pkg("right-pad"), "^3.0.0", None).unwrap(),
+            r#"// Our dependencies.
+dependencies {
+  // Padding.
+  test/left-pad ^1.0.0
+
+  // More padding.
+  test/right-pad ^3.0.0 registry="work"  // private fork
+}
+"#
+        );
+        assert_eq!(
+            add_dependency(
+                MANIFEST.to_string(),
+                &pkg("right-pad"),
+                "^3.0.0",
+                Some("registry=\"home\""),
+            ).unwrap(),
+            r#"// Our dependencies.
+dependencies {
+  // Padding.
+  test/left-pad ^1.0.0
+
+  // More padding.
+  test/right-pad ^3.0.0 registry="home"  // private fork
+}
+"#
+        );
+        assert_eq!(
+            add_dependency(MANIFEST.to_string(), &pkg("left-pad"), "^1.1.0", None).unwrap(),
+            MANIFEST.replace("^1.0.0", "^1.1.0")
+        );
+    }
+
+    #[test]
+    fn adds_a_dependencies_block() {
+        assert_eq!(
+            add_dependency("pm \"1\"\n".to_string(), &pkg("left-pad"), "^1.0.0", None).unwrap(),
+            "pm \"1\"\n\ndependencies {\n    test/left-pad ^1.0.0\n}\n"
+        );
+    }
+
+    #[test]
+    fn removes_dependencies() {
+        assert_eq!(
+            remove_dependency(MANIFEST.to_string(), &pkg("right-pad")).unwrap(),
+            r#"// Our dependencies.
+dependencies {
+  // Padding.
+  test/left-pad ^1.0.0
+
+  // More padding.
+}
+"#
+        );
+        assert!(remove_dependency(MANIFEST.to_string(), &pkg("up-pad")).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use pm_lib::index::Releases;
+use pm_lib::package::PackageName;
+use pm_lib::version::Version;
+
+use graph::Graph;
+
+/// How a locked package compares to the releases available for it.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct Outdated {
+    pub package: PackageName,
+    pub locked: Version,
+    /// The newest release allowed by every constraint on the package, which
+    /// `pm lock` would pick if the lockfile didn't hold it back.
+    pub compatible: Option<Version>,
+    /// The newest release of all.
+    pub latest: Option<Version>,
+    /// Whether the locked release has been yanked.
+    pub yanked: bool,
+    /// Whether the locked release has been deprecated.
+    pub deprecated: bool,
+}
+
+impl Outdated {
+    /// Whether there's anything to report about the package.
+    pub fn needs_attention(&self) -> bool {
+        self.yanked
+            || self.deprecated
+            || self.compatible.as_ref() != Some(&self.locked)
+            || self.latest.as_ref() != Some(&self.locked)
+    }
+}
+
+/// Compare the locked version of `package` in `graph` with its `releases`.
+/// Yanked releases and, unless the locked version is one, prereleases, are
+/// never suggested.
+pub fn check(graph: &Graph, package: &PackageName, releases: &Releases) -> Outdated {
+    let locked = graph.versions[package].clone();
+    let constraints = graph.dependents_of(package);
+    let candidates = releases.iter().filter(|&(version, release)| {
+        !release.yanked && (!version.has_pre() || locked.has_pre())
+    });
+    let latest = candidates.clone().map(|(version, _)| version).max().cloned();
+    let compatible = candidates
+        .map(|(version, _)| version)
+        .filter(|version| {
+            constraints
+                .iter()
+                .all(|&(_, constraint)| constraint.contains(version))
+        }).max()
+        .cloned();
+    let locked_release = releases.get(&locked);
+    Outdated {
+        package: package.clone(),
+        compatible,
+        latest,
+        yanked: locked_release.map_or(false, |release| release.yanked),
+        deprecated: locked_release.map_or(false, |release| release.deprecated),
+        locked,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pm_lib::index::Release;
+    use pm_lib::test_helpers::{pkg, ver};
+
+    #[test]
+    fn finds_newer_releases() {
+        let index = gen_registry!(
+            a => (
+                "1.0.0" => deps!()
+            )
+        );
+        let versions = vec![(pkg("a"), ver("1.0.0"))].into_iter().collect();
+        let graph = Graph::new(&index, &deps!(a => "^1"), &versions);
+
+        let mut releases = Releases::new();
+        releases.insert(
+            ver("1.0.0"),
+            Release {
+                deprecated: true,
+                ..Release::default()
+            },
+        );
+        releases.insert(ver("1.1.0"), Release::default());
+        releases.insert(
+            ver("1.2.0"),
+            Release {
+                yanked: true,
+                ..Release::default()
+            },
+        );
+        releases.insert(ver("2.0.0"), Release::default());
+        releases.insert(ver("3.0.0-beta.1"), Release::default());
+
+        let outdated = check(&graph, &pkg("a"), &releases);
+        assert_eq!(
+            outdated,
+            Outdated {
+                package: pkg("a"),
+                locked: ver("1.0.0"),
+                compatible: Some(ver("1.1.0")),
+                latest: Some(ver("2.0.0")),
+                yanked: false,
+                deprecated: true,
+            }
+        );
+        assert!(outdated.needs_attention());
+    }
+}
@@ -6,7 +6,14 @@ use std::io::Read;
 use std::path::{Path, PathBuf};
 use failure;
 
-use manifest::Manifest;
+use pm_lib::constraint::VersionConstraint;
+use pm_lib::index::{Dependencies, Dependency};
+use pm_manifest::manifest::{
+    get_dependencies, get_overrides, get_workspace_members, parse_and_check_manifest, Manifest,
+    Source, Sources,
+};
+
+use git::GitScm;
 
 fn find_manifest(path: &Path) -> Option<PathBuf> {
     let manifest = path.join("manifest");
@@ -22,18 +29,221 @@ pub fn find_manifest_path() -> Result<PathBuf, failure::Error> {
     find_manifest(&cwd).ok_or(format_err!("no project file found!"))
 }
 
-pub fn find_project_dir() -> Result<PathBuf, failure::Error> {
+/// The directory of the package the current directory is in.
+pub fn find_package_dir() -> Result<PathBuf, failure::Error> {
     let mut manifest_path = find_manifest_path()?;
     manifest_path.pop();
     Ok(manifest_path)
 }
 
+/// The directory holding the lockfile and installed packages: the root of the
+/// workspace if the current package is a member of one, otherwise the
+/// package's own directory.
+pub fn find_project_dir() -> Result<PathBuf, failure::Error> {
+    let package_dir = find_package_dir()?;
+    Ok(match find_workspace(&package_dir)? {
+        Some(workspace) => workspace.root,
+        None => package_dir,
+    })
+}
+
+/// Several packages in one repository, resolved and locked together.
+pub struct Workspace {
+    pub root: PathBuf,
+    /// The members' directories, relative to `root`.
+    pub members: Vec<PathBuf>,
+}
+
+impl Workspace {
+    pub fn member_dirs(&self) -> Vec<PathBuf> {
+        self.members.iter().map(|member| self.root.join(member)).collect()
+    }
+}
+
+fn read_workspace_members(manifest_path: &Path) -> Result<Option<Vec<PathBuf>>, failure::Error> {
+    let manifest_pair = parse_and_check_manifest(read_manifest_source(manifest_path)?)?;
+    Ok(get_workspace_members(manifest_pair)?)
+}
+
+/// Find the workspace that the package in `package_dir` belongs to: the
+/// nearest manifest at or above it with a `workspace` block that either is the
+/// package's own manifest or lists the package as a member. Manifests above
+/// the package which can't be read are passed over, as they may not belong to
+/// the project at all.
+pub fn find_workspace(package_dir: &Path) -> Result<Option<Workspace>, failure::Error> {
+    let package_dir = package_dir.canonicalize()?;
+    let mut dir = Some(package_dir.clone());
+    while let Some(candidate) = dir.take() {
+        if let Some(manifest_path) = find_manifest(&candidate) {
+            let root = manifest_path.parent().expect("manifest has a parent").to_path_buf();
+            let members = match read_workspace_members(&manifest_path) {
+                Ok(members) => members,
+                Err(_) if root != package_dir => None,
+                Err(error) => return Err(error),
+            };
+            if let Some(members) = members {
+                let workspace = Workspace {
+                    root: root.clone(),
+                    members,
+                };
+                if workspace.root == package_dir
+                    || workspace
+                        .member_dirs()
+                        .iter()
+                        .any(|member| member.canonicalize().ok().as_ref() == Some(&package_dir))
+                {
+                    return Ok(Some(workspace));
+                }
+            }
+            dir = root.parent().map(Path::to_path_buf);
+        }
+    }
+    Ok(None)
+}
+
+/// Find a member of the current workspace, given either its package name or
+/// its directory relative to the workspace root.
+pub fn find_member(member: &str) -> Result<PathBuf, failure::Error> {
+    let workspace = find_workspace(&find_package_dir()?)?
+        .ok_or_else(|| format_err!("Not in a workspace, so there is no member {}", member))?;
+    for (path, dir) in workspace.members.iter().zip(workspace.member_dirs()) {
+        if path == Path::new(member) || read_manifest_in(&dir)?.name.to_string() == member {
+            return Ok(dir);
+        }
+    }
+    Err(format_err!("The workspace has no member {}", member))
+}
+
+pub fn read_manifest_source(manifest_path: &Path) -> Result<String, failure::Error> {
+    Ok(File::open(manifest_path).and_then(|mut f| {
+        let mut s = String::new();
+        f.read_to_string(&mut s).map(|_| s)
+    })?)
+}
+
 pub fn read_manifest() -> Result<Manifest, failure::Error> {
     let manifest_path = find_manifest_path()?;
     let root = manifest_path.parent().unwrap_or(Path::new(&"."));
-    let data = File::open(manifest_path.clone()).and_then(|mut f| {
-        let mut s = String::new();
-        f.read_to_string(&mut s).map(|_| s)
-    })?;
-    Ok(Manifest::from_str(data, root)?)
+    let data = read_manifest_source(&manifest_path)?;
+    Ok(Manifest::from_str(data, root, &GitScm)?)
+}
+
+/// Read the manifest of the package in `dir`, such as a path dependency.
+pub fn read_manifest_in(dir: &Path) -> Result<Manifest, failure::Error> {
+    let data = read_manifest_source(&dir.join("manifest"))?;
+    Ok(Manifest::from_str(data, dir, &GitScm)?)
+}
+
+/// What the project's manifest asks to have installed.
+pub struct Requirements {
+    pub dependencies: Dependencies,
+    /// Where each of the `dependencies` comes from.
+    pub sources: Sources,
+    /// Packages to take from somewhere else, wherever they appear in the
+    /// dependency graph.
+    pub overrides: Sources,
+}
+
+/// Read only the dependencies of the project, and where they come from. Unlike
+/// `read_manifest`, this does not require a `package` section.
+///
+/// In a workspace, these are the dependencies of the whole workspace.
+pub fn read_dependencies() -> Result<Requirements, failure::Error> {
+    let package_dir = find_package_dir()?;
+    match find_workspace(&package_dir)? {
+        Some(workspace) => read_workspace_requirements(&workspace),
+        None => read_requirements(&package_dir),
+    }
+}
+
+/// The workspace root's own requirements, plus every member. Members are
+/// depended on from their directories, and override any other dependency on
+/// them, so that the members' dependencies are resolved together and members
+/// depending on each other get the local copy.
+pub fn read_workspace_requirements(workspace: &Workspace) -> Result<Requirements, failure::Error> {
+    let mut requirements = read_requirements(&workspace.root)?;
+    for (path, dir) in workspace.members.iter().zip(workspace.member_dirs()) {
+        let name = read_manifest_in(&dir)?.name;
+        let source = Source::Path(path.clone());
+        requirements
+            .dependencies
+            .insert(name.clone(), Dependency::new(VersionConstraint::Range(None, None)));
+        requirements.sources.insert(name.clone(), source.clone());
+        requirements.overrides.entry(name).or_insert(source);
+    }
+    Ok(requirements)
+}
+
+fn read_requirements(dir: &Path) -> Result<Requirements, failure::Error> {
+    let data = read_manifest_source(&dir.join("manifest"))?;
+    let manifest_pair = parse_and_check_manifest(data)?;
+    let (dependencies, sources) = get_dependencies(manifest_pair.clone())?;
+    Ok(Requirements {
+        dependencies,
+        sources,
+        overrides: get_overrides(manifest_pair)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lockfile::LockedSource;
+    use pm_lib::index::TargetSet;
+    use pm_lib::test_helpers::{pkg, ver, TempDir};
+    use resolve::{resolve_project, Origin, Resolution};
+    use std::io::Write;
+    use test_helpers::write_package;
+
+    fn write_manifest(dir: &Path, source: &str) {
+        File::create(dir.join("manifest"))
+            .unwrap()
+            .write_all(source.as_bytes())
+            .unwrap();
+    }
+
+    #[test]
+    fn workspace_members_resolve_locally() {
+        let temp = TempDir::new();
+        let root = temp.path();
+        write_manifest(root, "workspace {\n    members [\"a\" \"b\"]\n}\n");
+        write_package(&root.join("a"), "test/a", "1.2.0", r#"test/b "^0.1""#);
+        write_package(&root.join("b"), "test/b", "0.1.0", "");
+        let b_dir = root.join("b").canonicalize().unwrap();
+
+        let workspace = find_workspace(&root.join("a"))
+            .unwrap()
+            .expect("a is a workspace member");
+        let requirements = read_workspace_requirements(&workspace).unwrap();
+        let Resolution {
+            lockfile, origins, ..
+        } = resolve_project(&workspace.root, &requirements, &None, None, &TargetSet::All).unwrap();
+
+        assert_eq!(
+            lockfile.versions(),
+            &vec![(pkg("a"), ver("1.2.0")), (pkg("b"), ver("0.1.0"))]
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(origins.get(&pkg("b")), Some(&Origin::Path(b_dir)));
+        assert_eq!(
+            lockfile.source(&pkg("b")),
+            Some(&LockedSource::Path {
+                path: PathBuf::from("b"),
+            })
+        );
+    }
+
+    #[test]
+    fn passes_over_broken_manifests_above_the_package() {
+        let temp = TempDir::new();
+        let root = temp.path();
+        write_manifest(root, "package {\n");
+        write_package(&root.join("a"), "test/a", "1.2.0", "");
+        assert!(find_workspace(&root.join("a")).unwrap().is_none());
+
+        // The package's own manifest still has to be readable.
+        write_manifest(&root.join("a"), "package {\n");
+        assert!(find_workspace(&root.join("a")).is_err());
+    }
 }
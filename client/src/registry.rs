@@ -13,6 +13,17 @@ use config::get_config;
 #[derive(Deserialize)]
 pub struct RegistryError {
     message: String,
+    /// The HTTP status the registry responded with.
+    #[serde(skip)]
+    status: u16,
+}
+
+impl RegistryError {
+    /// Whether the registry doesn't have what was asked for, as opposed to
+    /// failing to look it up.
+    pub fn is_not_found(&self) -> bool {
+        self.status == 404
+    }
 }
 
 impl fmt::Display for RegistryError {
@@ -23,12 +34,55 @@ impl fmt::Display for RegistryError {
 
 pub type Response<A> = Result<A, RegistryError>;
 
-fn read_auth() -> Result<String, failure::Error> {
-    let config = get_config()?;
-    config.auth.token.ok_or(format_err!("Please log in first using `pm login`."))
+/// The body of a successful response to a request which returns no data.
+#[derive(Deserialize)]
+#[allow(dead_code)]
+pub struct Receipt {
+    pub ok: bool,
+}
+
+/// A registry to talk to, as configured in the user's config.
+pub struct Registry {
+    name: Option<String>,
+    url: String,
+    token: Option<String>,
+}
+
+impl Registry {
+    /// Look up the registry called `name` in the config, or the default
+    /// registry if `name` is `None`.
+    pub fn from_config(name: &Option<String>) -> Result<Registry, failure::Error> {
+        let (url, token) = get_config()?.registry(name)?;
+        Ok(Registry {
+            name: name.clone(),
+            url: url.trim_right_matches('/').to_string(),
+            token,
+        })
+    }
+
+    pub fn name(&self) -> &Option<String> {
+        &self.name
+    }
+
+    /// The full URL of `path` on this registry.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.url, path)
+    }
+
+    fn read_auth(&self) -> Result<String, failure::Error> {
+        self.token.clone().ok_or_else(|| match self.name {
+            None => format_err!("Please log in first using `pm login`."),
+            Some(ref name) => format_err!(
+                "Please log in to registry `{}` first using `pm login --registry {}`.",
+                name,
+                name
+            ),
+        })
+    }
 }
 
 fn request<A, R>(
+    registry: &Registry,
     method: Method,
     url: &str,
     args: Map<String, String>,
@@ -48,41 +102,80 @@ where
     let http = reqwest::Client::new();
     let mut req = http.request(
         method,
-        &format!("http://localhost:8000/{}?{}", url, args_str),
+        &format!("{}?{}", registry.url(url), args_str),
     );
     if auth {
-        req.header(Authorization(format!("Bearer {}", read_auth()?)));
+        req.header(Authorization(format!("Bearer {}", registry.read_auth()?)));
     }
     if let Some(data) = body {
         req.body(Body::new(data));
     }
     let res = req.send()?;
+    let status = res.status();
 
-    if res.status().is_success() {
+    if status.is_success() {
         Ok(Ok(::serde_json::from_reader(res)?))
     } else {
-        Ok(Err(::serde_json::from_reader(res)?))
+        let mut error: RegistryError = ::serde_json::from_reader(res)
+            .map_err(|_| format_err!("The registry responded with {}", status))?;
+        error.status = status.as_u16();
+        Ok(Err(error))
     }
 }
 
-pub fn get<A>(url: &str, args: Map<String, String>) -> Result<Response<A>, failure::Error>
+pub fn get<A>(
+    registry: &Registry,
+    url: &str,
+    args: Map<String, String>,
+) -> Result<Response<A>, failure::Error>
 where
     for<'de> A: Deserialize<'de>,
 {
-    request::<A, &'static [u8]>(Method::Get, url, args, None, false)
+    request::<A, &'static [u8]>(registry, Method::Get, url, args, None, false)
 }
 
-pub fn get_auth<A>(url: &str, args: Map<String, String>) -> Result<Response<A>, failure::Error>
+pub fn get_auth<A>(
+    registry: &Registry,
+    url: &str,
+    args: Map<String, String>,
+) -> Result<Response<A>, failure::Error>
 where
     for<'de> A: Deserialize<'de>,
 {
-    request::<A, &'static [u8]>(Method::Get, url, args, None, true)
+    request::<A, &'static [u8]>(registry, Method::Get, url, args, None, true)
 }
 
-pub fn post<A, R>(url: &str, args: Map<String, String>, data: R) -> Result<Response<A>, failure::Error>
+pub fn post<A, R>(
+    registry: &Registry,
+    url: &str,
+    args: Map<String, String>,
+    data: R,
+) -> Result<Response<A>, failure::Error>
 where
     for<'de> A: Deserialize<'de>,
     R: Read + Send + 'static,
 {
-    request(Method::Post, url, args, Some(data), true)
+    request(registry, Method::Post, url, args, Some(data), true)
+}
+
+/// Download a file, such as a release artifact, rather than a JSON response.
+pub fn download(registry: &Registry, url: &str) -> Result<reqwest::Response, failure::Error> {
+    let res = reqwest::get(&registry.url(url))?;
+    if res.status().is_success() {
+        Ok(res)
+    } else {
+        Err(format_err!("Failed to download {}: {}", registry.url(url), res.status()))
+    }
+}
+
+/// POST without credentials, for requests made before the user has any.
+pub fn post_anon<A>(
+    registry: &Registry,
+    url: &str,
+    args: Map<String, String>,
+) -> Result<Response<A>, failure::Error>
+where
+    for<'de> A: Deserialize<'de>,
+{
+    request(registry, Method::Post, url, args, Some(&b""[..]), false)
 }
@@ -0,0 +1,632 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs::canonicalize;
+use std::path::{Path, PathBuf};
+
+use failure;
+
+use pm_lib::constraint::VersionConstraint;
+use pm_lib::index::{resolvable_releases, Dependencies, Dependency, Index, Releases, TargetSet};
+use pm_lib::package::PackageName;
+use pm_lib::version::Version;
+use pm_manifest::manifest::{GitReference, Manifest, Source, Sources};
+
+use git;
+use lockfile::{LockedSource, LockedSources, Lockfile, VersionSet};
+use project::{read_manifest_in, Requirements};
+use registry::{self, Registry};
+use solver::{self, Solution};
+
+/// Where a package in the index was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// The registry with the given name in the config, or the default registry.
+    Registry(Option<String>),
+    /// A local directory.
+    Path(PathBuf),
+    /// A commit of a Git repository, checked out in `dir`. `files` are the
+    /// files a release of it would contain.
+    Git {
+        url: String,
+        reference: GitReference,
+        commit: String,
+        dir: PathBuf,
+        files: Vec<String>,
+    },
+}
+
+pub type Origins = BTreeMap<PackageName, Origin>;
+
+/// The registry's full listing of each package found in a registry, yanked
+/// releases included.
+pub type RegistryReleases = HashMap<PackageName, Releases>;
+
+/// Collect the listing of every package reachable from the project's
+/// dependencies, returning it along with where each package was found and the
+/// registry listings it was built from. Yanked releases are left out of the
+/// index, unless `lockfile` pins them. Dependencies on every target are
+/// followed, so the index can be resolved for any of them afterwards.
+///
+/// Each dependency is looked up in the registry named by its `registry`
+/// option, or in `default_registry` otherwise. The dependencies of a package
+/// are looked up in the registry the package came from, falling back to
+/// `default_registry` if it doesn't have them, so packages in a private
+/// registry can depend on public ones. Dependencies with a `registry` option
+/// never fall back, and neither do packages which `lockfile` records as coming
+/// from a particular registry, so the registry a package comes from can't
+/// change behind the project's back. Any failure to look a package up other
+/// than the registry not having it is an error.
+///
+/// Path and Git dependencies are read from their manifest, and listed as a
+/// single release with the version and dependencies given there. Git
+/// dependencies stay at the commit in `lockfile`, if it has one.
+///
+/// Packages in the project's `overrides` are taken from the source given
+/// there instead, wherever they appear.
+pub fn fetch_index(
+    project_dir: &Path,
+    requirements: &Requirements,
+    default_registry: &Option<String>,
+    lockfile: Option<&Lockfile>,
+) -> Result<(Index, Origins, RegistryReleases), failure::Error> {
+    let mut registries = HashMap::new();
+    let mut index = Index::new();
+    let mut found = Origins::new();
+    let mut registry_releases = RegistryReleases::new();
+    let mut seen = HashSet::new();
+    let mut queue: VecDeque<(PackageName, Source, Fallback)> = locate_dependencies(
+        &requirements.dependencies,
+        &requirements.sources,
+        project_dir,
+        default_registry,
+    ).into_iter()
+    .collect();
+    while let Some((package, source, fallback)) = queue.pop_front() {
+        if !seen.insert(package.clone()) {
+            continue;
+        }
+        let overridden = requirements.overrides.get(&package);
+        let source = match overridden {
+            Some(source) => locate(Some(source), project_dir, default_registry),
+            None => source,
+        };
+        match source {
+            Source::Path(path) => {
+                let dir = canonicalize(&path).map_err(|e| {
+                    format_err!("Can't find {} in {}: {}", package, path.display(), e)
+                })?;
+                let manifest = read_local_manifest(&package, &dir)?;
+                queue.extend(locate_dependencies(
+                    &manifest.dependencies,
+                    &manifest.sources,
+                    &dir,
+                    default_registry,
+                ));
+                index.insert(
+                    package.clone(),
+                    vec![(manifest.version, manifest.dependencies)]
+                        .into_iter()
+                        .collect(),
+                );
+                found.insert(package, Origin::Path(dir));
+            }
+            Source::Git { url, reference } => {
+                let locked_commit = match lockfile.and_then(|l| l.source(&package)) {
+                    Some(&LockedSource::Git {
+                        url: ref locked_url,
+                        reference: ref locked_reference,
+                        ref commit,
+                    }) if *locked_url == url && *locked_reference == reference =>
+                    {
+                        Some(commit.clone())
+                    }
+                    _ => None,
+                };
+                let checkout = git::checkout(
+                    &git::cache_dir()?,
+                    &url,
+                    &reference,
+                    locked_commit.as_ref().map(|commit| commit.as_str()),
+                )?;
+                let manifest = read_local_manifest(&package, &checkout.dir)?;
+                queue.extend(locate_dependencies(
+                    &manifest.dependencies,
+                    &manifest.sources,
+                    &checkout.dir,
+                    default_registry,
+                ));
+                index.insert(
+                    package.clone(),
+                    vec![(manifest.version, manifest.dependencies)]
+                        .into_iter()
+                        .collect(),
+                );
+                found.insert(
+                    package,
+                    Origin::Git {
+                        url,
+                        reference,
+                        commit: checkout.commit,
+                        dir: checkout.dir,
+                        files: manifest.files,
+                    },
+                );
+            }
+            Source::Registry(registry_name) => {
+                let locked_registry = match lockfile.and_then(|l| l.source(&package)) {
+                    Some(&LockedSource::Registry { ref registry }) => Some(registry.clone()),
+                    _ => None,
+                };
+                // An overridden package has to come from where it's overridden
+                // to, but other packages may fall back to the default registry,
+                // unless they name their registry or are locked to one.
+                let candidates = match (overridden, fallback, locked_registry) {
+                    (None, Fallback::Allowed, Some(locked_registry)) => vec![locked_registry],
+                    (None, Fallback::Allowed, None) if registry_name != *default_registry => {
+                        vec![registry_name, default_registry.clone()]
+                    }
+                    _ => vec![registry_name],
+                };
+                let fetched = fetch_releases(&mut registries, &package, candidates)?;
+                let (releases, registry_name) = match fetched {
+                    Some(releases) => releases,
+                    // Leave the package out; the solver reports it as missing.
+                    None => continue,
+                };
+                let package_releases =
+                    resolvable_releases(&releases, lockfile.and_then(|l| l.get(&package)));
+                for dependencies in package_releases.values() {
+                    queue.extend(dependencies.keys().map(|dependency| {
+                        let source = Source::Registry(registry_name.clone());
+                        (dependency.clone(), source, Fallback::Allowed)
+                    }));
+                }
+                index.insert(package.clone(), package_releases);
+                registry_releases.insert(package.clone(), releases);
+                found.insert(package, Origin::Registry(registry_name));
+            }
+        }
+    }
+    Ok((index, found, registry_releases))
+}
+
+/// Read the manifest of a path or Git dependency, and check that it is the
+/// package we expect.
+fn read_local_manifest(package: &PackageName, dir: &Path) -> Result<Manifest, failure::Error> {
+    let manifest = read_manifest_in(dir).map_err(|e| {
+        format_err!(
+            "Can't read the manifest of {} in {}: {}",
+            package,
+            dir.display(),
+            e
+        )
+    })?;
+    if manifest.name != *package {
+        return Err(format_err!(
+            "Expected {} in {}, but found {}",
+            package,
+            dir.display(),
+            manifest.name
+        ));
+    }
+    Ok(manifest)
+}
+
+/// Whether a package may be taken from the default registry if the registry
+/// it's looked up in doesn't have it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fallback {
+    Allowed,
+    /// The dependency names its registry.
+    Forbidden,
+}
+
+/// Where to look for each of `deps`, as declared by the manifest in `dir`.
+fn locate_dependencies(
+    deps: &Dependencies,
+    sources: &Sources,
+    dir: &Path,
+    default_registry: &Option<String>,
+) -> Vec<(PackageName, Source, Fallback)> {
+    deps.keys()
+        .map(|package| {
+            let fallback = match sources.get(package) {
+                Some(&Source::Registry(Some(_))) => Fallback::Forbidden,
+                _ => Fallback::Allowed,
+            };
+            (
+                package.clone(),
+                locate(sources.get(package), dir, default_registry),
+                fallback,
+            )
+        }).collect()
+}
+
+/// Make `source`, as declared by the manifest in `dir`, independent of where it
+/// was declared.
+fn locate(source: Option<&Source>, dir: &Path, default_registry: &Option<String>) -> Source {
+    match source {
+        Some(&Source::Path(ref path)) => Source::Path(dir.join(path)),
+        Some(&Source::Registry(None)) | None => Source::Registry(default_registry.clone()),
+        Some(source) => source.clone(),
+    }
+}
+
+/// Look `package` up in each of the registries named by `candidates` in turn,
+/// moving on to the next only if a registry doesn't have it. Returns the
+/// releases and the name of the registry they were found in.
+pub fn fetch_releases(
+    registries: &mut HashMap<Option<String>, Registry>,
+    package: &PackageName,
+    candidates: Vec<Option<String>>,
+) -> Result<Option<(Releases, Option<String>)>, failure::Error> {
+    for name in candidates {
+        if !registries.contains_key(&name) {
+            let registry = Registry::from_config(&name)?;
+            registries.insert(name.clone(), registry);
+        }
+        let response = registry::get::<Releases>(
+            &registries[&name],
+            &format!("index/{}/{}", package.namespace, package.name),
+            ordmap!{},
+        )?;
+        match response {
+            Ok(releases) => return Ok(Some((releases, name))),
+            Err(ref error) if error.is_not_found() => continue,
+            Err(error) => {
+                return Err(format_err!(
+                    "Can't look up {} in {}: {}",
+                    package,
+                    registry_description(&name),
+                    error
+                ))
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn registry_description(name: &Option<String>) -> String {
+    match name {
+        &Some(ref name) => format!("registry `{}`", name),
+        &None => "the default registry".to_string(),
+    }
+}
+
+/// A resolved project, and what it was resolved against.
+pub struct Resolution {
+    pub lockfile: Lockfile,
+    /// Where each package in the lockfile comes from.
+    pub origins: Origins,
+    /// The releases the project was resolved against, with overrides applied,
+    /// and their dependencies on every target.
+    pub index: Index,
+    /// The project's own dependencies on every target, with overrides applied.
+    pub dependencies: Dependencies,
+    /// The solver's solution, which also records why each package was
+    /// picked.
+    pub solution: Solution,
+    /// The registry listings `index` was built from.
+    pub registry_releases: RegistryReleases,
+}
+
+impl Resolution {
+    /// The packages of the lockfile needed on `targets`, which must be among
+    /// those the project was resolved for.
+    pub fn versions_on(&self, targets: &TargetSet) -> Result<VersionSet, failure::Error> {
+        let solution = resolve(&self.index, &self.dependencies, Some(&self.lockfile), targets)?;
+        Ok(to_version_set(&solution))
+    }
+}
+
+/// The tags given to a `--target` option, separated by commas.
+pub fn target_tags(option: &str) -> Vec<String> {
+    option
+        .split(',')
+        .map(|tag| tag.trim())
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| tag.to_string())
+        .collect()
+}
+
+/// Resolve the project's dependencies on `targets`, keeping to `lockfile` where
+/// possible. Resolving for `TargetSet::All` gives a lockfile which holds on
+/// every target.
+pub fn resolve_project(
+    project_dir: &Path,
+    requirements: &Requirements,
+    default_registry: &Option<String>,
+    lockfile: Option<&Lockfile>,
+    targets: &TargetSet,
+) -> Result<Resolution, failure::Error> {
+    let (index, origins, registry_releases) =
+        fetch_index(project_dir, requirements, default_registry, lockfile)?;
+    let (index, dependencies) =
+        apply_overrides(&index, &requirements.dependencies, &requirements.overrides);
+    let solution = resolve(&index, &dependencies, lockfile, targets)?;
+    let versions = to_version_set(&solution);
+    let sources = locked_sources(&versions, &origins, &requirements.overrides);
+    Ok(Resolution {
+        lockfile: Lockfile::new(versions, sources, targets.clone()),
+        origins,
+        index,
+        dependencies,
+        solution,
+        registry_releases,
+    })
+}
+
+/// Resolve `deps` against `index` on `targets`. If there is a lockfile, we
+/// first try to keep every package at its locked version, and only if that
+/// fails (because the manifest changed) do we re-resolve from scratch.
+pub fn resolve(
+    index: &Index,
+    deps: &Dependencies,
+    lockfile: Option<&Lockfile>,
+    targets: &TargetSet,
+) -> Result<Solution, failure::Error> {
+    if let Some(lockfile) = lockfile {
+        if let Ok(solution) = solver::solve(&pin(index, lockfile), deps, targets) {
+            return Ok(solution);
+        }
+    }
+    Ok(solver::solve(index, deps, targets)?)
+}
+
+/// Restrict every package in `index` that has a version in `lockfile` to that
+/// version, as long as it is still available.
+fn pin(index: &Index, lockfile: &Lockfile) -> Index {
+    index
+        .iter()
+        .map(|(package, releases)| {
+            let pinned_releases = match lockfile.get(package) {
+                Some(version) if releases.contains_key(version) => releases
+                    .iter()
+                    .filter(|&(v, _)| v == version)
+                    .map(|(v, deps)| (v.clone(), deps.clone()))
+                    .collect(),
+                _ => releases.clone(),
+            };
+            (package.clone(), pinned_releases)
+        }).collect()
+}
+
+/// Let packages which are overridden with a path or Git source, which only have
+/// a single release, satisfy every constraint on them, so that the replacement
+/// is used throughout the dependency graph whatever its version.
+pub fn apply_overrides(
+    index: &Index,
+    deps: &Dependencies,
+    overrides: &Sources,
+) -> (Index, Dependencies) {
+    let any_version = |deps: &Dependencies| -> Dependencies {
+        deps.iter()
+            .map(|(package, dependency)| match overrides.get(package) {
+                Some(&Source::Path(_)) | Some(&Source::Git { .. }) => (
+                    package.clone(),
+                    Dependency {
+                        constraint: VersionConstraint::Range(None, None),
+                        targets: dependency.targets.clone(),
+                    },
+                ),
+                _ => (package.clone(), dependency.clone()),
+            }).collect()
+    };
+    let index = index
+        .iter()
+        .map(|(package, releases)| {
+            (
+                package.clone(),
+                releases
+                    .iter()
+                    .map(|(version, deps)| (version.clone(), any_version(deps)))
+                    .collect(),
+            )
+        }).collect();
+    (index, any_version(deps))
+}
+
+/// The sources to record in the lockfile for the packages in `versions`: the
+/// commits of Git packages, the sources of overridden packages, and the
+/// registry every other package came from.
+pub fn locked_sources(
+    versions: &VersionSet,
+    origins: &Origins,
+    overrides: &Sources,
+) -> LockedSources {
+    versions
+        .keys()
+        .filter_map(|package| {
+            let source = match (origins.get(package), overrides.get(package)) {
+                (
+                    Some(&Origin::Git {
+                        ref url,
+                        ref reference,
+                        ref commit,
+                        ..
+                    }),
+                    _,
+                ) => LockedSource::Git {
+                    url: url.clone(),
+                    reference: reference.clone(),
+                    commit: commit.clone(),
+                },
+                (_, Some(&Source::Path(ref path))) => LockedSource::Path { path: path.clone() },
+                (Some(&Origin::Registry(ref registry)), _) => LockedSource::Registry {
+                    registry: registry.clone(),
+                },
+                _ => return None,
+            };
+            Some((package.clone(), source))
+        }).collect()
+}
+
+pub fn to_version_set(solution: &Solution) -> VersionSet {
+    solution
+        .0
+        .iter()
+        .map(|(package, version)| (PackageName::clone(&package), Version::clone(&version)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pm_lib::test_helpers::{pkg, ver, TempDir};
+    use std::path::PathBuf;
+    use test_helpers::write_package;
+
+    #[test]
+    fn prefers_locked_versions() {
+        let index = gen_registry!(
+            A => (
+                "1.0.0" => deps!(),
+                "1.1.0" => deps!()
+            )
+        );
+        let deps = deps!(A => "^1");
+        let lockfile = Lockfile::new(
+            vec![(pkg("A"), ver("1.0.0"))].into_iter().collect(),
+            LockedSources::new(),
+            TargetSet::All,
+        );
+
+        assert_eq!(
+            to_version_set(&resolve(&index, &deps, None, &TargetSet::All).unwrap()),
+            vec![(pkg("A"), ver("1.1.0"))].into_iter().collect()
+        );
+        assert_eq!(
+            to_version_set(&resolve(&index, &deps, Some(&lockfile), &TargetSet::All).unwrap()),
+            vec![(pkg("A"), ver("1.0.0"))].into_iter().collect()
+        );
+
+        // When the manifest no longer allows the locked version, we re-resolve.
+        let deps = deps!(A => ">=1.1");
+        assert_eq!(
+            to_version_set(&resolve(&index, &deps, Some(&lockfile), &TargetSet::All).unwrap()),
+            vec![(pkg("A"), ver("1.1.0"))].into_iter().collect()
+        );
+    }
+
+    #[test]
+    fn path_dependencies() {
+        let temp = TempDir::new();
+        let root = temp.path();
+        write_package(&root.join("a"), "test/a", "1.2.0", r#"test/b path="../b""#);
+        write_package(&root.join("b"), "test/b", "0.1.0", "");
+        let b_dir = canonicalize(root.join("b")).unwrap();
+
+        let requirements = Requirements {
+            dependencies: deps!(a => "^1"),
+            sources: vec![(pkg("a"), Source::Path(PathBuf::from("a")))]
+                .into_iter()
+                .collect(),
+            overrides: Sources::new(),
+        };
+        let deps = requirements.dependencies.clone();
+        let (index, found, _) = fetch_index(&root, &requirements, &None, None).unwrap();
+
+        assert_eq!(
+            to_version_set(&resolve(&index, &deps, None, &TargetSet::All).unwrap()),
+            vec![(pkg("a"), ver("1.2.0")), (pkg("b"), ver("0.1.0"))]
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(found.get(&pkg("b")), Some(&Origin::Path(b_dir)));
+    }
+
+    #[test]
+    fn resolves_for_targets() {
+        let temp = TempDir::new();
+        let root = temp.path();
+        write_package(
+            &root.join("a"),
+            "test/a",
+            "1.0.0",
+            r#"test/b path="../b" target="windows""#,
+        );
+        write_package(&root.join("b"), "test/b", "0.1.0", "");
+
+        let requirements = Requirements {
+            dependencies: deps!(a => "^1"),
+            sources: vec![(pkg("a"), Source::Path(PathBuf::from("a")))]
+                .into_iter()
+                .collect(),
+            overrides: Sources::new(),
+        };
+        let linux = TargetSet::Only(vec!["linux".to_string()].into_iter().collect());
+        let all = resolve_project(&root, &requirements, &None, None, &TargetSet::All).unwrap();
+        let on_linux = resolve_project(&root, &requirements, &None, None, &linux).unwrap();
+
+        assert_eq!(
+            all.lockfile.versions(),
+            &vec![(pkg("a"), ver("1.0.0")), (pkg("b"), ver("0.1.0"))]
+                .into_iter()
+                .collect()
+        );
+        let only_a = vec![(pkg("a"), ver("1.0.0"))].into_iter().collect();
+        assert_eq!(on_linux.lockfile.versions(), &only_a);
+        assert_eq!(all.versions_on(&linux).unwrap(), only_a);
+    }
+
+    #[test]
+    fn locks_registries() {
+        let versions = vec![(pkg("A"), ver("1.0.0")), (pkg("B"), ver("1.0.0"))]
+            .into_iter()
+            .collect();
+        let origins = vec![
+            (pkg("A"), Origin::Registry(Some("work".to_string()))),
+            (pkg("B"), Origin::Registry(None)),
+        ].into_iter()
+        .collect();
+        assert_eq!(
+            locked_sources(&versions, &origins, &Sources::new()),
+            vec![
+                (
+                    pkg("A"),
+                    LockedSource::Registry {
+                        registry: Some("work".to_string()),
+                    },
+                ),
+                (pkg("B"), LockedSource::Registry { registry: None }),
+            ].into_iter()
+            .collect()
+        );
+    }
+
+    #[test]
+    fn overrides_accept_any_version() {
+        let index = gen_registry!(
+            A => (
+                "1.0.0" => deps!(B => "^1")
+            ),
+            B => (
+                "2.0.0" => deps!()
+            )
+        );
+        let deps = deps!(A => "^1", B => "^1");
+        assert!(resolve(&index, &deps, None, &TargetSet::All).is_err());
+
+        let overrides = vec![(pkg("B"), Source::Path(PathBuf::from("../b-fork")))]
+            .into_iter()
+            .collect();
+        let (index, deps) = apply_overrides(&index, &deps, &overrides);
+        assert_eq!(
+            to_version_set(&resolve(&index, &deps, None, &TargetSet::All).unwrap()),
+            vec![(pkg("A"), ver("1.0.0")), (pkg("B"), ver("2.0.0"))]
+                .into_iter()
+                .collect()
+        );
+
+        let versions = to_version_set(&resolve(&index, &deps, None, &TargetSet::All).unwrap());
+        assert_eq!(
+            locked_sources(&versions, &Origins::new(), &overrides),
+            vec![(
+                pkg("B"),
+                LockedSource::Path {
+                    path: PathBuf::from("../b-fork"),
+                },
+            )].into_iter()
+            .collect()
+        );
+    }
+}
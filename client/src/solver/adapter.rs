@@ -5,7 +5,7 @@ use std::vec::Vec;
 use pm_lib::constraint::VersionConstraint;
 use pm_lib::package::PackageName;
 use pm_lib::version::Version;
-use pm_lib::index::{Index, Dependencies};
+use pm_lib::index::{Dependencies, Dependency, Index, TargetSet};
 use solver::failure::Failure;
 use solver::constraints::{Constraint, ConstraintSet};
 use solver::path::Path;
@@ -13,13 +13,17 @@ use solver::mappable::Mappable;
 
 pub struct RegistryAdapter<'r> {
     registry: &'r Index,
+    /// The targets to resolve for; dependencies which don't apply on them are
+    /// passed over.
+    targets: &'r TargetSet,
     cache: RefCell<HashMap<(Arc<PackageName>, Arc<VersionConstraint>), Option<Vec<Arc<Version>>>>>,
 }
 
 impl<'r> RegistryAdapter<'r> {
-    pub fn new(registry: &Index) -> RegistryAdapter {
+    pub fn new(registry: &'r Index, targets: &'r TargetSet) -> RegistryAdapter<'r> {
         RegistryAdapter {
             registry: registry,
+            targets: targets,
             cache: RefCell::new(HashMap::new()),
         }
     }
@@ -98,9 +102,9 @@ impl<'r> RegistryAdapter<'r> {
             .get(&version)
             .expect(&format!("release not found: {} {}", package, version));
         let mut constraint_set = ConstraintSet::new();
-        for (dep_package, version_constraint) in release {
+        for (dep_package, dependency) in self.applicable(release) {
             let dep_package_arc = Arc::new(dep_package.clone());
-            let version_constraint_arc = Arc::new(version_constraint.clone());
+            let version_constraint_arc = Arc::new(dependency.constraint.clone());
             let constraint = self.constraint_for(
                 dep_package_arc.clone(),
                 version_constraint_arc.clone(),
@@ -113,9 +117,9 @@ impl<'r> RegistryAdapter<'r> {
 
     pub fn constraint_set_from(&self, deps: &Dependencies) -> Result<ConstraintSet, Failure> {
         let mut constraint_set = ConstraintSet::new();
-        for (package, version_constraint) in deps {
+        for (package, dependency) in self.applicable(deps) {
             let package_arc = Arc::new(package.clone());
-            let version_constraint_arc = Arc::new(version_constraint.clone());
+            let version_constraint_arc = Arc::new(dependency.constraint.clone());
             let constraint = self.constraint_for(
                 package_arc.clone(),
                 version_constraint_arc.clone(),
@@ -125,4 +129,12 @@ impl<'r> RegistryAdapter<'r> {
         }
         Ok(constraint_set)
     }
+
+    /// Those of `deps` which apply on the targets being resolved for.
+    fn applicable<'d>(
+        &'d self,
+        deps: &'d Dependencies,
+    ) -> impl Iterator<Item = (&'d PackageName, &'d Dependency)> + 'd {
+        deps.iter().filter(move |&(_, dependency)| self.targets.includes(dependency))
+    }
 }
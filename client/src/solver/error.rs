@@ -1,3 +1,4 @@
+use std::fmt;
 use std::sync::Arc;
 use pm_lib::constraint::VersionConstraint;
 use pm_lib::package::PackageName;
@@ -40,6 +41,52 @@ impl Error {
     }
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            &Error::Conflict(ref conflict) => write!(
+                f,
+                "Conflicting dependencies on {}:\n    {} -> {} {}\n    {} -> {} {}",
+                conflict.package,
+                DisplayPath(&conflict.existing_path),
+                conflict.package,
+                conflict.existing,
+                DisplayPath(&conflict.conflicting_path),
+                conflict.package,
+                conflict.conflicting
+            ),
+            &Error::PackageMissing(ref missing) => write!(
+                f,
+                "Package {} not found, required by {}",
+                missing.package,
+                DisplayPath(&missing.path)
+            ),
+            &Error::UninhabitedConstraint(ref uninhabited) => write!(
+                f,
+                "No version of {} matches {}, required by {}",
+                uninhabited.package,
+                uninhabited.constraint,
+                DisplayPath(&uninhabited.path)
+            ),
+        }
+    }
+}
+
+impl ::failure::Fail for Error {}
+
+/// Formats a `Path` as `manifest -> A 1.0 -> B 2.0`.
+struct DisplayPath<'a>(&'a Path);
+
+impl<'a> fmt::Display for DisplayPath<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "manifest")?;
+        for &(ref package, ref version) in self.0.iter() {
+            write!(f, " -> {} {}", package, version)?;
+        }
+        Ok(())
+    }
+}
+
 impl Conflict {
     /// This function turns a `solver::failure::Conflict` (internal to the
     /// solver) into an `error::Conflict`. While the `failure::Conflict` has a
@@ -104,6 +151,7 @@ impl Conflict {
                 .expect(
                     "package must be listed in dependency set, according to path",
                 )
+                .constraint
                 .clone()
         };
 
@@ -158,6 +206,7 @@ impl Conflict {
 #[cfg(test)]
 mod test {
     use super::*;
+    use pm_lib::index::TargetSet;
     use pm_lib::test_helpers::{pkg, range};
     use solver::test_helpers::{constraint, path};
 
@@ -193,7 +242,7 @@ mod test {
                     "3" => deps!()
                 )
             );
-        let ra = RegistryAdapter::new(&registry);
+        let ra = RegistryAdapter::new(&registry, &TargetSet::All);
         let deps =
             deps!(
                 X => ">= 3 < 4"
@@ -1,6 +1,6 @@
 use std::convert::From;
 
-use pm_lib::index::{Dependencies, Index};
+use pm_lib::index::{Dependencies, Index, TargetSet};
 
 #[cfg(test)]
 #[macro_use]
@@ -109,8 +109,10 @@ fn cheap_attempt(
     }
 }
 
-pub fn solve(reg: &Index, deps: &Dependencies) -> Result<Solution, Error> {
-    let ra = RegistryAdapter::new(reg);
+/// Pick a version of every package needed by `deps` from `reg`, following
+/// only the dependencies which apply on `targets`.
+pub fn solve(reg: &Index, deps: &Dependencies, targets: &TargetSet) -> Result<Solution, Error> {
+    let ra = RegistryAdapter::new(reg, targets);
     solve_inner(&ra, &deps).map_err(|failure| Error::from_failure(&reg, &deps, &ra, failure))
 }
 
@@ -181,7 +183,7 @@ fn infer_indirect_dependencies(
 mod unit_test {
     use self::test_helpers::sample_registry;
     use super::*;
-    use pm_lib::index::{read_index, Dependencies, Index, Package};
+    use pm_lib::index::{read_index, Dependencies, Dependency, Index, Package};
     use pm_lib::test_helpers::{pkg, range, ver};
     use solver::constraints::Constraint;
     use solver::error::{Conflict, Error};
@@ -202,7 +204,7 @@ mod unit_test {
 
         b.iter(|| {
             assert_eq!(
-                solve(&reg, &problem),
+                solve(&reg, &problem, &TargetSet::All),
                 Ok(solution!{
                     base64 => "0.6.0",
                     byteorder => "1.1.0",
@@ -261,7 +263,7 @@ mod unit_test {
 
         b.iter(|| {
             assert_eq!(
-                solve(&reg, &problem),
+                solve(&reg, &problem, &TargetSet::All),
                 Err(Error::Conflict(Conflict {
                     package: Arc::new(pkg("hyper")),
                     existing: range("^0.11"),
@@ -276,7 +278,7 @@ mod unit_test {
     #[test]
     fn find_best_solution_set() {
         let sample_reg = sample_registry();
-        let sample_ra = RegistryAdapter::new(&sample_reg);
+        let sample_ra = RegistryAdapter::new(&sample_reg, &TargetSet::All);
 
         let problem = deps!(
             down_pad => "^1.0.0",
@@ -298,7 +300,7 @@ mod unit_test {
     #[test]
     fn conflicting_subdependencies() {
         let sample_reg = sample_registry();
-        let sample_ra = RegistryAdapter::new(&sample_reg);
+        let sample_ra = RegistryAdapter::new(&sample_reg, &TargetSet::All);
 
         // left_pad and lol_pad have conflicting constraints for right_pad,
         // thus no solution is possible.
@@ -321,6 +323,57 @@ mod unit_test {
         );
     }
 
+    #[test]
+    fn solutions_keep_paths() {
+        let sample_reg = sample_registry();
+        let sample_ra = RegistryAdapter::new(&sample_reg, &TargetSet::All);
+
+        let problem = deps!(
+            down_pad => "^1.0.0",
+            left_pad => "^2.0.0"
+        );
+
+        let solution = solve_inner(&sample_ra, &problem).unwrap();
+        assert_eq!(solution.path(&pkg("down_pad")), Some(&path(&[])));
+        assert_eq!(
+            solution.path(&pkg("right_pad")),
+            Some(&path(&[("left_pad", "2.0.0")]))
+        );
+        assert_eq!(
+            solution.path(&pkg("up_pad")),
+            Some(&path(&[("left_pad", "2.0.0"), ("right_pad", "2.0.1")]))
+        );
+    }
+
+    #[test]
+    fn follows_only_dependencies_on_the_targets_solved_for() {
+        let mut reg = sample_registry();
+        let mut deps = Dependencies::new();
+        deps.insert(
+            pkg("win_pad"),
+            Dependency {
+                constraint: range("^1"),
+                targets: vec!["windows".to_string()].into_iter().collect(),
+            },
+        );
+        let mut package = Package::new();
+        package.insert(ver("1.0.0"), deps);
+        reg.insert(pkg("term_pad"), package);
+        let problem = deps!(
+            term_pad => "^1.0.0"
+        );
+
+        let linux = TargetSet::Only(vec!["linux".to_string()].into_iter().collect());
+        assert_eq!(
+            solve(&reg, &problem, &linux),
+            Ok(solution!(
+                term_pad => "1.0.0"
+            ))
+        );
+        // win_pad isn't in the registry, which only matters on Windows.
+        assert!(solve(&reg, &problem, &TargetSet::All).is_err());
+    }
+
     #[test]
     #[ignore]
     fn large_number_of_dependencies_does_not_cause_stack_overflow() {
@@ -330,7 +383,7 @@ mod unit_test {
         for i in 0..n {
             let mut deps = Dependencies::new();
             if i != n - 1 {
-                deps.insert(pkg(&format!("P{}", i + 1)), range("^1"));
+                deps.insert(pkg(&format!("P{}", i + 1)), Dependency::new(range("^1")));
             }
             let mut package = Package::new();
             package.insert(ver("1"), deps);
@@ -339,7 +392,7 @@ mod unit_test {
         let problem = deps!{
             P0 => "^1"
         };
-        solve(&reg, &problem).unwrap();
+        solve(&reg, &problem, &TargetSet::All).unwrap();
     }
 
     #[test]
@@ -378,7 +431,7 @@ mod unit_test {
                 "1" => deps!()
             )
         );
-        let ra = RegistryAdapter::new(&reg);
+        let ra = RegistryAdapter::new(&reg, &TargetSet::All);
         let stack = constraint_set(&[("X", &[("1", &[]), ("2", &[]), ("3", &[]), ("4", &[])])]);
         let ps = partial_sln(&[("S", ("1", &[]))]);
         let expected = constraint_set(&[
@@ -35,8 +35,31 @@ impl Mappable for PartialSolution {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Solution(pub Map<Arc<PackageName>, Arc<Version>>);
+/// The version picked for each package, along with the `Path` that justified
+/// picking it: the chain of releases leading from one of the root dependencies
+/// to the release depending on the package. Paths of root dependencies are
+/// empty.
+#[derive(Clone, Debug)]
+pub struct Solution(
+    pub Map<Arc<PackageName>, Arc<Version>>,
+    pub Map<Arc<PackageName>, Path>,
+);
+
+impl Solution {
+    pub fn path(&self, package: &PackageName) -> Option<&Path> {
+        self.1.get(package)
+    }
+}
+
+// Solutions are equal if they pick the same versions, however the solver
+// justified them.
+impl PartialEq for Solution {
+    fn eq(&self, other: &Solution) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Solution {}
 
 impl Mappable for Solution {
     type K = Arc<PackageName>;
@@ -47,7 +70,7 @@ impl Mappable for Solution {
     }
 
     fn wrap(m: Map<Self::K, Self::V>) -> Self {
-        Solution(m)
+        Solution(m, Map::new())
     }
 }
 
@@ -56,18 +79,24 @@ impl FromIterator<(Arc<PackageName>, Arc<Version>)> for Solution {
     where
         T: IntoIterator<Item = (Arc<PackageName>, Arc<Version>)>,
     {
-        Solution(Map::<Arc<PackageName>, Arc<Version>>::from_iter(iter))
+        Solution::wrap(Map::<Arc<PackageName>, Arc<Version>>::from_iter(iter))
     }
 }
 
 impl From<PartialSolution> for Solution {
     fn from(partial_solution: PartialSolution) -> Solution {
-        // Strip all paths from a PartialSolution to obtain a Solution
-        partial_solution
+        let versions = partial_solution
             .iter()
             .map(|(package_name, justified_version)| {
                 (package_name.clone(), justified_version.version.clone())
             })
-            .collect()
+            .collect();
+        let paths = partial_solution
+            .iter()
+            .map(|(package_name, justified_version)| {
+                (package_name.clone(), justified_version.path.clone())
+            })
+            .collect();
+        Solution(versions, paths)
     }
 }
@@ -24,7 +24,8 @@ macro_rules! deps {
         let mut deps = ::std::collections::BTreeMap::new();
         $({
             let constraint = ::pm_lib::constraint::VersionConstraint::from_str($constraint).unwrap();
-            deps.insert(::pm_lib::test_helpers::pkg(stringify!($dep)), constraint);
+            let dependency = ::pm_lib::index::Dependency::new(constraint);
+            deps.insert(::pm_lib::test_helpers::pkg(stringify!($dep)), dependency);
         })*;
         deps
     }};
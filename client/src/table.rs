@@ -0,0 +1,97 @@
+use std::cmp::{max, min};
+
+use console::{Style, Term};
+
+/// How wide a column of a table is. Columns are never narrower than their
+/// title.
+#[derive(PartialEq, Eq)]
+pub enum Width {
+    /// As wide as its widest cell.
+    Fit,
+    /// As wide as its widest cell, up to a limit.
+    AtMost(usize),
+    /// Whatever the other columns leave of the terminal's width.
+    Fill,
+}
+
+/// A column of a table: its title, width and style, and what it shows for
+/// each row.
+pub struct Column<T> {
+    pub title: &'static str,
+    pub width: Width,
+    pub style: Style,
+    pub cell: fn(&T) -> String,
+}
+
+/// Print `rows` as a table of `columns`, with a header.
+pub fn print_table<T>(columns: &[Column<T>], rows: &[T]) {
+    let border = Style::new().dim();
+    let header = Style::new().green();
+    let bar = border.apply_to("|").to_string();
+
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| columns.iter().map(|column| (column.cell)(row)).collect())
+        .collect();
+    let fitted: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            let widest = cells.iter().map(|row| row[i].len()).max().unwrap_or(0);
+            match column.width {
+                Width::AtMost(limit) => max(column.title.len(), min(limit, widest)),
+                _ => max(column.title.len(), widest),
+            }
+        }).collect();
+    let available = (Term::stdout().size().1 as usize).saturating_sub(columns.len() - 1);
+    let taken: usize = columns
+        .iter()
+        .zip(&fitted)
+        .filter(|&(column, _)| column.width != Width::Fill)
+        .map(|(_, width)| width)
+        .sum();
+    let widths: Vec<usize> = columns
+        .iter()
+        .zip(&fitted)
+        .map(|(column, &width)| match column.width {
+            Width::Fill => max(column.title.len(), available.saturating_sub(taken)),
+            _ => width,
+        }).collect();
+
+    let sep = border.apply_to(
+        widths
+            .iter()
+            .map(|&width| "-".repeat(width))
+            .collect::<Vec<_>>()
+            .join("|"),
+    );
+    println!("{}", sep);
+    println!(
+        "{}",
+        columns
+            .iter()
+            .zip(&widths)
+            .map(|(column, &width)| header
+                .apply_to(format!("{:1$}", column.title, width))
+                .to_string())
+            .collect::<Vec<_>>()
+            .join(&bar)
+    );
+    println!("{}", sep);
+    for row in cells {
+        let last = row.len() - 1;
+        println!(
+            "{}",
+            row.iter()
+                .enumerate()
+                .map(|(i, cell)| {
+                    // The last column isn't padded, so lines don't end in
+                    // trailing space.
+                    let width = if i == last { 0 } else { widths[i] };
+                    columns[i].style.apply_to(format!("{:1$}", cell, width)).to_string()
+                }).collect::<Vec<_>>()
+                .join(&bar)
+        )
+    }
+    println!("{}", sep);
+}
@@ -0,0 +1,30 @@
+use std::fs::{create_dir_all, File};
+use std::io::Write;
+use std::path::Path;
+
+/// Write the manifest of a package called `name` into `dir`, with the given
+/// lines of the `dependencies` block.
+pub fn write_package(dir: &Path, name: &str, version: &str, dependencies: &str) {
+    create_dir_all(dir).unwrap();
+    File::create(dir.join("manifest"))
+        .unwrap()
+        .write_all(
+            format!(
+                r#"
+dependencies {{
+    {}
+}}
+package {{
+    name "{}"
+    version "{}"
+    description "A local package."
+    license "MIT"
+    files {{
+        add_uncommitted "manifest"
+    }}
+}}
+"#,
+                dependencies, name, version
+            ).as_bytes(),
+        ).unwrap();
+}
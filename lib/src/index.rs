@@ -1,10 +1,13 @@
 use std::fs;
 use std::fs::File;
 use std::io::{Read, Write};
-use std::collections::{HashMap, BTreeMap};
+use std::collections::{HashMap, BTreeMap, BTreeSet};
+use std::env;
 use std::path::Path;
 use std::sync::Arc;
 use rmp_serde::{self, encode};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json;
 
 use version::Version;
@@ -40,7 +43,146 @@ quick_error! {
 
 pub type Index = HashMap<PackageName, Package>;
 pub type Package = HashMap<Version, Dependencies>;
-pub type Dependencies = BTreeMap<PackageName, VersionConstraint>;
+pub type Dependencies = BTreeMap<PackageName, Dependency>;
+
+/// A dependency on a package: the versions of it which will do, and the
+/// targets it is restricted to, the OSes, architectures or custom tags on
+/// which it applies. A dependency without targets applies everywhere.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub constraint: VersionConstraint,
+    pub targets: BTreeSet<String>,
+}
+
+impl Dependency {
+    /// A dependency which applies everywhere.
+    pub fn new(constraint: VersionConstraint) -> Dependency {
+        Dependency {
+            constraint,
+            targets: BTreeSet::new(),
+        }
+    }
+}
+
+/// A dependency which applies everywhere is written as its bare constraint,
+/// as every dependency was before they could have targets, so indexes and
+/// manifests from back then still read.
+impl Serialize for Dependency {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.targets.is_empty() {
+            return self.constraint.serialize(serializer);
+        }
+        let mut state = serializer.serialize_struct("Dependency", 2)?;
+        state.serialize_field("constraint", &self.constraint)?;
+        state.serialize_field("targets", &self.targets)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Dependency {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(match SerializedDependency::deserialize(deserializer)? {
+            SerializedDependency::Everywhere(constraint) => Dependency::new(constraint),
+            SerializedDependency::On {
+                constraint,
+                targets,
+            } => Dependency {
+                constraint,
+                targets,
+            },
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SerializedDependency {
+    Everywhere(VersionConstraint),
+    On {
+        constraint: VersionConstraint,
+        targets: BTreeSet<String>,
+    },
+}
+
+/// Which targets to resolve dependencies for.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetSet {
+    /// All of them, so the resolution holds wherever the project is used.
+    All,
+    /// Only those with one of these tags.
+    Only(BTreeSet<String>),
+}
+
+impl Default for TargetSet {
+    fn default() -> TargetSet {
+        TargetSet::All
+    }
+}
+
+impl TargetSet {
+    /// The machine pm is running on, as Rust names its OS, architecture and
+    /// OS family, along with any custom `tags`.
+    pub fn host(tags: &[String]) -> TargetSet {
+        let mut host: BTreeSet<String> = tags.iter().cloned().collect();
+        host.insert(env::consts::OS.to_string());
+        host.insert(env::consts::ARCH.to_string());
+        host.insert(env::consts::FAMILY.to_string());
+        TargetSet::Only(host)
+    }
+
+    pub fn is_all(&self) -> bool {
+        *self == TargetSet::All
+    }
+
+    /// Whether `dependency` applies.
+    pub fn includes(&self, dependency: &Dependency) -> bool {
+        dependency.targets.is_empty() || match self {
+            &TargetSet::All => true,
+            &TargetSet::Only(ref tags) => !tags.is_disjoint(&dependency.targets),
+        }
+    }
+
+    /// Those of `dependencies` which apply.
+    pub fn filter(&self, dependencies: &Dependencies) -> Dependencies {
+        dependencies
+            .iter()
+            .filter(|&(_, dependency)| self.includes(dependency))
+            .map(|(package, dependency)| (package.clone(), dependency.clone()))
+            .collect()
+    }
+}
+
+/// A release as listed by the registry.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct Release {
+    pub dependencies: Dependencies,
+    #[serde(default)]
+    pub yanked: bool,
+    #[serde(default)]
+    pub deprecated: bool,
+}
+
+/// All releases of a package, as listed by the registry.
+pub type Releases = HashMap<Version, Release>;
+
+/// Turn the registry's listing of a package into the `Package` the solver
+/// picks from. Yanked releases are dropped unless they are `pinned` (e.g. by a
+/// lockfile), so existing projects keep resolving while new resolutions avoid
+/// them.
+pub fn resolvable_releases(releases: &Releases, pinned: Option<&Version>) -> Package {
+    releases
+        .iter()
+        .filter(|&(version, release)| !release.yanked || Some(version) == pinned)
+        .map(|(version, release)| (version.clone(), release.dependencies.clone()))
+        .collect()
+}
 
 pub fn read_index(path: &Path) -> Result<Arc<Index>, Error> {
     let mut f = File::open(path)?;
@@ -79,6 +221,69 @@ where
 mod unit_test {
     use super::*;
     use test::Bencher;
+    use test_helpers::{pkg, ver};
+
+    #[test]
+    fn yanked_releases_are_only_resolvable_when_pinned() {
+        let mut releases = Releases::new();
+        releases.insert(ver("1.0"), Release::default());
+        releases.insert(
+            ver("1.1"),
+            Release {
+                yanked: true,
+                ..Release::default()
+            },
+        );
+
+        let unpinned = resolvable_releases(&releases, None);
+        assert!(unpinned.contains_key(&ver("1.0")));
+        assert!(!unpinned.contains_key(&ver("1.1")));
+
+        let pinned = resolvable_releases(&releases, Some(&ver("1.1")));
+        assert!(pinned.contains_key(&ver("1.0")));
+        assert!(pinned.contains_key(&ver("1.1")));
+    }
+
+    #[test]
+    fn filters_dependencies_by_target() {
+        let any = VersionConstraint::from_str("*").unwrap();
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect();
+        let on = |targets: &[&str]| Dependency {
+            constraint: any.clone(),
+            targets: tags(targets),
+        };
+        let dependencies: Dependencies = vec![
+            (pkg("everywhere"), Dependency::new(any.clone())),
+            (pkg("unix-only"), on(&["linux", "macos"])),
+            (pkg("windows-only"), on(&["windows"])),
+        ].into_iter()
+        .collect();
+
+        let linux = TargetSet::Only(tags(&["linux", "x86_64"]));
+        assert_eq!(
+            linux.filter(&dependencies).keys().collect::<Vec<_>>(),
+            vec![&pkg("everywhere"), &pkg("unix-only")]
+        );
+        assert_eq!(TargetSet::All.filter(&dependencies), dependencies);
+    }
+
+    #[test]
+    fn writes_dependencies_without_targets_as_bare_constraints() {
+        let json = concat!(
+            r#"{"test/everywhere":"^1","#,
+            r#""test/windows-only":{"constraint":"^2","targets":["windows"]}}"#
+        );
+        let dependencies: Dependencies = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            dependencies[&pkg("everywhere")],
+            Dependency::new(VersionConstraint::from_str("^1").unwrap())
+        );
+        assert_eq!(
+            dependencies[&pkg("windows-only")].targets,
+            vec!["windows".to_string()].into_iter().collect()
+        );
+        assert_eq!(serde_json::to_string(&dependencies).unwrap(), json);
+    }
 
     #[bench] #[ignore]
     fn read_cargo_index(_b: &mut Bencher) {
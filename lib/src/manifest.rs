@@ -1,4 +1,6 @@
 use version::Version;
+use index::Dependencies;
+use package::PackageName;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum License {
@@ -16,7 +18,118 @@ pub struct Manifest {
     pub description: String,
     pub license: License,
     pub keywords: Vec<String>,
+    #[serde(default)]
+    pub dependencies: Dependencies,
+    /// The source of the manifest, exactly as it was written.
     pub manifest: String,
+    /// The file name and contents of the package's README.
     pub readme: Option<(String, String)>,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    #[serde(default)]
+    pub homepage: Option<String>,
+    #[serde(default)]
+    pub repository: Option<String>,
+    #[serde(default)]
+    pub bugs: Option<String>,
     pub data: Vec<u8>,
 }
+
+impl Manifest {
+    pub fn normalized(&self) -> NormalizedManifest {
+        let (license, license_file) = match self.license {
+            License::SPDX(ref tag) => (Some(tag.clone()), None),
+            License::File(ref file) => (None, Some(file.clone())),
+            License::SPDXAndFile(ref tag, ref file) => (Some(tag.clone()), Some(file.clone())),
+        };
+        NormalizedManifest {
+            name: PackageName {
+                namespace: self.namespace.clone(),
+                name: self.name.clone(),
+            },
+            version: self.version.clone(),
+            description: self.description.clone(),
+            license,
+            license_file,
+            keywords: self.keywords.clone(),
+            authors: self.authors.clone(),
+            homepage: self.homepage.clone(),
+            repository: self.repository.clone(),
+            bugs: self.bugs.clone(),
+            readme: self.readme.as_ref().map(|&(ref file_name, _)| file_name.clone()),
+            dependencies: self.dependencies.clone(),
+        }
+    }
+}
+
+/// What a release's manifest says, in a form tools can read as JSON without
+/// parsing the manifest format, as served by the registry alongside the
+/// manifest source.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedManifest {
+    pub name: PackageName,
+    pub version: Version,
+    pub description: String,
+    pub license: Option<String>,
+    pub license_file: Option<String>,
+    pub keywords: Vec<String>,
+    pub authors: Vec<String>,
+    pub homepage: Option<String>,
+    pub repository: Option<String>,
+    pub bugs: Option<String>,
+    /// The file name of the README.
+    pub readme: Option<String>,
+    pub dependencies: Dependencies,
+}
+
+/// A release's manifest, as served by the registry.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseManifest {
+    /// The manifest source, if the release was published with it.
+    pub source: Option<String>,
+    /// The normalized manifest, if the release was published with it.
+    pub manifest: Option<NormalizedManifest>,
+}
+
+#[cfg(test)]
+mod unit_test {
+    use super::*;
+    use constraint::VersionConstraint;
+    use index::Dependency;
+    use serde_json;
+    use test_helpers::{pkg, ver};
+
+    #[test]
+    fn normalizes_manifests() {
+        let manifest = Manifest {
+            namespace: "test".to_string(),
+            name: "pad".to_string(),
+            version: ver("1.0.0"),
+            description: "Pads things.".to_string(),
+            license: License::SPDXAndFile("MIT".to_string(), "LICENSE".to_string()),
+            keywords: vec![],
+            dependencies: vec![(
+                pkg("left-pad"),
+                Dependency::new(VersionConstraint::from_str("^1").unwrap()),
+            )].into_iter()
+            .collect(),
+            manifest: "pm \"1\"\n".to_string(),
+            readme: Some(("README.md".to_string(), "# Pad".to_string())),
+            authors: vec![],
+            homepage: None,
+            repository: None,
+            bugs: None,
+            data: vec![],
+        };
+        let normalized = manifest.normalized();
+        assert_eq!(normalized.name, pkg("pad"));
+        assert_eq!(normalized.license, Some("MIT".to_string()));
+        assert_eq!(normalized.license_file, Some("LICENSE".to_string()));
+        assert_eq!(normalized.readme, Some("README.md".to_string()));
+        let json = serde_json::to_string(&normalized).unwrap();
+        assert_eq!(
+            serde_json::from_str::<NormalizedManifest>(&json).unwrap(),
+            normalized
+        );
+    }
+}
@@ -1,5 +1,11 @@
 #![allow(unused_macros)]
 
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use version::Version;
 use package::PackageName;
 use constraint::VersionConstraint;
@@ -27,6 +33,41 @@ pub fn pkg(s: &str) -> PackageName {
     }
 }
 
+/// A fresh directory for a test to write files into, removed along with
+/// everything in it when dropped, even if the test fails.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new() -> TempDir {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(format!(
+            "pm-test-{}-{}",
+            process::id(),
+            COUNT.fetch_add(1, Ordering::SeqCst)
+        ));
+        // Left over from an earlier run which was killed, if it exists.
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.path.join(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
 macro_rules! ver {
     ( $( $x:expr ),* ) => {{
         let mut version_parts = Vec::new();
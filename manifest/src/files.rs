@@ -1,7 +1,4 @@
-#![allow(dead_code)]
-
 use failure;
-use git::GitScmProvider;
 use glob::{MatchOptions, Pattern, PatternError};
 use std::collections::{BTreeSet, HashSet};
 use std::error::Error as StdError;
@@ -18,8 +15,32 @@ use std::path::{Path, PathBuf};
 // behavior due to ordering.
 type FileSet = HashSet<String>;
 
-pub struct FilesSectionInterpreter {
+/// Where `add_committed` finds the files committed to version control. This
+/// crate doesn't depend on any version control system itself; pm provides one
+/// backed by Git.
+pub trait Scm {
+    /// The files committed under `root`, relative to it. Fails if the working
+    /// tree has changes which aren't committed.
+    fn committed_files(&self, root: &Path) -> Result<Vec<String>, failure::Error>;
+}
+
+/// For reading manifests outside of version control, where `add_committed`
+/// always fails.
+pub struct NoScm;
+
+impl Scm for NoScm {
+    fn committed_files(&self, _root: &Path) -> Result<Vec<String>, failure::Error> {
+        Err(failure::Error::from(NoScmError))
+    }
+}
+
+#[derive(Fail, Debug)]
+#[fail(display = "Committed files can't be listed without version control")]
+pub struct NoScmError;
+
+pub struct FilesSectionInterpreter<'a> {
     pub root: PathBuf,
+    pub scm: &'a Scm,
     pub files_on_disk: BTreeSet<String>,
     pub directories_on_disk: BTreeSet<String>,
     pub committed_files: Vec<String>,
@@ -35,11 +56,12 @@ pub struct AddCommittedNotFoundError;
 pub struct AddUncommittedNotFoundError;
 
 // Interpret "add" and "remove" commands in the `files { ... }` section.
-impl FilesSectionInterpreter {
-    pub fn new(root: PathBuf) -> Result<Self, failure::Error> {
+impl<'a> FilesSectionInterpreter<'a> {
+    pub fn new(root: PathBuf, scm: &'a Scm) -> Result<Self, failure::Error> {
         let (files, directories) = walk_dir(&root)?;
         Ok(FilesSectionInterpreter {
             root: root,
+            scm: scm,
             files_on_disk: files,
             directories_on_disk: directories,
             committed_files: Vec::new(),
@@ -47,12 +69,10 @@ impl FilesSectionInterpreter {
         })
     }
 
-    // Populate `committed_files` with the results of `git ls-files`.
+    // Populate `committed_files` with the files `scm` has committed.
     pub fn initialize_committed_files(&mut self) -> Result<(), failure::Error> {
         if !self.did_initialize_committed_files {
-            let git_scm_provider = GitScmProvider::new(&self.root)?;
-            git_scm_provider.check_repo_is_pristine()?;
-            self.committed_files = git_scm_provider.ls_files()?;
+            self.committed_files = self.scm.committed_files(&self.root)?;
             self.did_initialize_committed_files = true;
         }
         Ok(())
@@ -285,9 +305,10 @@ impl CompiledGlob {
 mod test {
     use super::*;
 
-    fn make_fsi(files: &[&str]) -> FilesSectionInterpreter {
+    fn make_fsi(files: &[&str]) -> FilesSectionInterpreter<'static> {
         FilesSectionInterpreter {
             root: PathBuf::from("dummy"),
+            scm: &NoScm,
             files_on_disk: files.iter().map(|s| s.to_string()).collect(),
             directories_on_disk: generate_directories(files),
             committed_files: Vec::new(),
@@ -399,4 +420,12 @@ mod test {
             assert_file_set(&file_set, &[]);
         }
     }
+
+    #[test]
+    fn add_committed_needs_scm() {
+        let mut fsi = make_fsi(&["src/a.rs"]);
+        fsi.did_initialize_committed_files = false;
+        let error = fsi.add_committed(&mut FileSet::new(), "src").unwrap_err();
+        assert!(error.downcast::<NoScmError>().is_ok());
+    }
 }
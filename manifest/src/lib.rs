@@ -0,0 +1,38 @@
+//! Reading, checking and formatting pm manifests, without the rest of pm.
+//!
+//! ```ignore
+//! let manifest = Manifest::from_str(source.clone(), &package_dir, &NoScm)?;
+//! let (dependencies, sources) = get_dependencies(parse_and_check_manifest(source)?)?;
+//! ```
+//!
+//! Errors found in a manifest are `ManifestParserError`s, which know where in
+//! the manifest they were found; `ManifestParserError::diagnostics` lists them
+//! with their line and column.
+
+#[macro_use]
+extern crate failure;
+#[macro_use]
+extern crate failure_derive;
+extern crate glob;
+extern crate pest;
+#[macro_use]
+extern crate pest_derive;
+extern crate pm_lib;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[cfg(test)]
+#[macro_use]
+extern crate matches;
+
+pub mod files;
+pub mod manifest;
+pub mod manifest_parser;
+pub mod manifest_parser_error;
+pub mod manifest_cst;
+pub mod manifest_fmt;
+pub mod manifest_schema;
+
+pub use files::{NoScm, Scm};
+pub use manifest::{check_manifest, get_dependencies, parse_and_check_manifest, Manifest};
+pub use manifest_parser_error::{Diagnostic, Location, ManifestParserError};
@@ -0,0 +1,1175 @@
+use files::{FilesSectionInterpreter, Scm};
+use manifest_parser::{
+    check_block_fields, check_repeatable_block_fields, children, closest, find_optional_rule,
+    find_rule, get_field, get_fields, get_list, get_option, get_optional_block_field,
+    get_optional_field, get_optional_list_field, get_string, Arguments, Pair, Rule,
+};
+use manifest_parser_error::{ErrorCollector, ManifestParserError, PestErrorExt, PestResultExt};
+use manifest_schema::parse_migrated_manifest;
+use pm_lib::constraint::VersionConstraint;
+use pm_lib::index::{Dependencies, Dependency};
+use pm_lib::manifest::License;
+use pm_lib::package::PackageName;
+use pm_lib::version::Version;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Where a dependency comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// The registry with the given name in the config, or the default
+    /// registry if `None`.
+    Registry(Option<String>),
+    /// A package in a local directory, relative to the manifest that depends
+    /// on it.
+    Path(PathBuf),
+    /// A package in a Git repository, at the root of its working tree.
+    Git { url: String, reference: GitReference },
+}
+
+pub type Sources = BTreeMap<PackageName, Source>;
+
+/// Which commit of a Git dependency to use.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GitReference {
+    Rev(String),
+    Branch(String),
+    Tag(String),
+    /// Whatever the repository's HEAD points to.
+    #[serde(rename = "default")]
+    DefaultBranch,
+}
+
+impl fmt::Display for GitReference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            &GitReference::Rev(ref rev) => write!(f, "revision {}", rev),
+            &GitReference::Branch(ref branch) => write!(f, "branch {}", branch),
+            &GitReference::Tag(ref tag) => write!(f, "tag {}", tag),
+            &GitReference::DefaultBranch => write!(f, "the default branch"),
+        }
+    }
+}
+
+// The Manifest struct represents a parsed manifest file.
+
+#[derive(Debug)]
+pub struct Manifest {
+    pub name: PackageName,
+    pub version: Version,
+
+    pub dependencies: Dependencies,
+    pub sources: Sources,
+
+    pub authors: Vec<String>,
+    pub description: String,
+    pub homepage: Option<String>,
+    pub repository: Option<String>,
+    pub bugs: Option<String>,
+    pub keywords: Vec<String>,
+
+    pub license: License,
+
+    pub readme: Option<(String, String)>,
+    pub files: Vec<String>,
+}
+
+impl Manifest {
+    /// Read the manifest of the package in `root`. The files listed with
+    /// `add_committed` are taken from `scm`; pass `NoScm` to read manifests
+    /// outside of version control.
+    pub fn from_str(
+        manifest_source: String,
+        root: &Path,
+        scm: &Scm,
+    ) -> Result<Self, ::failure::Error> {
+        let manifest_pair = parse_and_check_manifest(manifest_source)?;
+
+        Ok(Self::from_manifest_pair(manifest_pair, root, scm)?)
+    }
+
+    /// Read the manifest, reporting everything wrong with it at once rather
+    /// than just the first problem found.
+    pub fn from_manifest_pair(
+        manifest_pair: Pair,
+        root: &Path,
+        scm: &Scm,
+    ) -> Result<Self, ::failure::Error> {
+        let mut errors = ErrorCollector::new();
+        let variables = match errors.collect(Variables::from_manifest_pair(manifest_pair.clone())) {
+            Some(variables) => variables,
+            // Every string might refer to the variables, so stop here.
+            None => return Err(::failure::Error::from(errors.into_error())),
+        };
+        let dependencies = errors.collect(get_dependencies(manifest_pair.clone()));
+
+        let package_arguments_pair = get_optional_field(manifest_pair.clone(), "package")
+            .ok_or_else(|| {
+                // We use get_optional_field and .ok_or_else to produce a
+                // clearer error message.
+                format_err!("A `package {{ ... }}` section is required to publish this package")
+                    .with_pos(&manifest_pair.clone().into_span().end_pos())
+            });
+        let block_pair = match errors.collect(package_arguments_pair.and_then(|arguments_pair| {
+            Arguments::from_pair(arguments_pair, 0, 0, &[], Some(true))
+        })) {
+            Some(arguments) => arguments.block.expect("validated block presence"),
+            // Without a package section, there's nothing more to check.
+            None => return Err(::failure::Error::from(errors.into_error())),
+        };
+
+        errors.collect(check_block_fields(
+            block_pair.clone(),
+            &[
+                "name",
+                "version",
+                "description",
+                "keywords",
+                "homepage",
+                "repository",
+                "bugs",
+                "license",
+                "license_file",
+                "authors",
+                "readme",
+                "files",
+            ],
+        ));
+
+        let name = errors.collect(get_name(block_pair.clone(), &variables));
+        let version = errors.collect(get_version(block_pair.clone(), &variables));
+        let description = errors.collect(
+            get_field(block_pair.clone(), "description")
+                .and_then(Arguments::get_single)
+                .and_then(|pair| variables.string(pair)),
+        );
+
+        let homepage = errors.collect(variables.string_field(block_pair.clone(), "homepage"));
+        let repository = errors.collect(variables.string_field(block_pair.clone(), "repository"));
+        let bugs = errors.collect(variables.string_field(block_pair.clone(), "bugs"));
+
+        let authors = errors.collect(variables.string_list_field(block_pair.clone(), "authors"));
+        let keywords = errors.collect(variables.string_list_field(block_pair.clone(), "keywords"));
+
+        let license = errors.collect(get_license(block_pair.clone(), &variables));
+        let readme = errors.collect(get_readme(block_pair.clone(), root, &variables));
+
+        let files = errors.collect(
+            get_field(block_pair.clone(), "files")
+                .and_then(Arguments::get_block)
+                .map_err(::failure::Error::from)
+                .and_then(|files_block| evaluate_files_block(files_block, root, scm, &variables)),
+        );
+
+        errors.finish()?;
+        let (dependencies, sources) = dependencies.expect("errors were reported");
+        let reported = "errors were reported";
+        Ok(Manifest {
+            name: name.expect(reported),
+            version: version.expect(reported),
+
+            dependencies: dependencies,
+            sources: sources,
+
+            authors: authors.expect(reported),
+            description: description.expect(reported),
+            homepage: homepage.expect(reported),
+            repository: repository.expect(reported),
+            bugs: bugs.expect(reported),
+            keywords: keywords.expect(reported),
+
+            license: license.expect(reported),
+
+            readme: readme.expect(reported),
+
+            files: files.expect(reported),
+        })
+    }
+}
+
+fn get_name(block_pair: Pair, variables: &Variables) -> Result<PackageName, ManifestParserError> {
+    let name_pair = Arguments::get_single(get_field(block_pair, "name")?)?;
+    let name_string = variables.string(name_pair.clone())?;
+    PackageName::from_str(&name_string)
+        .ok_or_else(|| format_err!("Invalid package name").with_pair(&name_pair))
+}
+
+fn get_version(block_pair: Pair, variables: &Variables) -> Result<Version, ManifestParserError> {
+    let version_pair = Arguments::get_single(get_field(block_pair, "version")?)?;
+    let version_string = variables.string(version_pair.clone())?;
+    Version::from_str(&version_string)
+        .ok_or_else(|| format_err!("Invalid version number").with_pair(&version_pair))
+}
+
+fn get_license(block_pair: Pair, variables: &Variables) -> Result<License, ManifestParserError> {
+    let license = variables.string_field(block_pair.clone(), "license")?;
+    let license_file = variables.string_field(block_pair.clone(), "license_file")?;
+    match (license, license_file) {
+        (Some(tag), None) => Ok(License::SPDX(tag)),
+        (None, Some(file)) => Ok(License::File(file)),
+        (Some(tag), Some(file)) => Ok(License::SPDXAndFile(tag, file)),
+        (None, None) => Err(
+            format_err!("package section needs at least one of license or license_file")
+                .with_pos(&block_pair.into_span().start_pos())
+                .with_code("missing-license"),
+        ),
+    }
+}
+
+/// The file names `find_readme` looks for, in order of preference.
+const README_NAMES: &'static [&'static str] =
+    &["readme.md", "readme.markdown", "readme.txt", "readme"];
+
+/// Read the README named by the `readme` field, or else found by
+/// `find_readme`, giving its path relative to `root` and its contents. A named
+/// README which can't be read is an error, so that it isn't quietly left out
+/// of a release. Every manifest read has its README read too, dependencies'
+/// included, so a found one which can't be read, such as one which isn't
+/// UTF-8, counts as none instead.
+fn get_readme(
+    block_pair: Pair,
+    root: &Path,
+    variables: &Variables,
+) -> Result<Option<(String, String)>, ::failure::Error> {
+    match get_optional_field(block_pair, "readme") {
+        Some(arguments_pair) => {
+            let path_pair = Arguments::get_single(arguments_pair)?;
+            let path = variables.string(path_pair.clone())?;
+            if !root.join(&path).is_file() {
+                return Err(::failure::Error::from(
+                    format_err!("README file {} doesn't exist", path).with_pair(&path_pair),
+                ));
+            }
+            match read_readme(root, &path) {
+                Ok(contents) => Ok(Some((path, contents))),
+                Err(error) => Err(::failure::Error::from(
+                    format_err!("Can't read README file {}: {}", path, error)
+                        .with_pair(&path_pair),
+                )),
+            }
+        }
+        None => match find_readme(root) {
+            Ok(Some(path)) => match read_readme(root, &path) {
+                Ok(contents) => Ok(Some((path, contents))),
+                Err(_) => Ok(None),
+            },
+            Ok(None) | Err(_) => Ok(None),
+        },
+    }
+}
+
+fn read_readme(root: &Path, path: &str) -> io::Result<String> {
+    let mut contents = String::new();
+    File::open(root.join(path))?.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+/// Look for a README next to the manifest, ignoring case, so it can be left
+/// out of the `readme` field.
+pub fn find_readme(root: &Path) -> Result<Option<String>, ::failure::Error> {
+    let mut candidates = vec![];
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        if let Ok(file_name) = entry.file_name().into_string() {
+            let lowercase = file_name.to_lowercase();
+            if let Some(rank) = README_NAMES.iter().position(|&name| name == lowercase) {
+                candidates.push((rank, file_name));
+            }
+        }
+    }
+    Ok(candidates.into_iter().min().map(|(_, file_name)| file_name))
+}
+
+const MANIFEST_FIELDS: &'static [&'static str] = &[
+    "pm",
+    "let",
+    "dependencies",
+    "overrides",
+    "package",
+    "workspace",
+];
+
+pub fn parse_and_check_manifest(manifest_source: String) -> Result<Pair, ::failure::Error> {
+    let manifest_pair = parse_migrated_manifest(manifest_source)?;
+
+    check_repeatable_block_fields(manifest_pair.clone(), MANIFEST_FIELDS, &["let"])?;
+
+    Ok(manifest_pair)
+}
+
+/// Check everything in the manifest, reporting every problem found rather than
+/// just the first. The package section is only checked if there is one.
+pub fn check_manifest(
+    manifest_source: String,
+    root: &Path,
+    scm: &Scm,
+) -> Result<(), ManifestParserError> {
+    let manifest_pair = parse_migrated_manifest(manifest_source)?;
+    let mut errors = ErrorCollector::new();
+    errors.collect(check_repeatable_block_fields(
+        manifest_pair.clone(),
+        MANIFEST_FIELDS,
+        &["let"],
+    ));
+    if errors
+        .collect(Variables::from_manifest_pair(manifest_pair.clone()))
+        .is_none()
+    {
+        // Every string might refer to the variables, so stop here.
+        return Err(errors.into_error());
+    }
+    errors.collect(get_overrides(manifest_pair.clone()));
+    errors.collect(get_workspace_members(manifest_pair.clone()));
+    if get_optional_field(manifest_pair.clone(), "package").is_some() {
+        // This checks the dependencies too.
+        errors.collect(Manifest::from_manifest_pair(manifest_pair, root, scm));
+    } else {
+        errors.collect(get_dependencies(manifest_pair));
+    }
+    errors.finish()
+}
+
+/// Read the `dependencies` block for the platform pm is running on, giving the
+/// dependencies, with the targets those with a `target` option are restricted
+/// to, and where they come from.
+pub fn get_dependencies(
+    manifest_pair: Pair,
+) -> Result<(Dependencies, Sources), ::failure::Error> {
+    get_dependencies_for(manifest_pair, &Platform::current())
+}
+
+/// Read the `dependencies` block for `platform`. Dependencies inside `if`
+/// blocks only apply if their condition holds, but are checked either way:
+///
+/// ```text
+/// let pad="1.2.0"
+///
+/// dependencies {
+///     js/left-pad ^${pad}
+///     if os="windows" {
+///         js/win-pad ^${pad}
+///     }
+///     js/mac-pad ^${pad} target=["macos" "ios"]
+/// }
+/// ```
+///
+/// Unlike `if` blocks, which are decided as the manifest is read, `target`
+/// options are recorded, so the project can be resolved for targets other
+/// than the one it's on.
+pub fn get_dependencies_for(
+    manifest_pair: Pair,
+    platform: &Platform,
+) -> Result<(Dependencies, Sources), ::failure::Error> {
+    read_dependencies(manifest_pair, &Conditions::Evaluate(platform))
+}
+
+/// Read the `dependencies` block to publish it. Whoever installs the package
+/// may be on another platform than the one it's published from, so rather
+/// than being decided here, dependencies inside `if` blocks are restricted to
+/// the targets their condition names: `if os=["linux" "macos"]` is published
+/// as `target=["linux" "macos"]`. Conditions which targets can't express, on
+/// more than one of `os`, `arch` and `family` at once, are refused.
+pub fn get_published_dependencies(
+    manifest_pair: Pair,
+) -> Result<(Dependencies, Sources), ::failure::Error> {
+    read_dependencies(manifest_pair, &Conditions::AsTargets)
+}
+
+/// What to do with the dependencies inside `if` blocks.
+enum Conditions<'a> {
+    /// Keep them if `platform` satisfies their condition.
+    Evaluate(&'a Platform),
+    /// Keep all of them, restricted to the targets their condition names.
+    AsTargets,
+}
+
+fn read_dependencies(
+    manifest_pair: Pair,
+    conditions: &Conditions,
+) -> Result<(Dependencies, Sources), ::failure::Error> {
+    let variables = Variables::from_manifest_pair(manifest_pair.clone())?;
+    let mut errors = ErrorCollector::new();
+    let mut dependencies = (Dependencies::new(), Sources::new());
+    read_dependency_entries(
+        get_optional_block_field(manifest_pair, "dependencies")?,
+        true,
+        None,
+        conditions,
+        &variables,
+        &mut errors,
+        &mut dependencies,
+    );
+    errors.finish()?;
+    Ok(dependencies)
+}
+
+/// Read the entries of a `dependencies` block, or of an `if` block inside
+/// one, adding them to `dependencies` if the block is `active`, and
+/// restricting them to `condition_targets` if the block is an `if` block being
+/// read as targets.
+fn read_dependency_entries(
+    entries: Vec<(Pair, Pair)>,
+    active: bool,
+    condition_targets: Option<&BTreeSet<String>>,
+    conditions: &Conditions,
+    variables: &Variables,
+    errors: &mut ErrorCollector,
+    dependencies: &mut (Dependencies, Sources),
+) {
+    for (package_name_pair, arguments_pair) in entries {
+        if package_name_pair.as_str() == "if" {
+            let condition = Arguments::from_pair(
+                arguments_pair,
+                0,
+                0,
+                CONDITION_KEYS,
+                Some(true),
+            ).and_then(|arguments| {
+                let block_pair = arguments.block.expect("validated block presence");
+                match conditions {
+                    &Conditions::Evaluate(platform) => {
+                        let holds = platform.satisfies(arguments.options, variables)?;
+                        Ok((holds, None, block_pair))
+                    }
+                    &Conditions::AsTargets => {
+                        if condition_targets.is_some() {
+                            return Err(format_err!(
+                                "`if` blocks inside `if` blocks can't be published; combine \
+                                 them into one condition"
+                            ).with_pair(&package_name_pair)
+                            .with_code("unpublishable-condition"));
+                        }
+                        let targets = targets_of(arguments.options, variables)?;
+                        Ok((true, Some(targets), block_pair))
+                    }
+                }
+            });
+            if let Some((holds, targets, block_pair)) = errors.collect(condition) {
+                read_dependency_entries(
+                    get_fields(block_pair),
+                    active && holds,
+                    targets.as_ref().or(condition_targets),
+                    conditions,
+                    variables,
+                    errors,
+                    dependencies,
+                );
+            }
+            continue;
+        }
+        let dependency = Arguments::from_pair(
+            arguments_pair,
+            0,
+            2,
+            &["registry", "path", "git", "rev", "branch", "tag", "target"],
+            Some(false),
+        ).map_err(::failure::Error::from)
+        .and_then(|arguments| {
+            let (package_name, version_constraint) = make_dependency(
+                package_name_pair.clone(),
+                arguments.positional_arguments,
+                variables,
+            )?;
+            let source = make_source(arguments.options.clone(), variables)?;
+            let targets = match (get_option(arguments.options, "target"), condition_targets) {
+                (Some(option_pair), None) => {
+                    variables.option_strings(option_pair)?.into_iter().collect()
+                }
+                (Some(option_pair), Some(_)) => {
+                    return Err(::failure::Error::from(
+                        format_err!("Dependencies inside `if` blocks can't also have a `target`")
+                            .with_pair(&option_pair)
+                            .with_code("unpublishable-condition"),
+                    ))
+                }
+                (None, Some(condition_targets)) => condition_targets.clone(),
+                (None, None) => BTreeSet::new(),
+            };
+            Ok((package_name, version_constraint, source, targets))
+        });
+        let dependency = errors.collect(dependency);
+        if let Some((package_name, version_constraint, source, targets)) = dependency {
+            if !active {
+                continue;
+            }
+            if dependencies.0.contains_key(&package_name) {
+                errors.push(
+                    format_err!("Duplicate dependency")
+                        .with_pair(&package_name_pair)
+                        .with_code("duplicate-dependency"),
+                );
+            } else {
+                dependencies.1.insert(package_name.clone(), source);
+                dependencies.0.insert(
+                    package_name,
+                    Dependency {
+                        constraint: version_constraint,
+                        targets,
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Read the `overrides` block, which replaces packages anywhere in the
+/// dependency graph with another source:
+///
+/// ```text
+/// overrides {
+///     js/left-pad git="https://github.com/someone/left-pad" branch="fix"
+/// }
+/// ```
+///
+/// Only the overrides of the project being installed apply; those of its
+/// dependencies are ignored.
+pub fn get_overrides(manifest_pair: Pair) -> Result<Sources, ::failure::Error> {
+    let variables = Variables::from_manifest_pair(manifest_pair.clone())?;
+    let mut errors = ErrorCollector::new();
+    let mut overrides = Sources::new();
+    for (package_name_pair, arguments_pair) in
+        get_optional_block_field(manifest_pair, "overrides")?
+    {
+        let entry = make_override(package_name_pair.clone(), arguments_pair, &variables);
+        if let Some((package_name, source)) = errors.collect(entry) {
+            if overrides.contains_key(&package_name) {
+                errors.push(
+                    format_err!("Duplicate override")
+                        .with_pair(&package_name_pair)
+                        .with_code("duplicate-override"),
+                );
+            } else {
+                overrides.insert(package_name, source);
+            }
+        }
+    }
+    errors.finish()?;
+    Ok(overrides)
+}
+
+fn make_override(
+    package_name_pair: Pair,
+    arguments_pair: Pair,
+    variables: &Variables,
+) -> Result<(PackageName, Source), ::failure::Error> {
+    let arguments = Arguments::from_pair(
+        arguments_pair.clone(),
+        0,
+        0,
+        &["registry", "path", "git", "rev", "branch", "tag"],
+        Some(false),
+    )?;
+    let package_name = PackageName::from_str(package_name_pair.as_str())
+        .ok_or_else(|| format_err!("Invalid package name").with_pair(&package_name_pair))?;
+    let source = make_source(arguments.options, variables)?;
+    if source == Source::Registry(None) {
+        return Err(::failure::Error::from(
+            format_err!("Expected a `registry`, `path` or `git` to override with")
+                .with_pos(&arguments_pair.into_span().end_pos()),
+        ));
+    }
+    Ok((package_name, source))
+}
+
+/// Read the `workspace` block, if there is one, giving the directories of the
+/// workspace's member packages relative to the manifest:
+///
+/// ```text
+/// workspace {
+///     members ["packages/core" "packages/cli"]
+/// }
+/// ```
+pub fn get_workspace_members(
+    manifest_pair: Pair,
+) -> Result<Option<Vec<PathBuf>>, ::failure::Error> {
+    let variables = Variables::from_manifest_pair(manifest_pair.clone())?;
+    match get_optional_field(manifest_pair, "workspace") {
+        None => Ok(None),
+        Some(arguments_pair) => {
+            let block_pair = Arguments::get_block(arguments_pair)?;
+            check_block_fields(block_pair.clone(), &["members"])?;
+            let members = variables
+                .string_list_field(block_pair, "members")?
+                .into_iter()
+                .map(PathBuf::from)
+                .collect();
+            Ok(Some(members))
+        }
+    }
+}
+
+/// Work out where a dependency comes from, given its `registry`, `path`, or
+/// `git` options.
+pub fn make_source(options_pair: Pair, variables: &Variables) -> Result<Source, ::failure::Error> {
+    let registry = variables.string_option(options_pair.clone(), "registry")?;
+    let path = variables.string_option(options_pair.clone(), "path")?;
+    let git = variables.string_option(options_pair.clone(), "git")?;
+    let mut references = vec![];
+    if let Some(rev) = variables.string_option(options_pair.clone(), "rev")? {
+        references.push(GitReference::Rev(rev));
+    }
+    if let Some(branch) = variables.string_option(options_pair.clone(), "branch")? {
+        references.push(GitReference::Branch(branch));
+    }
+    if let Some(tag) = variables.string_option(options_pair.clone(), "tag")? {
+        references.push(GitReference::Tag(tag));
+    }
+
+    let error = |message: &str| {
+        Err(::failure::Error::from(
+            format_err!("{}", message).with_pair(&options_pair),
+        ))
+    };
+    let kinds = [registry.is_some(), path.is_some(), git.is_some()];
+    if kinds.iter().filter(|&&given| given).count() > 1 {
+        return error("Expected only one of `registry`, `path` or `git`");
+    }
+    if git.is_none() && !references.is_empty() {
+        return error("`rev`, `branch` and `tag` can only be used with `git`");
+    }
+    if references.len() > 1 {
+        return error("Expected only one of `rev`, `branch` or `tag`");
+    }
+
+    Ok(match (path, git) {
+        (Some(path), _) => Source::Path(PathBuf::from(path)),
+        (_, Some(url)) => Source::Git {
+            url,
+            reference: references.pop().unwrap_or(GitReference::DefaultBranch),
+        },
+        (None, None) => Source::Registry(registry),
+    })
+}
+
+pub fn make_dependency(
+    package_name_pair: Pair,
+    vcc_pairs: Vec<Pair>,
+    variables: &Variables,
+) -> Result<(PackageName, VersionConstraint), ::failure::Error> {
+    let package_name = PackageName::from_str(package_name_pair.as_str())
+        .ok_or_else(|| format_err!("Invalid package name").with_pair(&package_name_pair))?;
+
+    // e.g. [">=2.0.0", "<4.0.0"], or ["^${version}"]
+    let components = vcc_pairs
+        .iter()
+        .map(|vcc_pair| {
+            variables
+                .interpolate(vcc_pair.as_str())
+                .map_err(|error| error.with_pair(vcc_pair).with_code("unknown-variable"))
+        }).collect::<Result<Vec<_>, _>>()?;
+    let version_constraint = match components.len() {
+        0 => VersionConstraint::from_str("*"),
+        1 | 2 => VersionConstraint::from_str(&components.join(" ")),
+        _ => unreachable!(),
+    }.ok_or_else(|| {
+        format_err!("Invalid version constraint")
+            .with_pos(&vcc_pairs[0].clone().into_span().start_pos())
+    })?;
+
+    Ok((package_name, version_constraint))
+}
+
+/// The variables bound by the manifest's `let` fields:
+///
+/// ```text
+/// let version="1.2.0" registry="work"
+/// ```
+///
+/// Strings in the `dependencies`, `overrides` and `package` sections, and
+/// version constraints, can refer to them as `${version}`. A literal `${` is
+/// written `$${`. Later bindings can refer to earlier ones.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Variables(BTreeMap<String, String>);
+
+impl Variables {
+    pub fn from_manifest_pair(manifest_pair: Pair) -> Result<Variables, ManifestParserError> {
+        let mut variables = Variables::default();
+        let mut errors = ErrorCollector::new();
+        for (symbol_pair, arguments_pair) in get_fields(manifest_pair) {
+            if symbol_pair.as_str() != "let" {
+                continue;
+            }
+            let positional_arguments_pair =
+                find_rule(arguments_pair.clone(), Rule::positional_arguments);
+            let options_pair = find_rule(arguments_pair.clone(), Rule::options);
+            let option_pairs = children(options_pair, Rule::option);
+            if option_pairs.is_empty()
+                || !children(positional_arguments_pair, Rule::positional_argument).is_empty()
+                || find_optional_rule(arguments_pair.clone(), Rule::block).is_some()
+            {
+                errors.push(
+                    format_err!("Expected `let name=\"value\"`").with_pair(&arguments_pair),
+                );
+                continue;
+            }
+            for option_pair in option_pairs {
+                let name_pair = find_rule(option_pair.clone(), Rule::option_name);
+                let name = name_pair.as_str().to_string();
+                let value = match find_optional_rule(option_pair.clone(), Rule::option_value) {
+                    Some(value_pair) => variables.string(value_pair),
+                    None => Err(format_err!("Expected `{}=\"...\"`", name).with_pair(&option_pair)),
+                };
+                if let Some(value) = errors.collect(value) {
+                    if variables.0.contains_key(&name) {
+                        errors.push(
+                            format_err!("Variable `{}` is already defined", name)
+                                .with_pair(&name_pair)
+                                .with_code("duplicate-variable"),
+                        );
+                    } else {
+                        variables.0.insert(name, value);
+                    }
+                }
+            }
+        }
+        errors.finish()?;
+        Ok(variables)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(|value| value.as_str())
+    }
+
+    /// Replace each `${name}` in `text` with the value of the variable.
+    pub fn interpolate(&self, text: &str) -> Result<String, ::failure::Error> {
+        let mut out = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("${") {
+            if rest[..start].ends_with('$') {
+                // `$${` is an escaped `${`.
+                out.push_str(&rest[..start]);
+                out.push('{');
+                rest = &rest[start + 2..];
+                continue;
+            }
+            out.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format_err!("Expected `}}` to end `${{`"))?;
+            let name = &rest[start + 2..start + end];
+            match self.get(name) {
+                Some(value) => out.push_str(value),
+                None => {
+                    let names: Vec<&str> = self.0.keys().map(|name| name.as_str()).collect();
+                    return Err(match closest(name, &names) {
+                        Some(suggestion) => format_err!(
+                            "Unknown variable `{}`; did you mean `{}`?",
+                            name,
+                            suggestion
+                        ),
+                        None => format_err!("Unknown variable `{}`", name),
+                    });
+                }
+            }
+            rest = &rest[start + end + 1..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    /// Read a string, interpolating variables.
+    pub fn string(&self, pair: Pair) -> Result<String, ManifestParserError> {
+        let text = get_string(pair.clone())?;
+        self.interpolate(&text)
+            .map_err(|error| error.with_pair(&pair).with_code("unknown-variable"))
+    }
+
+    /// Read the string field called `field_name`, if there is one.
+    pub fn string_field(
+        &self,
+        block_pair: Pair,
+        field_name: &'static str,
+    ) -> Result<Option<String>, ManifestParserError> {
+        get_optional_field(block_pair, field_name).map_or(Ok(None), |arguments_pair| {
+            Ok(Some(self.string(Arguments::get_single(arguments_pair)?)?))
+        })
+    }
+
+    /// Read a list of strings, reporting every item which isn't a string.
+    pub fn string_list_field(
+        &self,
+        block_pair: Pair,
+        field_name: &'static str,
+    ) -> Result<Vec<String>, ManifestParserError> {
+        let mut errors = ErrorCollector::new();
+        let strings = get_optional_list_field(block_pair, field_name)?
+            .into_iter()
+            .filter_map(|item_pair| errors.collect(self.string(item_pair)))
+            .collect();
+        errors.finish()?;
+        Ok(strings)
+    }
+
+    /// Read the value of an option which is a string, or a list of strings,
+    /// such as `os="linux"` or `os=["linux" "macos"]`.
+    pub fn option_strings(&self, option_pair: Pair) -> Result<Vec<String>, ManifestParserError> {
+        let name = find_rule(option_pair.clone(), Rule::option_name).as_str().to_string();
+        let value_pair = find_optional_rule(option_pair.clone(), Rule::option_value)
+            .ok_or_else(|| format_err!("Expected `{}=\"...\"`", name).with_pair(&option_pair))?;
+        match find_optional_rule(value_pair.clone(), Rule::list) {
+            Some(_) => get_list(value_pair)?
+                .into_iter()
+                .map(|item_pair| self.string(item_pair))
+                .collect(),
+            None => Ok(vec![self.string(value_pair)?]),
+        }
+    }
+
+    /// Read the string option called `name`, if it's given.
+    pub fn string_option(
+        &self,
+        options_pair: Pair,
+        name: &'static str,
+    ) -> Result<Option<String>, ManifestParserError> {
+        match get_option(options_pair, name) {
+            None => Ok(None),
+            Some(option_pair) => match find_optional_rule(option_pair.clone(), Rule::option_value) {
+                Some(value_pair) => Ok(Some(self.string(value_pair)?)),
+                None => Err(format_err!("Expected `{} = \"...\"`", name).with_pair(&option_pair)),
+            },
+        }
+    }
+}
+
+/// The options `if` blocks can test.
+const CONDITION_KEYS: &'static [&'static str] = &["os", "arch", "family"];
+
+/// The platform `if` blocks in the manifest are tested against, as named by
+/// Rust's `std::env::consts`: `os="linux"`, `arch="x86_64"`, `family="unix"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Platform {
+    pub os: String,
+    pub arch: String,
+    pub family: String,
+}
+
+impl Platform {
+    pub fn current() -> Platform {
+        Platform {
+            os: env::consts::OS.to_string(),
+            arch: env::consts::ARCH.to_string(),
+            family: env::consts::FAMILY.to_string(),
+        }
+    }
+
+    /// Whether the platform meets every condition given as an option of an
+    /// `if` field. A condition can be a string, or a list of strings of which
+    /// any may match, such as `os=["linux" "macos"]`.
+    fn satisfies(
+        &self,
+        options_pair: Pair,
+        variables: &Variables,
+    ) -> Result<bool, ManifestParserError> {
+        let option_pairs = children(options_pair.clone(), Rule::option);
+        if option_pairs.is_empty() {
+            return Err(format_err!("Expected a condition, such as `os=\"linux\"`")
+                .with_pair(&options_pair));
+        }
+        let mut holds = true;
+        for option_pair in option_pairs {
+            let key = find_rule(option_pair.clone(), Rule::option_name).as_str().to_string();
+            let values = variables.option_strings(option_pair)?;
+            let actual = match key.as_str() {
+                "os" => &self.os,
+                "arch" => &self.arch,
+                _ => &self.family,
+            };
+            holds = holds && values.contains(actual);
+        }
+        Ok(holds)
+    }
+}
+
+/// The targets on which the condition given as the options of an `if` field
+/// holds, if it only tests one of `os`, `arch` and `family`.
+fn targets_of(
+    options_pair: Pair,
+    variables: &Variables,
+) -> Result<BTreeSet<String>, ManifestParserError> {
+    let option_pairs = children(options_pair.clone(), Rule::option);
+    match option_pairs.len() {
+        0 => Err(format_err!("Expected a condition, such as `os=\"linux\"`")
+            .with_pair(&options_pair)),
+        1 => Ok(variables.option_strings(option_pairs[0].clone())?.into_iter().collect()),
+        _ => Err(format_err!(
+            "Conditions on more than one of `os`, `arch` and `family` can't be published; \
+             use a `target` option instead"
+        ).with_pair(&options_pair)
+        .with_code("unpublishable-condition")),
+    }
+}
+
+pub fn evaluate_files_block(
+    files_block_pair: Pair,
+    root: &Path,
+    scm: &Scm,
+    variables: &Variables,
+) -> Result<Vec<String>, ::failure::Error> {
+    let mut file_section_interpreter = FilesSectionInterpreter::new(root.to_path_buf(), scm)?;
+    let mut file_set = HashSet::<String>::new();
+    for (symbol_pair, arguments_pair) in get_fields(files_block_pair) {
+        match symbol_pair.as_str() {
+            "add_committed" => {
+                let glob_pair = Arguments::get_single(arguments_pair)?;
+                let glob = variables.string(glob_pair.clone())?;
+
+                file_section_interpreter
+                    .add_committed(&mut file_set, &glob)
+                    .pair_context(&glob_pair)?;
+            }
+            "add_uncommitted" => {
+                let glob_pair = Arguments::get_single(arguments_pair)?;
+                let glob = variables.string(glob_pair.clone())?;
+
+                file_section_interpreter
+                    .add_uncommitted(&mut file_set, &glob)
+                    .pair_context(&glob_pair)?;
+            }
+            "remove" => {
+                let glob_pair = Arguments::get_single(arguments_pair)?;
+                let glob = variables.string(glob_pair.clone())?;
+
+                file_section_interpreter
+                    .remove(&mut file_set, &glob)
+                    .pair_context(&glob_pair)?;
+            }
+            _ => {
+                return Err(::failure::Error::from(
+                    format_err!("Expected `add_committed`, `add_uncommitted`, or `remove`")
+                        .with_pair(&symbol_pair),
+                ));
+            }
+        }
+    }
+    let mut file_set_vec: Vec<String> = file_set.into_iter().collect();
+    file_set_vec.sort_unstable();
+    Ok(file_set_vec)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use files::NoScm;
+    use pm_lib::test_helpers::{pkg, TempDir};
+    use std::io::Write;
+
+    const MANIFEST: &'static str = r#"
+package {
+    name "test/pad"
+    version "1.0.0"
+    description "Pads things."
+    authors ["Ada <ada@example.com>"]
+    homepage "https://example.com/pad"
+    license "MIT"
+    files {
+        add_uncommitted "**"
+    }
+}
+"#;
+
+    fn write(path: &Path, contents: &str) {
+        File::create(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .unwrap();
+    }
+
+    fn linux() -> Platform {
+        Platform {
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            family: "unix".to_string(),
+        }
+    }
+
+    #[test]
+    fn interpolates_variables() {
+        let manifest_pair = parse_and_check_manifest(
+            "let pad=\"1.2.0\"\nlet next=\"${pad}-beta\" literal=\"$${pad}\"\n".to_string(),
+        ).unwrap();
+        let variables = Variables::from_manifest_pair(manifest_pair).unwrap();
+        assert_eq!(variables.get("next"), Some("1.2.0-beta"));
+        assert_eq!(variables.get("literal"), Some("${pad}"));
+        assert_eq!(
+            variables.interpolate("^${pad} <${next}").unwrap(),
+            "^1.2.0 <1.2.0-beta"
+        );
+        assert_eq!(
+            variables.interpolate("${pda}").unwrap_err().to_string(),
+            "Unknown variable `pda`; did you mean `pad`?"
+        );
+    }
+
+    #[test]
+    fn reads_conditional_dependencies() {
+        let manifest_pair = parse_and_check_manifest(
+            r#"let pad="1.2.0"
+dependencies {
+    test/left-pad ^${pad}
+    if os=["linux" "macos"] {
+        test/right-pad ^${pad} git="https://example.com/${pad}"
+        if arch="arm" {
+            test/arm-pad ^1
+        }
+    }
+    if os="windows" {
+        test/win-pad ^1
+    }
+    test/mac-pad ^1 target=["macos" "ios"]
+}
+"#.to_string(),
+        ).unwrap();
+        let (dependencies, sources) = get_dependencies_for(manifest_pair, &linux()).unwrap();
+        assert_eq!(
+            dependencies.keys().cloned().collect::<Vec<_>>(),
+            vec![pkg("left-pad"), pkg("mac-pad"), pkg("right-pad")]
+        );
+        assert_eq!(
+            dependencies[&pkg("mac-pad")].targets,
+            vec!["ios".to_string(), "macos".to_string()].into_iter().collect()
+        );
+        assert_eq!(
+            dependencies[&pkg("left-pad")],
+            Dependency::new(VersionConstraint::from_str("^1.2.0").unwrap())
+        );
+        assert_eq!(
+            sources[&pkg("right-pad")],
+            Source::Git {
+                url: "https://example.com/1.2.0".to_string(),
+                reference: GitReference::DefaultBranch,
+            }
+        );
+
+        // Entries are checked even if their condition doesn't hold.
+        let manifest_pair = parse_and_check_manifest(
+            "dependencies {\n    if os=\"windows\" {\n        test/win-pad ^${nope}\n    }\n}\n"
+                .to_string(),
+        ).unwrap();
+        assert!(get_dependencies_for(manifest_pair, &linux()).is_err());
+    }
+
+    #[test]
+    fn publishes_conditions_as_targets() {
+        let published = |manifest_source: &str| {
+            get_published_dependencies(
+                parse_and_check_manifest(manifest_source.to_string()).unwrap(),
+            )
+        };
+        let (dependencies, _) = published(
+            r#"dependencies {
+    test/left-pad ^1
+    if os=["linux" "macos"] {
+        test/right-pad ^1
+    }
+    if os="windows" {
+        test/win-pad ^1
+    }
+}
+"#,
+        ).unwrap();
+        assert_eq!(
+            dependencies.keys().cloned().collect::<Vec<_>>(),
+            vec![pkg("left-pad"), pkg("right-pad"), pkg("win-pad")]
+        );
+        assert!(dependencies[&pkg("left-pad")].targets.is_empty());
+        assert_eq!(
+            dependencies[&pkg("right-pad")].targets,
+            vec!["linux".to_string(), "macos".to_string()].into_iter().collect()
+        );
+        assert_eq!(
+            dependencies[&pkg("win-pad")].targets,
+            vec!["windows".to_string()].into_iter().collect()
+        );
+
+        // Conditions which targets can't express are refused.
+        let unpublishable = [
+            "if os=\"linux\" arch=\"arm\" {\n test/pad ^1\n }",
+            "if os=\"linux\" {\n if arch=\"arm\" {\n test/pad ^1\n }\n }",
+            "if os=\"linux\" {\n test/pad ^1 target=\"arm\"\n }",
+        ];
+        for entry in unpublishable.iter() {
+            assert!(published(&format!("dependencies {{\n    {}\n}}\n", entry)).is_err());
+        }
+    }
+
+    #[test]
+    fn reads_metadata_and_finds_readmes() {
+        let temp = TempDir::new();
+        let root = temp.path();
+        write(&root.join("readme.txt"), "Plain.");
+        write(&root.join("Readme.md"), "# Pad");
+        let detected = Manifest::from_str(MANIFEST.to_string(), &root, &NoScm);
+        let named = Manifest::from_str(
+            MANIFEST.replace("license \"MIT\"", "license \"MIT\"\n    readme \"readme.txt\""),
+            &root,
+            &NoScm,
+        );
+        let missing = Manifest::from_str(
+            MANIFEST.replace("license \"MIT\"", "license \"MIT\"\n    readme \"NOPE\""),
+            &root,
+            &NoScm,
+        );
+
+        let detected = detected.unwrap();
+        assert_eq!(detected.authors, vec!["Ada <ada@example.com>".to_string()]);
+        assert_eq!(detected.homepage, Some("https://example.com/pad".to_string()));
+        assert_eq!(
+            detected.readme,
+            Some(("Readme.md".to_string(), "# Pad".to_string()))
+        );
+        assert_eq!(
+            named.unwrap().readme,
+            Some(("readme.txt".to_string(), "Plain.".to_string()))
+        );
+        assert!(missing.is_err());
+
+        // A README that isn't UTF-8 can't be read, but doesn't stop the
+        // manifest from loading.
+        let temp = TempDir::new();
+        File::create(temp.join("README"))
+            .and_then(|mut file| file.write_all(&[0xff, 0xfe]))
+            .unwrap();
+        let unreadable = Manifest::from_str(MANIFEST.to_string(), temp.path(), &NoScm);
+        assert_eq!(unreadable.unwrap().readme, None);
+        // Unless it was asked for by name.
+        let named = Manifest::from_str(
+            MANIFEST.replace("license \"MIT\"", "license \"MIT\"\n    readme \"README\""),
+            temp.path(),
+            &NoScm,
+        );
+        assert!(named.is_err());
+    }
+
+    #[test]
+    fn interpolates_file_globs_and_workspace_members() {
+        let temp = TempDir::new();
+        let root = temp.path();
+        fs::create_dir(root.join("src")).unwrap();
+        write(&root.join("src/pad.js"), "pad();");
+        write(&root.join("notes.txt"), "Notes.");
+        let manifest = Manifest::from_str(
+            format!(
+                "let dir=\"src\"\n{}",
+                MANIFEST.replace("add_uncommitted \"**\"", "add_uncommitted \"${dir}/**\"")
+            ),
+            &root,
+            &NoScm,
+        );
+        assert_eq!(manifest.unwrap().files, vec!["src/pad.js".to_string()]);
+
+        let manifest_pair = parse_and_check_manifest(
+            "let dir=\"packages\"\nworkspace {\n    members [\"${dir}/core\"]\n}\n".to_string(),
+        ).unwrap();
+        assert_eq!(
+            get_workspace_members(manifest_pair).unwrap(),
+            Some(vec![PathBuf::from("packages/core")])
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use std::fmt;
+
+use manifest_parser::{parse_manifest, Pair, Rule};
+use manifest_parser_error::ManifestParserError;
+
+/// A lossless concrete syntax tree of a manifest. Unlike the pest pairs it is
+/// built from, it keeps every byte of the source, including the punctuation
+/// between rules, so it can be edited and written back out without disturbing
+/// the comments, blank lines and layout around the edit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// A pair matched by a grammar rule.
+    Rule(Rule, Vec<Node>),
+    /// Text which isn't part of any child pair, such as a `{` or `"`.
+    Text(String),
+}
+
+impl Node {
+    pub fn parse(manifest_source: String) -> Result<Node, ManifestParserError> {
+        Ok(Node::from_pair(parse_manifest(manifest_source)?))
+    }
+
+    pub fn from_pair(pair: Pair) -> Node {
+        let rule = pair.as_rule();
+        let span = pair.clone().into_span();
+        let text = span.as_str();
+        let start = span.start();
+        let mut position = start;
+        let mut children = vec![];
+        for child_pair in pair.into_inner() {
+            let child_span = child_pair.clone().into_span();
+            if child_span.start() > position {
+                children.push(Node::Text(
+                    text[position - start..child_span.start() - start].to_string(),
+                ));
+            }
+            position = child_span.end();
+            children.push(Node::from_pair(child_pair));
+        }
+        if span.end() > position {
+            children.push(Node::Text(text[position - start..].to_string()));
+        }
+        Node::Rule(rule, children)
+    }
+
+    pub fn rule(&self) -> Option<Rule> {
+        match self {
+            &Node::Rule(rule, _) => Some(rule),
+            &Node::Text(_) => None,
+        }
+    }
+
+    pub fn children(&self) -> &[Node] {
+        match self {
+            &Node::Rule(_, ref children) => children,
+            &Node::Text(_) => &[],
+        }
+    }
+
+    pub fn children_mut(&mut self) -> &mut Vec<Node> {
+        match self {
+            &mut Node::Rule(_, ref mut children) => children,
+            &mut Node::Text(_) => panic!("text nodes have no children"),
+        }
+    }
+
+    /// The first child matched by `rule`.
+    pub fn child(&self, rule: Rule) -> Option<&Node> {
+        self.children()
+            .iter()
+            .find(|child| child.rule() == Some(rule))
+    }
+
+    pub fn child_mut(&mut self, rule: Rule) -> Option<&mut Node> {
+        self.children_mut()
+            .iter_mut()
+            .find(|child| child.rule() == Some(rule))
+    }
+
+    /// The first node matched by `rule` anywhere below this one, depth first.
+    pub fn find(&self, rule: Rule) -> Option<&Node> {
+        self.children().iter().filter_map(|child| {
+            if child.rule() == Some(rule) {
+                Some(child)
+            } else {
+                child.find(rule)
+            }
+        }).next()
+    }
+
+    fn write_to(&self, out: &mut String) {
+        match self {
+            &Node::Rule(_, ref children) => {
+                for child in children {
+                    child.write_to(out);
+                }
+            }
+            &Node::Text(ref text) => out.push_str(text),
+        }
+    }
+}
+
+/// Writes the source the node was parsed from, with any edits made since.
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let mut out = String::new();
+        self.write_to(&mut out);
+        f.write_str(&out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let source = r#"// A comment before anything.
+dependencies {  // trailing comment
+    js/left-pad ^1.0.0 registry="work"
+
+    js/right-pad >=1.0.0 <2.0.0   // extra spaces
+}
+package {
+    name "js/pad"
+    keywords ["pad"
+        "left" ]
+}
+"#;
+        let node = Node::parse(source.to_string()).unwrap();
+        assert_eq!(node.to_string(), source);
+        assert_eq!(
+            node.find(Rule::symbol).map(|symbol| symbol.to_string()),
+            Some("dependencies".to_string())
+        );
+    }
+}
@@ -0,0 +1,353 @@
+use manifest_cst::Node;
+use manifest_parser::Rule;
+use manifest_parser_error::ManifestParserError;
+
+/// The indentation of each level of blocks and lists.
+const INDENT: &'static str = "    ";
+
+/// Blocks whose entries are packages, which are sorted and aligned.
+const PACKAGE_BLOCKS: &'static [&'static str] = &["dependencies", "overrides"];
+
+/// Pretty-print a manifest in the canonical format:
+///
+/// * Blocks and multi-line lists are indented by four spaces per level, and
+///   arguments, options and list items are separated by single spaces.
+/// * Runs of blank lines become a single blank line, and blank lines at the
+///   start and end of blocks are removed.
+/// * The entries of `dependencies` and `overrides` blocks are sorted, within
+///   each group of entries separated by blank lines, and their constraints are
+///   lined up. `if` blocks among them go after the packages, and are formatted
+///   the same way.
+///
+/// Comments stay attached to what they're on the line of or directly above.
+pub fn format_manifest(manifest_source: String) -> Result<String, ManifestParserError> {
+    let manifest = Node::parse(manifest_source)?;
+    let mut out = String::new();
+    write_fields(
+        &mut out,
+        manifest
+            .child(Rule::fields_not_newline_terminated)
+            .expect("manifests consist of fields"),
+        0,
+        false,
+    );
+    Ok(out)
+}
+
+/// A field, with the comment lines directly above it, or comment lines which
+/// aren't above a field if `field` is `None`.
+struct Entry<'a> {
+    comments: Vec<String>,
+    field: Option<&'a Node>,
+}
+
+/// Write the fields of a block, or of the whole manifest, one per line.
+fn write_fields(out: &mut String, fields: &Node, depth: usize, package_block: bool) {
+    // Groups of entries, separated by blank lines.
+    let mut groups: Vec<Vec<Entry>> = vec![vec![]];
+    let mut comments = vec![];
+    for node in fields.children() {
+        match node.rule() {
+            Some(Rule::field) => {
+                groups.last_mut().expect("there's always a group").push(Entry {
+                    comments: comments.split_off(0),
+                    field: Some(node),
+                });
+            }
+            Some(Rule::blank_line) => match node.child(Rule::comment_) {
+                Some(comment) => comments.push(comment.to_string()),
+                None => {
+                    if !comments.is_empty() {
+                        groups.last_mut().expect("there's always a group").push(Entry {
+                            comments: comments.split_off(0),
+                            field: None,
+                        });
+                    }
+                    groups.push(vec![]);
+                }
+            },
+            _ => {}
+        }
+    }
+    if !comments.is_empty() {
+        groups.last_mut().expect("there's always a group").push(Entry {
+            comments,
+            field: None,
+        });
+    }
+    groups.retain(|group| !group.is_empty());
+
+    let name_width = if package_block {
+        groups
+            .iter()
+            .flat_map(|group| group.iter())
+            .filter_map(|entry| entry.field)
+            .filter(|&field| !is_condition(field))
+            .map(|field| field_name(field).len())
+            .max()
+    } else {
+        None
+    };
+    for (i, group) in groups.iter_mut().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        if package_block {
+            // Comments which aren't above a field stay at the end, after any
+            // `if` blocks.
+            group.sort_by_key(|entry| {
+                (
+                    entry.field.is_none(),
+                    entry.field.map(is_condition),
+                    entry.field.map(field_name),
+                )
+            });
+        }
+        for entry in group.iter() {
+            for comment in &entry.comments {
+                out.push_str(&format!("{}{}\n", indent(depth), comment));
+            }
+            if let Some(field) = entry.field {
+                write_field(out, field, depth, name_width, package_block);
+            }
+        }
+    }
+}
+
+/// Write a field on its own line (or more, for blocks and multi-line lists),
+/// padding its name to `name_width` if it has arguments.
+fn write_field(
+    out: &mut String,
+    field: &Node,
+    depth: usize,
+    name_width: Option<usize>,
+    package_block: bool,
+) {
+    let name = field_name(field);
+    let condition = package_block && is_condition(field);
+    let arguments = format_arguments(
+        field.child(Rule::arguments).expect("fields have arguments"),
+        depth,
+        (PACKAGE_BLOCKS.contains(&name.as_str()) && depth == 0) || condition,
+    );
+    out.push_str(&indent(depth));
+    out.push_str(&name);
+    if !arguments.is_empty() {
+        let padding = match name_width {
+            Some(width) if !condition => width - name.len(),
+            _ => 0,
+        };
+        out.push_str(&" ".repeat(padding + 1));
+        out.push_str(&arguments);
+    }
+    if let Some(comment) = field.child(Rule::comment_) {
+        out.push(' ');
+        out.push_str(&comment.to_string());
+    }
+    out.push('\n');
+}
+
+fn format_arguments(arguments: &Node, depth: usize, package_block: bool) -> String {
+    let mut parts = vec![];
+    let positional_arguments = arguments
+        .child(Rule::positional_arguments)
+        .expect("arguments have positional arguments");
+    for argument in positional_arguments.children() {
+        if argument.rule() == Some(Rule::positional_argument) {
+            parts.push(format_value(argument, depth));
+        }
+    }
+    let options = arguments.child(Rule::options).expect("arguments have options");
+    for option in options.children() {
+        if option.rule() == Some(Rule::option) {
+            let name = option
+                .child(Rule::option_name)
+                .expect("options have a name")
+                .to_string();
+            parts.push(match option.child(Rule::option_value) {
+                Some(value) => format!("{}={}", name, format_value(value, depth)),
+                None => name,
+            });
+        }
+    }
+    if let Some(block) = arguments.child(Rule::block) {
+        parts.push(format_block(block, depth, package_block));
+    }
+    parts.join(" ")
+}
+
+/// Format a node wrapping a single value: a string, list, block or version
+/// constraint component.
+fn format_value(node: &Node, depth: usize) -> String {
+    let value = node
+        .children()
+        .iter()
+        .find(|child| child.rule().is_some())
+        .expect("values have a child");
+    match value.rule() {
+        Some(Rule::list) => format_list(value, depth),
+        Some(Rule::block) => format_block(value, depth, false),
+        _ => value.to_string(),
+    }
+}
+
+fn format_block(block: &Node, depth: usize, package_block: bool) -> String {
+    let mut out = "{".to_string();
+    if let Some(comment) = block.child(Rule::comment_) {
+        out.push(' ');
+        out.push_str(&comment.to_string());
+    }
+    out.push('\n');
+    write_fields(
+        &mut out,
+        block
+            .child(Rule::fields_newline_terminated)
+            .expect("blocks have fields"),
+        depth + 1,
+        package_block,
+    );
+    out.push_str(&indent(depth));
+    out.push('}');
+    out
+}
+
+/// Format a list on one line if it was written on one line, and otherwise
+/// with each line of items indented on its own line, between the brackets.
+fn format_list(list: &Node, depth: usize) -> String {
+    // The items on each line, and the comment at its end.
+    let mut lines = vec![(vec![], None)];
+    for child in list.children() {
+        match child.rule() {
+            Some(Rule::list_item) => lines
+                .last_mut()
+                .expect("there's always a line")
+                .0
+                .push(format_value(child, depth + 1)),
+            Some(Rule::whitespace_with_newline) => {
+                if let Some(comment) = child.child(Rule::comment_) {
+                    lines.last_mut().expect("there's always a line").1 = Some(comment.to_string());
+                }
+                if child.child(Rule::newline).is_some() {
+                    lines.push((vec![], None));
+                }
+            }
+            _ => {}
+        }
+    }
+    if lines.len() == 1 {
+        return format!("[{}]", lines[0].0.join(" "));
+    }
+    let mut out = "[\n".to_string();
+    for (items, comment) in lines {
+        let line = match (items.is_empty(), comment) {
+            (true, Some(comment)) => comment,
+            (false, Some(comment)) => format!("{} {}", items.join(" "), comment),
+            (_, None) => items.join(" "),
+        };
+        if !line.is_empty() {
+            out.push_str(&format!("{}{}\n", indent(depth + 1), line));
+        }
+    }
+    out.push_str(&indent(depth));
+    out.push(']');
+    out
+}
+
+fn field_name(field: &Node) -> String {
+    field
+        .child(Rule::symbol)
+        .expect("fields have a symbol")
+        .to_string()
+}
+
+/// Whether the field is an `if` block, rather than a package.
+fn is_condition(field: &Node) -> bool {
+    field_name(field) == "if"
+}
+
+fn indent(depth: usize) -> String {
+    INDENT.repeat(depth)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formats_manifests() {
+        let source = r#"
+
+// Dependencies.
+dependencies {   // keep me
+  js/right-pad   ^2.0.0 registry = "work"
+   // Pads on the left.
+  js/left-pad ^1.0.0  // trailing
+
+
+
+  // Unattached comment.
+
+  js/up ^1.0.0
+      js/down >=1.0.0   <2.0.0
+}
+package {
+        name "js/pad"
+  keywords [ "pad"   "left" ]
+    files {
+      add_committed ["index.js"
+         // Docs too.
+         "README.md"]
+    }
+}
+
+
+"#;
+        let expected = r#"// Dependencies.
+dependencies { // keep me
+    // Pads on the left.
+    js/left-pad  ^1.0.0 // trailing
+    js/right-pad ^2.0.0 registry="work"
+
+    // Unattached comment.
+
+    js/down      >=1.0.0 <2.0.0
+    js/up        ^1.0.0
+}
+package {
+    name "js/pad"
+    keywords ["pad" "left"]
+    files {
+        add_committed [
+            "index.js"
+            // Docs too.
+            "README.md"
+        ]
+    }
+}
+"#;
+        let formatted = format_manifest(source.to_string()).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format_manifest(formatted).unwrap(), expected);
+    }
+
+    #[test]
+    fn formats_conditional_dependencies() {
+        let source = r#"dependencies {
+  if os="linux" {
+    js/b ^1
+      js/a ^1
+  }
+  js/zz ^1
+}
+"#;
+        let expected = r#"dependencies {
+    js/zz ^1
+    if os="linux" {
+        js/a ^1
+        js/b ^1
+    }
+}
+"#;
+        assert_eq!(format_manifest(source.to_string()).unwrap(), expected);
+    }
+}
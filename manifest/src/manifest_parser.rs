@@ -1,9 +1,8 @@
-#![allow(dead_code, unused_variables, unused_assignments)]
-
 use pest;
 use pest::Parser;
+use std::cmp::{max, min};
 
-use manifest_parser_error::{ManifestParserError, PestErrorExt};
+use manifest_parser_error::{ErrorCollector, ManifestParserError, PestErrorExt};
 
 // Ensure this file recompiles when the grammar is modified.
 const _GRAMMAR: &'static str = include_str!("grammar.pest");
@@ -27,12 +26,22 @@ pub fn parse_manifest(manifest_source: String) -> Result<Pair, ManifestParserErr
 pub fn check_block_fields(
     block_pair: Pair,
     fields: &'static [&'static str],
+) -> Result<(), ManifestParserError> {
+    check_repeatable_block_fields(block_pair, fields, &[])
+}
+
+/// Check that there are no unexpected fields, or duplicates of fields other
+/// than the `repeatable` ones.
+pub fn check_repeatable_block_fields(
+    block_pair: Pair,
+    fields: &'static [&'static str],
+    repeatable: &'static [&'static str],
 ) -> Result<(), ManifestParserError> {
     let symbol_pairs = get_fields(block_pair)
         .into_iter()
         .map(|(symbol_pair, _arguments_pair)| symbol_pair)
         .collect::<Vec<_>>();
-    check_keys(&symbol_pairs, "field", fields)
+    check_keys(&symbol_pairs, "field", fields, repeatable)
 }
 
 /// Check that there are no unexpected or duplicate options.
@@ -44,32 +53,83 @@ pub fn check_option_names(
         .into_iter()
         .map(|option_pair| find_rule(option_pair, Rule::option_name))
         .collect::<Vec<_>>();
-    check_keys(&name_pairs, "option", names)
+    check_keys(&name_pairs, "option", names, &[])
 }
 
 // Helper function for block and option checking. `element_type` is "field" or
-// "option".
+// "option". Every unexpected or duplicate key is reported, and unexpected keys
+// come with a suggestion if one of `names` is close enough to be a typo.
 fn check_keys(
     name_pairs: &[Pair],
     element_type: &'static str,
     names: &'static [&'static str],
+    repeatable: &'static [&'static str],
 ) -> Result<(), ManifestParserError> {
+    let mut errors = ErrorCollector::new();
     let mut seen = vec![false; names.len()];
-    'pair_loop: for name_pair in name_pairs {
+    for name_pair in name_pairs {
         let name = name_pair.as_str();
-        for i in 0..names.len() {
-            if name == names[i] {
-                if seen[i] {
-                    return Err(format_err!("Duplicate {}", element_type).with_pair(&name_pair));
-                } else {
-                    seen[i] = true;
-                    continue 'pair_loop;
-                }
+        match names.iter().position(|&expected| expected == name) {
+            Some(i) if seen[i] && !repeatable.contains(&name) => errors.push(
+                format_err!("Duplicate {} `{}`", element_type, name)
+                    .with_pair(&name_pair)
+                    .with_code(if element_type == "field" {
+                        "duplicate-field"
+                    } else {
+                        "duplicate-option"
+                    }),
+            ),
+            Some(i) => seen[i] = true,
+            None => {
+                let error = match closest(name, names) {
+                    Some(suggestion) => format_err!(
+                        "Unexpected {} `{}`; did you mean `{}`?",
+                        element_type,
+                        name,
+                        suggestion
+                    ),
+                    None => format_err!("Unexpected {} `{}`", element_type, name),
+                };
+                errors.push(error.with_pair(&name_pair).with_code(
+                    if element_type == "field" {
+                        "unknown-field"
+                    } else {
+                        "unknown-option"
+                    },
+                ));
             }
         }
-        return Err(format_err!("Unexpected {}", element_type).with_pair(&name_pair));
     }
-    Ok(())
+    errors.finish()
+}
+
+/// The name in `names` closest to `name`, if it's within a third of the length
+/// of `name` (and at least one edit) of it.
+pub fn closest<'a>(name: &str, names: &[&'a str]) -> Option<&'a str> {
+    let threshold = max(1, name.chars().count() / 3);
+    names
+        .iter()
+        .map(|&candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= threshold)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..b.len() + 1).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == b_char { 0 } else { 1 };
+            let insertion = current[j] + 1;
+            let deletion = previous[j + 1] + 1;
+            current.push(min(substitution, min(insertion, deletion)));
+        }
+        previous = current;
+    }
+    previous[b.len()]
 }
 
 pub fn get_option(options_pair: Pair, name: &'static str) -> Option<Pair> {
@@ -81,22 +141,6 @@ pub fn get_option(options_pair: Pair, name: &'static str) -> Option<Pair> {
     None
 }
 
-pub fn get_flag_option(
-    options_pair: Pair,
-    name: &'static str,
-) -> Result<bool, ManifestParserError> {
-    if let Some(option_pair) = get_option(options_pair, name) {
-        if find_optional_rule(option_pair.clone(), Rule::option_value).is_some() {
-            Err(format_err!("Unexpected value")
-                .with_span(&find_rule(option_pair, Rule::equal).into_span()))
-        } else {
-            Ok(true)
-        }
-    } else {
-        Ok(false)
-    }
-}
-
 // Return an arguments pair or an error if the field is missing.
 pub fn get_field(block_pair: Pair, field_name: &'static str) -> Result<Pair, ManifestParserError> {
     get_optional_field(block_pair.clone(), field_name)
@@ -226,16 +270,6 @@ pub fn get_optional_list_field(
     })
 }
 
-pub fn get_optional_string_field(
-    block_pair: Pair,
-    field_name: &'static str,
-) -> Result<Option<String>, ManifestParserError> {
-    get_optional_field(block_pair, field_name).map_or(Ok(None), |arguments_pair| {
-        let argument_pair = Arguments::get_single(arguments_pair)?;
-        Ok(Some(get_string(argument_pair)?))
-    })
-}
-
 pub fn get_fields(block_pair: Pair) -> Vec<(Pair, Pair)> {
     let fields_pair = find_optional_rule(block_pair.clone(), Rule::fields_newline_terminated)
         .unwrap_or_else(|| find_rule(block_pair.clone(), Rule::fields_not_newline_terminated));
@@ -265,14 +299,6 @@ pub fn get_list(pair: Pair) -> Result<Vec<Pair>, ManifestParserError> {
     Err(format_err!("Expected list").with_pair(&pair))
 }
 
-pub fn get_version_constraint_component(pair: Pair) -> Result<String, ManifestParserError> {
-    for vcc_pair in children(pair.clone(), Rule::version_constraint_component) {
-        let version_constraint_component = vcc_pair.as_str().to_string();
-        return Ok(version_constraint_component);
-    }
-    Err(format_err!("Expected version range").with_pair(&pair))
-}
-
 pub fn parse_string(string_pair: Pair) -> Result<String, ManifestParserError> {
     let mut s = "".to_string();
     for pair in string_pair.into_inner() {
@@ -321,3 +347,46 @@ pub fn find_optional_rule(pair: Pair, rule: Rule) -> Option<Pair> {
     pair.into_inner()
         .find(|child_pair| child_pair.as_rule() == rule)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FIELDS: &'static [&'static str] = &["dependencies", "license", "name"];
+
+    fn check(manifest_source: &str) -> Result<(), ManifestParserError> {
+        check_block_fields(parse_manifest(manifest_source.to_string())?, FIELDS)
+    }
+
+    #[test]
+    fn suggests_close_fields() {
+        assert_eq!(closest("licence", FIELDS), Some("license"));
+        assert_eq!(closest("dependancies", FIELDS), Some("dependencies"));
+        assert_eq!(closest("nam", FIELDS), Some("name"));
+        assert_eq!(closest("version", FIELDS), None);
+    }
+
+    #[test]
+    fn reports_every_bad_field() {
+        let error = check("name \"a\"\nlicence \"MIT\"\nname \"b\"\nversion \"1\"\n").unwrap_err();
+        let diagnostics = error.diagnostics(::std::path::Path::new("manifest.pm"));
+        let summary: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.column, d.code, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    Some(2),
+                    Some(1),
+                    "unknown-field",
+                    "Unexpected field `licence`; did you mean `license`?",
+                ),
+                (Some(3), Some(1), "duplicate-field", "Duplicate field `name`"),
+                (Some(4), Some(1), "unknown-field", "Unexpected field `version`"),
+            ]
+        );
+        assert!(check("name \"a\"\nlicense \"MIT\"\n").is_ok());
+    }
+}
@@ -0,0 +1,339 @@
+use failure;
+use pest;
+use std::convert::From;
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+
+use manifest_parser::Rule;
+
+// We'd like to store original Pest errors in this error type, but Pest errors
+// don't implement Send (due to Rc fields), which the Fail trait requires. So as
+// a workaround, we stringify them on instantiation.
+#[derive(Fail, Debug)]
+pub enum ManifestParserError {
+    #[fail(display = "{}", description)]
+    PestError {
+        description: String,
+        message: String,
+        location: Location,
+    },
+    #[fail(display = "{}", description)]
+    ErrorAtSpan {
+        description: String,
+        // We'd like to tag the original_error as #[cause], but failure::Error
+        // doesn't implement std::error::Error (yet?).
+        original_error: failure::Error,
+        location: Location,
+        code: &'static str,
+    },
+    #[fail(display = "{}", description)]
+    ErrorAtPos {
+        description: String,
+        original_error: failure::Error,
+        location: Location,
+        code: &'static str,
+    },
+    /// Everything found wrong with a manifest in one pass.
+    #[fail(display = "{}", _0)]
+    Multiple(Errors),
+    /// An error which doesn't come from a particular place in the manifest.
+    #[fail(display = "{}", _0)]
+    Other(failure::Error),
+}
+
+impl ManifestParserError {
+    /// Tag the error with a code, by which tools can tell kinds of errors
+    /// apart. Errors are tagged `invalid-manifest` otherwise.
+    pub fn with_code(mut self, new_code: &'static str) -> Self {
+        match self {
+            ManifestParserError::ErrorAtSpan { ref mut code, .. }
+            | ManifestParserError::ErrorAtPos { ref mut code, .. } => *code = new_code,
+            _ => {}
+        }
+        self
+    }
+
+    /// Machine-readable descriptions of the errors, which were found in
+    /// `file`.
+    pub fn diagnostics(&self, file: &Path) -> Vec<Diagnostic> {
+        let diagnostic = |location: Option<&Location>, code, message: String| Diagnostic {
+            file: file.to_path_buf(),
+            line: location.map(|location| location.line),
+            column: location.map(|location| location.column),
+            code,
+            message,
+        };
+        match self {
+            &ManifestParserError::PestError {
+                ref message,
+                ref location,
+                ..
+            } => vec![diagnostic(Some(location), "syntax-error", message.clone())],
+            &ManifestParserError::ErrorAtSpan {
+                ref original_error,
+                ref location,
+                code,
+                ..
+            }
+            | &ManifestParserError::ErrorAtPos {
+                ref original_error,
+                ref location,
+                code,
+                ..
+            } => vec![diagnostic(Some(location), code, original_error.to_string())],
+            &ManifestParserError::Multiple(ref errors) => errors
+                .0
+                .iter()
+                .flat_map(|error| error.diagnostics(file))
+                .collect(),
+            &ManifestParserError::Other(ref error) => {
+                vec![diagnostic(None, "invalid-manifest", error.to_string())]
+            }
+        }
+    }
+}
+
+/// Where in the manifest an error is, counting from 1.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl<'a, I> From<&'a pest::inputs::Position<I>> for Location
+where
+    I: pest::inputs::Input,
+{
+    fn from(pos: &pest::inputs::Position<I>) -> Self {
+        let (line, column) = pos.line_col();
+        Location { line, column }
+    }
+}
+
+/// A machine-readable description of an error in a manifest.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// Several errors, reported together.
+#[derive(Debug)]
+pub struct Errors(pub Vec<ManifestParserError>);
+
+impl Display for Errors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects errors as a manifest is read, so that everything wrong with it can
+/// be reported at once instead of just the first problem.
+pub struct ErrorCollector(Vec<ManifestParserError>);
+
+impl ErrorCollector {
+    pub fn new() -> Self {
+        ErrorCollector(vec![])
+    }
+
+    pub fn push<E>(&mut self, error: E)
+    where
+        failure::Error: From<E>,
+    {
+        match failure::Error::from(error).downcast::<ManifestParserError>() {
+            Ok(ManifestParserError::Multiple(errors)) => self.0.extend(errors.0),
+            Ok(error) => self.0.push(error),
+            Err(error) => self.0.push(ManifestParserError::Other(error)),
+        }
+    }
+
+    /// Record the error of `result` if it failed, or return its value.
+    pub fn collect<T, E>(&mut self, result: Result<T, E>) -> Option<T>
+    where
+        failure::Error: From<E>,
+    {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.push(error);
+                None
+            }
+        }
+    }
+
+    /// Fail with the errors collected, if there were any.
+    pub fn finish(mut self) -> Result<(), ManifestParserError> {
+        match self.0.len() {
+            0 => Ok(()),
+            1 => Err(self.0.pop().expect("there is an error")),
+            _ => Err(ManifestParserError::Multiple(Errors(self.0))),
+        }
+    }
+
+    /// The errors collected, of which there must be at least one.
+    pub fn into_error(self) -> ManifestParserError {
+        self.finish().expect_err("errors were collected")
+    }
+}
+
+pub trait PestErrorExt<E>
+where
+    failure::Error: From<E>,
+    E: Display,
+{
+    fn with_span<I>(self, span: &pest::inputs::Span<I>) -> ManifestParserError
+    where
+        I: pest::inputs::Input;
+
+    fn with_pair<R, I>(self, pair: &pest::iterators::Pair<R, I>) -> ManifestParserError
+    where
+        R: pest::RuleType,
+        I: pest::inputs::Input;
+
+    fn with_pos<I>(self, pos: &pest::inputs::Position<I>) -> ManifestParserError
+    where
+        I: pest::inputs::Input;
+}
+
+impl<E> PestErrorExt<E> for E
+where
+    failure::Error: From<E>,
+    E: Display,
+{
+    fn with_span<I>(self, span: &pest::inputs::Span<I>) -> ManifestParserError
+    where
+        I: pest::inputs::Input,
+    {
+        let dummy_pest_error: pest::Error<Rule, I> = pest::Error::CustomErrorSpan {
+            message: format!("{}", &self),
+            span: span.clone(),
+        };
+        ManifestParserError::ErrorAtSpan {
+            description: format!("{}", dummy_pest_error),
+            original_error: failure::Error::from(self),
+            location: Location::from(&span.start_pos()),
+            code: "invalid-manifest",
+        }
+    }
+
+    fn with_pair<R, I>(self, pair: &pest::iterators::Pair<R, I>) -> ManifestParserError
+    where
+        R: pest::RuleType,
+        I: pest::inputs::Input,
+    {
+        self.with_span(&pair.clone().into_span())
+    }
+
+    fn with_pos<I>(self, pos: &pest::inputs::Position<I>) -> ManifestParserError
+    where
+        I: pest::inputs::Input,
+    {
+        let dummy_pest_error: pest::Error<Rule, I> = pest::Error::CustomErrorPos {
+            message: format!("{}", &self),
+            pos: pos.clone(),
+        };
+        ManifestParserError::ErrorAtPos {
+            description: format!("{}", dummy_pest_error),
+            original_error: failure::Error::from(self),
+            location: Location::from(pos),
+            code: "invalid-manifest",
+        }
+    }
+}
+
+pub trait PestResultExt<T, E>
+where
+    failure::Error: From<E>,
+    E: Display,
+{
+    fn span_context<I>(self, span: &pest::inputs::Span<I>) -> Result<T, ManifestParserError>
+    where
+        I: pest::inputs::Input;
+
+    fn pair_context<R, I>(
+        self,
+        pair: &pest::iterators::Pair<R, I>,
+    ) -> Result<T, ManifestParserError>
+    where
+        R: pest::RuleType,
+        I: pest::inputs::Input;
+
+    fn pos_context<I>(self, pos: &pest::inputs::Position<I>) -> Result<T, ManifestParserError>
+    where
+        I: pest::inputs::Input;
+}
+
+impl<T, E> PestResultExt<T, E> for Result<T, E>
+where
+    failure::Error: From<E>,
+    E: Display,
+{
+    fn span_context<I>(self, span: &pest::inputs::Span<I>) -> Result<T, ManifestParserError>
+    where
+        I: pest::inputs::Input,
+    {
+        self.map_err(|err| err.with_span(span))
+    }
+
+    fn pair_context<R, I>(
+        self,
+        pair: &pest::iterators::Pair<R, I>,
+    ) -> Result<T, ManifestParserError>
+    where
+        R: pest::RuleType,
+        I: pest::inputs::Input,
+    {
+        self.map_err(|err| err.with_pair(pair))
+    }
+
+    fn pos_context<I>(self, pos: &pest::inputs::Position<I>) -> Result<T, ManifestParserError>
+    where
+        I: pest::inputs::Input,
+    {
+        self.map_err(|err| err.with_pos(pos))
+    }
+}
+
+impl From<pest::Error<Rule, pest::inputs::StringInput>> for ManifestParserError {
+    fn from(pest_error: pest::Error<Rule, pest::inputs::StringInput>) -> Self {
+        let (message, location) = match pest_error {
+            pest::Error::ParsingError {
+                ref positives,
+                ref pos,
+                ..
+            } => (
+                format!(
+                    "Expected {}",
+                    positives
+                        .iter()
+                        .map(|rule| format!("{:?}", rule))
+                        .collect::<Vec<_>>()
+                        .join(" or ")
+                ),
+                Location::from(pos),
+            ),
+            pest::Error::CustomErrorPos {
+                ref message,
+                ref pos,
+            } => (message.clone(), Location::from(pos)),
+            pest::Error::CustomErrorSpan {
+                ref message,
+                ref span,
+            } => (message.clone(), Location::from(&span.start_pos())),
+        };
+        ManifestParserError::PestError {
+            description: format!("{}", pest_error),
+            message,
+            location,
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use manifest_cst::Node;
+use manifest_parser::{get_optional_field, get_string, parse_manifest, Arguments, Pair};
+use manifest_parser_error::{ManifestParserError, PestErrorExt};
+
+/// The newest version of the manifest schema this client understands. A
+/// manifest declares the version it's written for with the `pm` field:
+///
+/// ```text
+/// pm "1"
+/// ```
+///
+/// Manifests written for older schemas are migrated to this one as they're
+/// read.
+pub const CURRENT_SCHEMA: u64 = 1;
+
+/// The schema version declared by the manifest, or 0 if there's no `pm`
+/// field. Versions newer than this client understands are refused, with a
+/// message saying to upgrade.
+pub fn schema_version(manifest_pair: Pair) -> Result<u64, ManifestParserError> {
+    let arguments_pair = match get_optional_field(manifest_pair, "pm") {
+        Some(arguments_pair) => arguments_pair,
+        None => return Ok(0),
+    };
+    let version_pair = Arguments::get_single(arguments_pair)?;
+    let version_string = get_string(version_pair.clone())?;
+    let version = version_string.parse::<u64>().map_err(|_| {
+        format_err!("Invalid schema version `{}`; expected a number", version_string)
+            .with_pair(&version_pair)
+            .with_code("invalid-schema")
+    })?;
+    if version > CURRENT_SCHEMA {
+        return Err(format_err!(
+            "This manifest is written for version {} of the manifest schema, but this \
+             version of pm only understands up to version {}; upgrade pm to use it",
+            version,
+            CURRENT_SCHEMA
+        ).with_pair(&version_pair)
+        .with_code("unsupported-schema"));
+    }
+    Ok(version)
+}
+
+/// Rewrite a manifest written for `schema` as one for the current schema, a
+/// version at a time. Returns whether anything was rewritten.
+pub fn migrate(schema: u64, _manifest: &mut Node) -> Result<bool, ManifestParserError> {
+    let mut changed = false;
+    for version in schema..CURRENT_SCHEMA {
+        changed |= match version {
+            // Schema 0 is the format of manifests without a `pm` field, written
+            // before the schema was versioned. Schema 1 is the same format.
+            0 => false,
+            _ => unreachable!("no migration from manifest schema {}", version),
+        };
+    }
+    Ok(changed)
+}
+
+/// Parse the manifest, migrating it in memory to the current schema if it was
+/// written for an older one, and refusing it if it was written for a newer one.
+/// The manifest on disk is left alone.
+pub fn parse_migrated_manifest(manifest_source: String) -> Result<Pair, ManifestParserError> {
+    let manifest_pair = parse_manifest(manifest_source)?;
+    let schema = schema_version(manifest_pair.clone())?;
+    let mut manifest = Node::from_pair(manifest_pair.clone());
+    if migrate(schema, &mut manifest)? {
+        parse_manifest(manifest.to_string())
+    } else {
+        // Nothing changed, so keep positions in errors pointing into the
+        // original source.
+        Ok(manifest_pair)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn version(manifest_source: &str) -> Result<u64, ManifestParserError> {
+        schema_version(parse_manifest(manifest_source.to_string())?)
+    }
+
+    #[test]
+    fn reads_schema_versions() {
+        assert_eq!(version("dependencies {\n}\n").unwrap(), 0);
+        assert_eq!(version("pm \"1\"\n").unwrap(), 1);
+        assert!(version("pm \"one\"\n").is_err());
+        let error = version("pm \"2\"\n").unwrap_err();
+        assert_eq!(
+            error.diagnostics(::std::path::Path::new("manifest"))[0].code,
+            "unsupported-schema"
+        );
+    }
+
+    #[test]
+    fn migrates_old_manifests() {
+        let source = "dependencies {\n  test/x ^1\n}\n";
+        let unchanged = Node::parse(source.to_string()).unwrap().to_string();
+        let mut manifest = Node::parse(source.to_string()).unwrap();
+        assert!(!migrate(0, &mut manifest).unwrap());
+        assert!(!migrate(CURRENT_SCHEMA, &mut manifest).unwrap());
+        assert_eq!(manifest.to_string(), unchanged);
+
+        let manifest_pair = parse_migrated_manifest(source.to_string()).unwrap();
+        assert_eq!(Node::from_pair(manifest_pair).to_string(), unchanged);
+    }
+
+    #[test]
+    fn refuses_newer_manifests() {
+        assert!(parse_migrated_manifest("dependencies {\n}\n".to_string()).is_ok());
+        assert!(parse_migrated_manifest("pm \"1\"\n".to_string()).is_ok());
+        assert!(parse_migrated_manifest("pm \"2\"\n".to_string()).is_err());
+    }
+}
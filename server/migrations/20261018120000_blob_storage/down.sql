@@ -0,0 +1,5 @@
+DROP INDEX package_releases_by_artifact_hash;
+
+ALTER TABLE package_releases
+  DROP COLUMN artifact_hash,
+  DROP COLUMN artifact_size;
@@ -0,0 +1,8 @@
+ALTER TABLE package_releases
+  ADD COLUMN artifact_hash TEXT NOT NULL DEFAULT '',
+  ADD COLUMN artifact_size BIGINT NOT NULL DEFAULT 0;
+
+CREATE INDEX package_releases_by_artifact_hash ON package_releases (artifact_hash);
+
+-- The artifacts in `files` are moved into the blob store by the server when it
+-- starts, so the table stays until a later migration drops it.
@@ -0,0 +1,5 @@
+ALTER TABLE package_releases
+  DROP COLUMN dependencies,
+  DROP COLUMN yanked,
+  DROP COLUMN yanked_by,
+  DROP COLUMN yanked_on;
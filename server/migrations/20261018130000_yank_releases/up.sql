@@ -0,0 +1,5 @@
+ALTER TABLE package_releases
+  ADD COLUMN dependencies TEXT NOT NULL DEFAULT '{}',
+  ADD COLUMN yanked BOOLEAN NOT NULL DEFAULT FALSE,
+  ADD COLUMN yanked_by TEXT REFERENCES users (id),
+  ADD COLUMN yanked_on TIMESTAMP;
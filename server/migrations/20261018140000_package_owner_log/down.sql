@@ -0,0 +1 @@
+DROP TABLE package_owner_log;
@@ -0,0 +1,13 @@
+CREATE TABLE package_owner_log (
+  id SERIAL PRIMARY KEY,
+  namespace TEXT NOT NULL,
+  name TEXT NOT NULL,
+  action TEXT NOT NULL,
+  owner TEXT NOT NULL,
+  actor TEXT NOT NULL,
+  time TIMESTAMP NOT NULL DEFAULT NOW(),
+  FOREIGN KEY (namespace, name) REFERENCES packages (namespace, name),
+  FOREIGN KEY (actor) REFERENCES users (id)
+);
+
+CREATE INDEX package_owner_log_by_package_id ON package_owner_log (namespace, name);
@@ -0,0 +1,2 @@
+DROP TABLE namespace_org_owners;
+DROP TABLE package_org_owners;
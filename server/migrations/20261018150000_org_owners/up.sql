@@ -0,0 +1,19 @@
+CREATE TABLE package_org_owners (
+  namespace TEXT NOT NULL,
+  name TEXT NOT NULL,
+  org_id TEXT NOT NULL,
+  org_name TEXT NOT NULL,
+  added_time TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (namespace, name, org_id),
+  FOREIGN KEY (namespace, name) REFERENCES packages (namespace, name)
+);
+
+CREATE INDEX package_org_owners_by_package_id ON package_org_owners (namespace, name);
+
+CREATE TABLE namespace_org_owners (
+  namespace TEXT NOT NULL,
+  org_id TEXT NOT NULL,
+  org_name TEXT NOT NULL,
+  added_time TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (namespace, org_id)
+);
@@ -0,0 +1,3 @@
+ALTER TABLE namespace_org_owners DROP CONSTRAINT namespace_org_owners_namespace_fkey;
+ALTER TABLE packages DROP CONSTRAINT packages_namespace_fkey;
+DROP TABLE namespaces;
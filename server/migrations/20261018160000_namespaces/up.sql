@@ -0,0 +1,22 @@
+CREATE TABLE namespaces (
+  name TEXT PRIMARY KEY,
+  version_scheme TEXT NOT NULL DEFAULT 'any',
+  package_creation TEXT NOT NULL DEFAULT 'anyone',
+  created_by TEXT,
+  created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+  FOREIGN KEY (created_by) REFERENCES users (id)
+);
+
+-- Namespaces already in use stay open to everyone, except those reserved for
+-- organisations, which stay reserved.
+INSERT INTO namespaces (name) SELECT DISTINCT namespace FROM packages;
+INSERT INTO namespaces (name, package_creation)
+  SELECT DISTINCT namespace, 'members' FROM namespace_org_owners
+  ON CONFLICT (name) DO UPDATE SET package_creation = 'members';
+
+ALTER TABLE packages
+  ADD CONSTRAINT packages_namespace_fkey
+  FOREIGN KEY (namespace) REFERENCES namespaces (name);
+ALTER TABLE namespace_org_owners
+  ADD CONSTRAINT namespace_org_owners_namespace_fkey
+  FOREIGN KEY (namespace) REFERENCES namespaces (name);
@@ -0,0 +1,14 @@
+CREATE TABLE api_tokens (
+  id TEXT PRIMARY KEY,
+  user_id TEXT NOT NULL,
+  name TEXT NOT NULL,
+  token_hash TEXT NOT NULL UNIQUE,
+  scopes TEXT[] NOT NULL,
+  packages TEXT[] NOT NULL DEFAULT '{}',
+  created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+  expires_on TIMESTAMP,
+  revoked_on TIMESTAMP,
+  FOREIGN KEY (user_id) REFERENCES users (id)
+);
+
+CREATE INDEX api_tokens_by_user_id ON api_tokens (user_id);
@@ -0,0 +1,2 @@
+DROP TABLE device_logins;
+DROP FUNCTION expire_device_logins();
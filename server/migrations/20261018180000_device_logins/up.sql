@@ -0,0 +1,19 @@
+CREATE TABLE device_logins (
+  device_code TEXT PRIMARY KEY,
+  user_code TEXT NOT NULL UNIQUE,
+  auth_token TEXT,
+  stamp TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE FUNCTION expire_device_logins() RETURNS trigger
+  LANGUAGE plpgsql
+  AS $$
+BEGIN
+  DELETE FROM device_logins WHERE stamp < NOW() - INTERVAL '15 minutes';
+  RETURN NEW;
+END;
+$$;
+
+CREATE TRIGGER expire_device_logins_trigger
+  AFTER INSERT ON device_logins
+  EXECUTE PROCEDURE expire_device_logins();
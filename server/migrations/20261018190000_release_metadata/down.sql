@@ -0,0 +1,5 @@
+ALTER TABLE package_releases
+  DROP COLUMN authors,
+  DROP COLUMN homepage,
+  DROP COLUMN repository,
+  DROP COLUMN bugs;
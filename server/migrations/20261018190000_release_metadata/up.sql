@@ -0,0 +1,5 @@
+ALTER TABLE package_releases
+  ADD COLUMN authors TEXT[] NOT NULL DEFAULT '{}',
+  ADD COLUMN homepage TEXT,
+  ADD COLUMN repository TEXT,
+  ADD COLUMN bugs TEXT;
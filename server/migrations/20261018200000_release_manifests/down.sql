@@ -0,0 +1,2 @@
+ALTER TABLE package_releases
+  DROP COLUMN normalized_manifest;
@@ -0,0 +1,2 @@
+ALTER TABLE package_releases
+  ADD COLUMN normalized_manifest TEXT;
@@ -1,13 +1,17 @@
+use std::env;
 use std::fmt;
+use std::str::FromStr;
 
 use data_encoding::BASE64URL;
 use serde_json;
+use url::Url;
 
 use rocket::request::FromFormValue;
 use rocket::http::RawStr;
 
 use error::{Res, Error};
 use user::{UserRecord, OrgRecord, User};
+use token::{ApiToken, Scope};
 
 use github::Github;
 use gitlab::Gitlab;
@@ -56,7 +60,23 @@ impl<'v> FromFormValue<'v> for AuthSource {
     }
 }
 
-
+/// The registry's own URL for `path`, which is relative to the public base
+/// URL the registry is reached at, set by the `PUBLIC_URL` environment
+/// variable. It's what auth providers send users back to, and defaults to
+/// `http://localhost:8000`.
+pub fn public_url(path: &str) -> Res<Url> {
+    let mut base = match env::var("PUBLIC_URL") {
+        Err(env::VarError::NotPresent) => "http://localhost:8000".to_string(),
+        Err(err) => return Err(Error::from(err)),
+        Ok(url) => url,
+    };
+    // Without a trailing slash, joining would replace the last segment of a
+    // base URL with a path, like `https://example.com/registry`.
+    if !base.ends_with('/') {
+        base.push('/');
+    }
+    Ok(Url::parse(&base)?.join(path)?)
+}
 
 pub trait AuthProvider {
     fn user(&self, token: &str) -> Res<UserRecord>;
@@ -75,8 +95,6 @@ impl AuthProvider for NullAuth {
     }
 }
 
-
-
 #[derive(Serialize, Deserialize, Clone, FromForm)]
 pub struct AuthToken {
     pub user: User,
@@ -99,3 +117,98 @@ impl AuthToken {
         Ok(BASE64URL.encode(serde_json::to_string(self)?.as_bytes()))
     }
 }
+
+enum Credentials {
+    OAuth(String),
+    Api {
+        scopes: Vec<Scope>,
+        packages: Vec<String>,
+    },
+    /// Memberships given up front, standing in for an auth provider.
+    #[cfg(test)]
+    Test(Vec<OrgRecord>),
+}
+
+/// An authenticated user, along with what they authenticated with: either an
+/// OAuth access token from their auth provider, or a registry API token.
+pub struct Identity {
+    pub user: User,
+    credentials: Credentials,
+}
+
+impl Identity {
+    pub fn oauth(token: &AuthToken) -> Identity {
+        Identity {
+            user: token.user.clone(),
+            credentials: Credentials::OAuth(token.token.clone()),
+        }
+    }
+
+    pub fn api(token: &ApiToken) -> Res<Identity> {
+        Ok(Identity {
+            user: User::from_str(&token.user_id)?,
+            credentials: Credentials::Api {
+                scopes: token.scopes()?,
+                packages: token.packages.clone(),
+            },
+        })
+    }
+
+    /// An identity which is a member of `orgs`, with the full access of an
+    /// OAuth login.
+    #[cfg(test)]
+    pub fn test(user: &User, orgs: &[OrgRecord]) -> Identity {
+        Identity {
+            user: user.clone(),
+            credentials: Credentials::Test(orgs.to_vec()),
+        }
+    }
+
+    /// The organisations the user is a member of, as reported by their auth
+    /// provider. Fails with `OAuthRequired` for API tokens: the provider can't
+    /// be asked on their behalf, so they never grant access through an
+    /// organisation.
+    pub fn orgs(&self) -> Res<Vec<OrgRecord>> {
+        match self.credentials {
+            Credentials::OAuth(ref token) => {
+                Ok(self.user.provider.provider()?.orgs(token)?.collect())
+            }
+            Credentials::Api { .. } => Err(Error::OAuthRequired),
+            #[cfg(test)]
+            Credentials::Test(ref orgs) => Ok(orgs.clone()),
+        }
+    }
+
+    /// Fail with `TokenScope` unless the credentials allow `scope` for the
+    /// package. OAuth credentials allow everything.
+    pub fn check_scope(&self, scope: Scope, namespace: &str, name: &str) -> Res<()> {
+        match self.credentials {
+            Credentials::OAuth(_) => Ok(()),
+            #[cfg(test)]
+            Credentials::Test(_) => Ok(()),
+            Credentials::Api {
+                ref scopes,
+                ref packages,
+                ..
+            } => {
+                let package = format!("{}/{}", namespace, name);
+                if scopes.contains(&scope) && (packages.is_empty() || packages.contains(&package)) {
+                    Ok(())
+                } else {
+                    Err(Error::TokenScope(scope.to_string(), package))
+                }
+            }
+        }
+    }
+
+    /// Fail with `OAuthRequired` when authenticated with an API token, for
+    /// actions such as creating new tokens which should need a full login.
+    pub fn require_oauth(&self) -> Res<()> {
+        match self.credentials {
+            Credentials::OAuth(_) => Ok(()),
+            #[cfg(test)]
+            Credentials::Test(_) => Ok(()),
+            Credentials::Api { .. } => Err(Error::OAuthRequired),
+        }
+    }
+}
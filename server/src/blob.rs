@@ -0,0 +1,277 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use chrono::Utc;
+use data_encoding::HEXLOWER;
+use hmac::{Hmac, Mac};
+use rand::prelude::random;
+use reqwest;
+use reqwest::header::Headers;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use error::{Error, Res};
+
+/// Storage for release artifacts, addressed by the SHA-256 hash of their
+/// contents.
+pub trait BlobStore: Send + Sync {
+    /// Store `data` and return its hash. Storing a blob which already exists
+    /// is a no-op.
+    fn put(&self, data: &[u8]) -> Res<String>;
+
+    /// Open the blob identified by `hash` for reading.
+    fn get(&self, hash: &str) -> Res<Box<Read>>;
+
+    /// Open `len` bytes of the blob identified by `hash`, starting at byte
+    /// `start`, for reading.
+    fn get_range(&self, hash: &str, start: u64, len: u64) -> Res<Box<Read>>;
+}
+
+pub fn hash(data: &[u8]) -> String {
+    HEXLOWER.encode(Sha256::digest(data).as_slice())
+}
+
+fn validate_hash(hash: &str) -> Res<()> {
+    if hash.len() == 64 && hash.chars().all(|c| c.is_digit(16) && !c.is_uppercase()) {
+        Ok(())
+    } else {
+        Err(Error::UnknownBlob(hash.to_string()))
+    }
+}
+
+/// Construct the blob store selected by the `BLOB_STORE` environment variable.
+/// Defaults to a filesystem store in `./blobs`.
+pub fn from_env() -> Res<Box<BlobStore>> {
+    match env::var("BLOB_STORE") {
+        Err(env::VarError::NotPresent) => Ok(Box::new(FsBlobStore::from_env()?)),
+        Err(err) => Err(Error::from(err)),
+        Ok(ref name) if name == "fs" => Ok(Box::new(FsBlobStore::from_env()?)),
+        Ok(ref name) if name == "s3" => Ok(Box::new(S3BlobStore::from_env()?)),
+        Ok(name) => Err(Error::NoSuchBlobStore(name)),
+    }
+}
+
+/// Stores blobs on the local filesystem, as `<root>/<first two hex digits of
+/// hash>/<hash>`.
+pub struct FsBlobStore {
+    root: PathBuf,
+}
+
+impl FsBlobStore {
+    pub fn new(root: PathBuf) -> Res<Self> {
+        fs::create_dir_all(&root)?;
+        Ok(FsBlobStore { root })
+    }
+
+    pub fn from_env() -> Res<Self> {
+        Self::new(PathBuf::from(
+            env::var("BLOB_STORE_PATH").unwrap_or_else(|_| "blobs".to_string()),
+        ))
+    }
+
+    fn path(&self, hash: &str) -> PathBuf {
+        let mut path = self.root.clone();
+        path.push(&hash[..2]);
+        path.push(hash);
+        path
+    }
+}
+
+impl BlobStore for FsBlobStore {
+    fn put(&self, data: &[u8]) -> Res<String> {
+        let hash = hash(data);
+        let path = self.path(&hash);
+        if !path.exists() {
+            fs::create_dir_all(path.parent().expect("blob path has a parent"))?;
+            // Write to a temporary file first, so that a blob is never visible
+            // under its hash before it is complete. Its name is unique, so that
+            // concurrent uploads of the same blob don't write to the same file.
+            let tmp_path = path.with_extension(format!("{:016x}.tmp", random::<u64>()));
+            if let Err(err) = File::create(&tmp_path).and_then(|mut file| file.write_all(data)) {
+                let _ = fs::remove_file(&tmp_path);
+                return Err(Error::from(err));
+            }
+            fs::rename(&tmp_path, &path)?;
+        }
+        Ok(hash)
+    }
+
+    fn get(&self, hash: &str) -> Res<Box<Read>> {
+        validate_hash(hash)?;
+        match File::open(self.path(hash)) {
+            Ok(file) => Ok(Box::new(file)),
+            Err(_) => Err(Error::UnknownBlob(hash.to_string())),
+        }
+    }
+
+    fn get_range(&self, hash: &str, start: u64, len: u64) -> Res<Box<Read>> {
+        validate_hash(hash)?;
+        match File::open(self.path(hash)) {
+            Ok(mut file) => {
+                file.seek(SeekFrom::Start(start))?;
+                Ok(Box::new(file.take(len)))
+            }
+            Err(_) => Err(Error::UnknownBlob(hash.to_string())),
+        }
+    }
+}
+
+/// Stores blobs in a bucket on an S3-compatible object storage service, using
+/// path-style URLs (`<endpoint>/<bucket>/<hash>`) so that local stand-ins like
+/// Minio work without DNS setup.
+pub struct S3BlobStore {
+    http: reqwest::Client,
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+}
+
+impl S3BlobStore {
+    pub fn new(
+        endpoint: &str,
+        bucket: &str,
+        region: &str,
+        access_key: &str,
+        secret_key: &str,
+    ) -> Res<Self> {
+        Ok(S3BlobStore {
+            http: reqwest::Client::new(),
+            endpoint: Url::parse(endpoint)?,
+            bucket: bucket.to_string(),
+            region: region.to_string(),
+            access_key: access_key.to_string(),
+            secret_key: secret_key.to_string(),
+        })
+    }
+
+    pub fn from_env() -> Res<Self> {
+        Self::new(
+            &env::var("S3_ENDPOINT")?,
+            &env::var("S3_BUCKET")?,
+            &env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            &env::var("S3_ACCESS_KEY")?,
+            &env::var("S3_SECRET_KEY")?,
+        )
+    }
+
+    fn object_path(&self, hash: &str) -> String {
+        format!("/{}/{}", self.bucket, hash)
+    }
+
+    fn host(&self) -> String {
+        let host = self.endpoint.host_str().unwrap_or("");
+        match self.endpoint.port() {
+            None => host.to_string(),
+            Some(port) => format!("{}:{}", host, port),
+        }
+    }
+
+    /// Sign a request as per AWS Signature Version 4, returning the headers to
+    /// send along with it.
+    fn sign(&self, method: &str, path: &str, payload_hash: &str) -> Headers {
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method,
+            path,
+            self.host(),
+            payload_hash,
+            amz_date,
+            "host;x-amz-content-sha256;x-amz-date",
+            payload_hash
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hash(canonical_request.as_bytes())
+        );
+
+        let mut key = hmac_sha256(format!("AWS4{}", self.secret_key).as_bytes(), date.as_bytes());
+        for part in &[self.region.as_str(), "s3", "aws4_request"] {
+            key = hmac_sha256(&key, part.as_bytes());
+        }
+        let signature = HEXLOWER.encode(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        let mut headers = Headers::new();
+        headers.set_raw("x-amz-date", amz_date);
+        headers.set_raw("x-amz-content-sha256", payload_hash.to_string());
+        headers.set_raw(
+            "Authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+                self.access_key, scope, signature
+            ),
+        );
+        headers
+    }
+
+    fn url(&self, path: &str) -> Res<Url> {
+        Ok(self.endpoint.join(path)?)
+    }
+
+    /// GET an object, or the inclusive byte range `range` of it.
+    fn fetch(&self, hash: &str, range: Option<(u64, u64)>) -> Res<Box<Read>> {
+        validate_hash(hash)?;
+        let path = self.object_path(hash);
+        // The SHA-256 of an empty payload.
+        let empty_hash = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let mut headers = self.sign("GET", &path, empty_hash);
+        if let Some((first, last)) = range {
+            headers.set_raw("Range", format!("bytes={}-{}", first, last));
+        }
+        let res = self.http.get(self.url(&path)?).headers(headers).send()?;
+        if res.status().is_success() {
+            Ok(Box::new(res))
+        } else {
+            Err(Error::UnknownBlob(hash.to_string()))
+        }
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_varkey(key).expect("HMAC accepts keys of any length");
+    mac.input(data);
+    mac.result().code().to_vec()
+}
+
+impl BlobStore for S3BlobStore {
+    fn put(&self, data: &[u8]) -> Res<String> {
+        let hash = hash(data);
+        let path = self.object_path(&hash);
+        let res = self
+            .http
+            .put(self.url(&path)?)
+            .headers(self.sign("PUT", &path, &hash))
+            .body(data.to_vec())
+            .send()?;
+        if res.status().is_success() {
+            Ok(hash)
+        } else {
+            Err(Error::BlobStore(format!(
+                "S3 upload of {} failed: {}",
+                hash,
+                res.status()
+            )))
+        }
+    }
+
+    fn get(&self, hash: &str) -> Res<Box<Read>> {
+        self.fetch(hash, None)
+    }
+
+    fn get_range(&self, hash: &str, start: u64, len: u64) -> Res<Box<Read>> {
+        if len == 0 {
+            return Ok(Box::new(::std::io::empty()));
+        }
+        self.fetch(hash, Some((start, start + len - 1)))
+    }
+}
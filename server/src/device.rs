@@ -0,0 +1,56 @@
+use data_encoding::HEXLOWER;
+use rand::prelude::random;
+use rand::{thread_rng, Rng};
+
+/// How often the client should poll for the outcome of a device login, in
+/// seconds.
+pub const POLL_INTERVAL: u64 = 5;
+
+/// Consonants only, so codes are easy to read out and never spell anything.
+static USER_CODE_ALPHABET: &'static [u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+
+/// Generate the secret code a client polls with.
+pub fn device_code() -> String {
+    HEXLOWER.encode(&random::<[u8; 32]>())
+}
+
+/// Generate the short code the user types into the browser, like `BCDF-GHJK`.
+pub fn user_code() -> String {
+    let mut rng = thread_rng();
+    let mut code = String::new();
+    for i in 0..8 {
+        if i == 4 {
+            code.push('-');
+        }
+        code.push(*rng.choose(USER_CODE_ALPHABET).expect("alphabet is not empty") as char);
+    }
+    code
+}
+
+/// Normalise a code typed in by the user, who might have left out the dash
+/// or used lower case.
+pub fn normalise_user_code(code: &str) -> String {
+    let letters: String = code
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_uppercase())
+        .collect();
+    if letters.len() == 8 {
+        format!("{}-{}", &letters[..4], &letters[4..])
+    } else {
+        letters
+    }
+}
+
+#[derive(Serialize)]
+pub struct DeviceLogin {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_url: String,
+    pub interval: u64,
+}
+
+#[derive(Serialize)]
+pub struct DevicePoll {
+    pub token: Option<String>,
+}
@@ -0,0 +1,146 @@
+use rocket::http::{ContentType, Status};
+use rocket::request::{FromRequest, Request};
+use rocket::response::{Body, Response};
+use rocket::Outcome;
+
+use blob::BlobStore;
+use error::{Error, Res};
+use package::Release;
+
+/// The request headers relevant to conditional and partial artifact downloads.
+pub struct DownloadHeaders {
+    range: Option<String>,
+    if_range: Option<String>,
+    if_none_match: Option<String>,
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for DownloadHeaders {
+    type Error = Error;
+
+    fn from_request(request: &'a Request<'r>) -> Outcome<Self, (Status, Self::Error), ()> {
+        let headers = request.headers();
+        Outcome::Success(DownloadHeaders {
+            range: headers.get_one("Range").map(str::to_string),
+            if_range: headers.get_one("If-Range").map(str::to_string),
+            if_none_match: headers.get_one("If-None-Match").map(str::to_string),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ByteRange {
+    Full,
+    /// `(start, length)`
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+/// Interpret a `Range` header for a resource of `size` bytes. We only support
+/// single byte ranges; anything else is ignored, which RFC 7233 permits, and
+/// the full resource is served instead.
+pub fn parse_range(header: &str, size: u64) -> ByteRange {
+    let spec = match header.trim() {
+        h if h.starts_with("bytes=") && !h.contains(',') => h["bytes=".len()..].trim(),
+        _ => return ByteRange::Full,
+    };
+    let (first, last) = match spec.find('-') {
+        None => return ByteRange::Full,
+        Some(i) => (spec[..i].trim(), spec[i + 1..].trim()),
+    };
+    if first.is_empty() {
+        // Suffix range: the last `n` bytes.
+        return match last.parse::<u64>() {
+            Err(_) => ByteRange::Full,
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if size == 0 => ByteRange::Unsatisfiable,
+            Ok(n) if n >= size => ByteRange::Partial(0, size),
+            Ok(n) => ByteRange::Partial(size - n, n),
+        };
+    }
+    let start = match first.parse::<u64>() {
+        Err(_) => return ByteRange::Full,
+        Ok(start) => start,
+    };
+    let end = if last.is_empty() {
+        size.saturating_sub(1)
+    } else {
+        match last.parse::<u64>() {
+            Err(_) => return ByteRange::Full,
+            Ok(end) if end < start => return ByteRange::Full,
+            Ok(end) => ::std::cmp::min(end, size.saturating_sub(1)),
+        }
+    };
+    if start >= size {
+        ByteRange::Unsatisfiable
+    } else {
+        ByteRange::Partial(start, end - start + 1)
+    }
+}
+
+pub fn etag(release: &Release) -> String {
+    format!("\"{}\"", release.artifact_hash)
+}
+
+fn etag_matches(header: &str, etag: &str) -> bool {
+    header
+        .split(',')
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag == etag || tag.trim_left_matches("W/") == etag)
+}
+
+/// Build the response for downloading the artifact of `release`.
+pub fn artifact_response<'r>(
+    blobs: &BlobStore,
+    release: &Release,
+    headers: &DownloadHeaders,
+) -> Res<Response<'r>> {
+    // Releases published before artifacts were kept in the blob store have no
+    // hash if their artifact couldn't be moved there.
+    if release.artifact_hash.is_empty() {
+        return Err(Error::MissingArtifact(
+            release.namespace.clone(),
+            release.name.clone(),
+            release.version.clone(),
+        ));
+    }
+    let etag = etag(release);
+    let size = release.artifact_size as u64;
+    let mut response = Response::build();
+    response
+        .header(ContentType::new("application", "brotli"))
+        .raw_header("ETag", etag.clone())
+        .raw_header("Accept-Ranges", "bytes");
+
+    if let Some(ref if_none_match) = headers.if_none_match {
+        if etag_matches(if_none_match, &etag) {
+            return response.status(Status::NotModified).ok();
+        }
+    }
+
+    // A range request is only honored if the client's copy is still current.
+    let range = match (&headers.range, &headers.if_range) {
+        (&Some(ref range), &None) => parse_range(range, size),
+        (&Some(ref range), &Some(ref if_range)) if *if_range == etag => parse_range(range, size),
+        _ => ByteRange::Full,
+    };
+
+    match range {
+        ByteRange::Full => response
+            .status(Status::Ok)
+            .raw_body(Body::Sized(blobs.get(&release.artifact_hash)?, size))
+            .ok(),
+        ByteRange::Partial(start, len) => response
+            .status(Status::PartialContent)
+            .raw_header(
+                "Content-Range",
+                format!("bytes {}-{}/{}", start, start + len - 1, size),
+            ).raw_body(Body::Sized(
+                blobs.get_range(&release.artifact_hash, start, len)?,
+                len,
+            )).ok(),
+        ByteRange::Unsatisfiable => response
+            .status(Status::RangeNotSatisfiable)
+            .raw_header("Content-Range", format!("bytes */{}", size))
+            .ok(),
+    }
+}
@@ -10,6 +10,7 @@ use rocket::response::{Response, Responder};
 use rocket::request::Request;
 use diesel;
 
+use token::MAX_EXPIRY_DAYS;
 use user::User;
 
 quick_error! {
@@ -72,9 +73,15 @@ quick_error! {
         InvalidUserID(name: String) {
             description(name)
         }
+        InvalidOrgID(name: String) {
+            description(name)
+        }
         InvalidLoginState(name: String) {
             description(name)
         }
+        UnknownDeviceLogin(code: String) {
+            display("No pending login for code {}; it may have expired", code)
+        }
         UserHasNoEmail(name: String) {
             description(name)
         }
@@ -84,12 +91,78 @@ quick_error! {
         UnknownPackage(namespace: String, name: String) {
             display("No such package: {}/{}", namespace, name)
         }
-        UnknownFile(namespace: String, name: String) {
-            display("No such file: {}/{}", namespace, name)
+        UnknownRelease(namespace: String, name: String, version: String) {
+            display("No such release: {}/{}-{}", namespace, name, version)
+        }
+        UnknownBlob(hash: String) {
+            display("No such blob: {}", hash)
+        }
+        MissingArtifact(namespace: String, name: String, version: String) {
+            display("The artifact of {}/{}-{} has been lost", namespace, name, version)
+        }
+        NoSuchBlobStore(name: String) {
+            display("No such blob store: {}", name)
+        }
+        BlobStore(reason: String) {
+            display("Blob store error: {}", reason)
+        }
+        UnknownNamespace(name: String) {
+            display("No such namespace: {}", name)
+        }
+        NamespaceAlreadyExists(name: String) {
+            display("This namespace already exists: {}", name)
+        }
+        InvalidNamespacePolicy(value: String) {
+            display("Invalid namespace policy: {}", value)
+        }
+        NotAdmin(user: User) {
+            display("User {} is not a registry administrator", user)
+        }
+        VersionSchemeViolation(namespace: String, version: String, scheme: String) {
+            display("Version {} is not allowed in namespace {}, which requires {} versions", version, namespace, scheme)
+        }
+        PackageCreationDenied(namespace: String, user: User) {
+            display("User {} may not create new packages in namespace {}", user, namespace)
+        }
+        InvalidTokenScope(scope: String) {
+            display("Invalid API token scope: {}", scope)
+        }
+        InvalidTokenExpiry(days: u64) {
+            display("An API token can't expire in {} days; the most is {}", days, MAX_EXPIRY_DAYS)
+        }
+        TokenScope(scope: String, package: String) {
+            display("This API token does not allow {} for {}", scope, package)
+        }
+        OAuthRequired {
+            display("This requires logging in with `pm login` rather than an API token")
+        }
+        UnknownToken(id: String) {
+            display("No such API token: {}", id)
         }
         AccessDenied(namespace: String, name: String, user: User) {
             display("User {} is not an owner of {}/{}", user, name, namespace)
         }
+        AlreadyOwner(namespace: String, name: String, owner: String) {
+            display("{} is already an owner of {}/{}", owner, namespace, name)
+        }
+        NotOwner(namespace: String, name: String, owner: String) {
+            display("{} is not an owner of {}/{}", owner, namespace, name)
+        }
+        NamespaceAccessDenied(namespace: String, user: User) {
+            display("User {} may not manage the owners of namespace {}", user, namespace)
+        }
+        AlreadyNamespaceOwner(namespace: String, owner: String) {
+            display("{} is already an owner of namespace {}", owner, namespace)
+        }
+        NotNamespaceOwner(namespace: String, owner: String) {
+            display("{} is not an owner of namespace {}", owner, namespace)
+        }
+        NotOrgMember(org: String, user: User) {
+            display("User {} is not a member of an organisation called {}", user, org)
+        }
+        LastOwner(namespace: String, name: String, user: User) {
+            display("Can't remove {} from {}/{}, because they are its last owner", user, namespace, name)
+        }
         InvalidManifest(reason: &'static str) {
             display("Invalid manifest: {}", reason)
         }
@@ -109,24 +182,53 @@ struct ServerError {
 
 impl<'a> Responder<'a> for Error {
     fn respond_to(self, _: &Request) -> Result<Response<'a>, Status> {
-        match self {
-            Error::Status(code) => Err(code),
-            // TODO real logging?
-            _ => {
-                println!("error: {:?}", self);
-                let data = serde_json::to_vec(&ServerError { message: format!("{}", self) })
-                    .unwrap_or(
-                        "{message:\"an error occurred but I couldn't serialise it for you\"}"
-                            .as_bytes()
-                            .to_owned(),
-                    );
-                Response::build()
-                    .status(Status::InternalServerError)
-                    .header(ContentType::JSON)
-                    .sized_body(Cursor::new(data))
-                    .ok()
-            }
-        }
+        let status = match self {
+            Error::Status(code) => return Err(code),
+            // Only failures of the registry itself are a 500. Clients tell a
+            // missing package apart from them, such as when looking it up in
+            // several registries.
+            Error::UnknownPackage(..)
+            | Error::UnknownRelease(..)
+            | Error::UnknownNamespace(..)
+            | Error::UnknownUser(..)
+            | Error::UnknownToken(..)
+            | Error::UnknownDeviceLogin(..) => Status::NotFound,
+            Error::MissingArtifact(..) => Status::Gone,
+            Error::AccessDenied(..)
+            | Error::NotAdmin(..)
+            | Error::NotOrgMember(..)
+            | Error::NamespaceAccessDenied(..)
+            | Error::PackageCreationDenied(..)
+            | Error::VersionSchemeViolation(..)
+            | Error::TokenScope(..)
+            | Error::OAuthRequired => Status::Forbidden,
+            Error::AlreadyOwner(..)
+            | Error::LastOwner(..)
+            | Error::AlreadyNamespaceOwner(..)
+            | Error::NamespaceAlreadyExists(..)
+            | Error::ReleaseAlreadyExists(..) => Status::Conflict,
+            Error::NotOwner(..)
+            | Error::NotNamespaceOwner(..)
+            | Error::InvalidLoginState(..)
+            | Error::InvalidTokenScope(..)
+            | Error::InvalidTokenExpiry(..)
+            | Error::InvalidArtifact(..)
+            | Error::MessagePack(..) => Status::BadRequest,
+            _ => Status::InternalServerError,
+        };
+        // TODO real logging?
+        println!("error: {:?}", self);
+        let data = serde_json::to_vec(&ServerError { message: format!("{}", self) })
+            .unwrap_or(
+                "{message:\"an error occurred but I couldn't serialise it for you\"}"
+                    .as_bytes()
+                    .to_owned(),
+            );
+        Response::build()
+            .status(status)
+            .header(ContentType::JSON)
+            .sized_body(Cursor::new(data))
+            .ok()
     }
 }
 
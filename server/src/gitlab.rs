@@ -8,7 +8,7 @@ use reqwest::header::{Accept, Authorization, qitem};
 use reqwest::mime::APPLICATION_JSON;
 
 use error::Res;
-use auth::{AuthSource, AuthProvider};
+use auth::{public_url, AuthSource, AuthProvider};
 use user::{User, Org, UserRecord, OrgRecord};
 
 pub static GITLAB_CLIENT_ID: &'static str = "05568e094f02af3b1593fe1b7e6f6651684885968232d87812334d8b74deb995";
@@ -95,7 +95,7 @@ impl Gitlab {
                 client_secret: env::var("GITLAB_SECRET")?,
                 code: code.to_string(),
                 grant_type: "authorization_code".to_string(),
-                redirect_uri: "http://localhost:8000/gitlab/callback".to_string(),
+                redirect_uri: public_url("gitlab/callback")?.to_string(),
             })
             .send()?
             .json()?)
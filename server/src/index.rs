@@ -0,0 +1,70 @@
+use serde_json;
+
+use pm_lib::index::{Release, Releases};
+use pm_lib::manifest::ReleaseManifest;
+use pm_lib::version::Version;
+
+use diesel::pg::PgConnection;
+
+use error::{Error, Res};
+use store::{self, Store};
+
+/// List the releases of a package for the client's solver, including yanked
+/// ones, which are marked as such, as are deprecated ones.
+pub fn package_index(store: &Store, namespace: &str, name: &str) -> Res<Releases> {
+    store.get_package(namespace, name)?;
+    let mut releases = Releases::new();
+    for release in store.get_releases(namespace, name)? {
+        if release.deleted.is_some() {
+            continue;
+        }
+        let version = Version::from_str(&release.version)
+            .ok_or_else(|| Error::InvalidManifest("illegal version string in index"))?;
+        releases.insert(
+            version,
+            Release {
+                dependencies: serde_json::from_str(&release.dependencies)?,
+                yanked: release.yanked,
+                deprecated: release.deprecated,
+            },
+        );
+    }
+    Ok(releases)
+}
+
+/// The manifest a release was published with, so tools can read its metadata
+/// without downloading the artifact.
+///
+/// The normalized manifest is built from the fields the client sent when
+/// publishing, not parsed from the manifest source, so nothing guarantees the
+/// two agree.
+pub fn release_manifest(
+    store: &Store,
+    namespace: &str,
+    name: &str,
+    version: &str,
+) -> Res<ReleaseManifest> {
+    release_manifest_db(&store.db()?, namespace, name, version)
+}
+
+pub fn release_manifest_db(
+    db: &PgConnection,
+    namespace: &str,
+    name: &str,
+    version: &str,
+) -> Res<ReleaseManifest> {
+    let release = store::get_live_release(db, namespace, name, version)?;
+    let manifest = match release.normalized_manifest {
+        Some(ref json) => Some(serde_json::from_str(json)?),
+        None => None,
+    };
+    Ok(ReleaseManifest {
+        // Releases published before the source was shipped have it empty.
+        source: if release.manifest.is_empty() {
+            None
+        } else {
+            Some(release.manifest)
+        },
+        manifest,
+    })
+}
@@ -20,28 +20,38 @@ extern crate diesel;
 #[macro_use]
 extern crate diesel_migrations;
 extern crate brotli;
+extern crate chrono;
+extern crate hmac;
 extern crate im;
 extern crate pm_lib;
+extern crate rand;
+extern crate sha2;
 extern crate tar;
 
 mod auth;
+mod blob;
+mod device;
+mod download;
 mod error;
-mod file;
 mod github;
 mod gitlab;
+mod index;
+mod namespace;
+mod owner;
 mod package;
 mod schema;
 mod search;
 mod store;
+mod token;
 mod upload;
 mod user;
 
 #[cfg(test)]
 mod test;
 
-use std::io::Cursor;
+use std::time::SystemTime;
 
-use rocket::http::{ContentType, Status};
+use rocket::http::Status;
 use rocket::request::{FromRequest, Request};
 use rocket::response::{content, Redirect, Response};
 use rocket::{Data, Outcome, State};
@@ -49,11 +59,17 @@ use rocket_contrib::Json;
 
 use url::Url;
 
-use auth::{AuthProvider, AuthToken};
+use pm_lib::package::PackageName;
+
+use auth::{public_url, AuthProvider, AuthToken, Identity};
+use download::DownloadHeaders;
 use error::{Error, Res};
 use github::{Github, GITHUB_CLIENT_ID};
 use gitlab::{Gitlab, GITLAB_CLIENT_ID};
+use namespace::{PackageCreation, VersionScheme};
 use store::Store;
+use token::{hash_token, ApiToken, Scope, TOKEN_PREFIX};
+use user::User;
 
 static STYLES: &'static str = "
 body {
@@ -129,7 +145,10 @@ fn parse_auth_header<'a>(header: &'a str) -> Option<&'a str> {
     }
 }
 
-pub struct Authenticate(AuthToken);
+pub enum Authenticate {
+    OAuth(AuthToken),
+    Api(String),
+}
 
 impl<'a, 'r> FromRequest<'a, 'r> for Authenticate {
     type Error = Error;
@@ -141,8 +160,11 @@ impl<'a, 'r> FromRequest<'a, 'r> for Authenticate {
             .and_then(parse_auth_header)
         {
             None => Outcome::Failure((Status::Unauthorized, Error::Status(Status::Unauthorized))),
+            Some(token) if token.starts_with(TOKEN_PREFIX) => {
+                Outcome::Success(Authenticate::Api(token.to_string()))
+            }
             Some(token) => match AuthToken::decode(token.as_bytes()) {
-                Ok(token) => Outcome::Success(Authenticate(token)),
+                Ok(token) => Outcome::Success(Authenticate::OAuth(token)),
                 Err(err) => Outcome::Failure((Status::Unauthorized, err)),
             },
         }
@@ -150,15 +172,25 @@ impl<'a, 'r> FromRequest<'a, 'r> for Authenticate {
 }
 
 impl Authenticate {
-    pub fn validate(&self, store: &Store) -> Res<AuthToken> {
-        match self.0.user.provider.provider() {
+    pub fn validate(&self, store: &Store) -> Res<Identity> {
+        match self {
+            &Authenticate::OAuth(ref token) => Self::validate_oauth(token, store),
+            &Authenticate::Api(ref token) => match store.get_api_token_by_hash(&hash_token(token)) {
+                Ok(ref api_token) if api_token.is_live() => Identity::api(api_token),
+                _ => Err(Error::Status(Status::Unauthorized)),
+            },
+        }
+    }
+
+    fn validate_oauth(token: &AuthToken, store: &Store) -> Res<Identity> {
+        match token.user.provider.provider() {
             Err(_) => Err(Error::Status(Status::Unauthorized)),
-            Ok(provider) => match provider.user(&self.0.token) {
+            Ok(provider) => match provider.user(&token.token) {
                 Err(_) => Err(Error::Status(Status::Unauthorized)),
                 Ok(user) => {
-                    if user.user()? == self.0.user {
+                    if user.user()? == token.user {
                         store.update_user(&user)?;
-                        Ok(self.0.clone())
+                        Ok(Identity::oauth(token))
                     } else {
                         Err(Error::Status(Status::Unauthorized))
                     }
@@ -181,15 +213,355 @@ struct SearchQuery {
 }
 
 #[get("/files/<namespace>/<name>/<version>")]
-fn files(store: State<Store>, namespace: String, name: String, version: String) -> Res<Response> {
-    match store.get_file(&namespace, &name) {
-        Err(_) => Err(Error::Status(Status::NotFound)),
-        Ok(file) => Response::build()
-            .status(Status::Ok)
-            .header(ContentType::new("application", "brotli"))
-            .sized_body(Cursor::new(file.data))
-            .ok(),
+fn files(
+    store: State<Store>,
+    headers: DownloadHeaders,
+    namespace: String,
+    name: String,
+    version: String,
+) -> Res<Response> {
+    let release = store.get_live_release(&namespace, &name, &version)?;
+    download::artifact_response(store.blobs(), &release, &headers)
+}
+
+#[get("/index/<namespace>/<name>")]
+fn package_index(
+    store: State<Store>,
+    namespace: String,
+    name: String,
+) -> Res<Json<pm_lib::index::Releases>> {
+    Ok(Json(index::package_index(&store, &namespace, &name)?))
+}
+
+#[get("/packages/<namespace>/<name>/<version>/manifest")]
+fn release_manifest(
+    store: State<Store>,
+    namespace: String,
+    name: String,
+    version: String,
+) -> Res<Json<pm_lib::manifest::ReleaseManifest>> {
+    Ok(Json(index::release_manifest(
+        &store,
+        &namespace,
+        &name,
+        &version,
+    )?))
+}
+
+#[derive(FromForm)]
+struct ReleaseQuery {
+    namespace: String,
+    name: String,
+    version: String,
+}
+
+#[post("/yank?<release>")]
+fn yank(
+    release: ReleaseQuery,
+    auth: Authenticate,
+    store: State<Store>,
+) -> Res<Json<upload::Receipt>> {
+    let identity = auth.validate(&store)?;
+    identity.check_scope(Scope::Yank, &release.namespace, &release.name)?;
+    owner::check_package_owner(&store, &release.namespace, &release.name, &identity)?;
+    store.set_yanked(
+        &release.namespace,
+        &release.name,
+        &release.version,
+        Some(&identity.user),
+    )?;
+    Ok(Json(upload::Receipt::ok()))
+}
+
+#[post("/unyank?<release>")]
+fn unyank(
+    release: ReleaseQuery,
+    auth: Authenticate,
+    store: State<Store>,
+) -> Res<Json<upload::Receipt>> {
+    let identity = auth.validate(&store)?;
+    identity.check_scope(Scope::Yank, &release.namespace, &release.name)?;
+    owner::check_package_owner(&store, &release.namespace, &release.name, &identity)?;
+    store.set_yanked(&release.namespace, &release.name, &release.version, None)?;
+    Ok(Json(upload::Receipt::ok()))
+}
+
+#[derive(FromForm)]
+struct PackageQuery {
+    namespace: String,
+    name: String,
+}
+
+#[derive(FromForm)]
+struct OwnerQuery {
+    namespace: String,
+    name: String,
+    user: User,
+}
+
+#[get("/owners?<package>")]
+fn list_owners(package: PackageQuery, store: State<Store>) -> Res<Json<owner::Owners>> {
+    Ok(Json(owner::list_owners(&store, &package.namespace, &package.name)?))
+}
+
+#[post("/owners/add?<owner>")]
+fn add_owner(
+    owner: OwnerQuery,
+    auth: Authenticate,
+    store: State<Store>,
+) -> Res<Json<upload::Receipt>> {
+    let identity = auth.validate(&store)?;
+    identity.check_scope(Scope::OwnerAdmin, &owner.namespace, &owner.name)?;
+    owner::check_package_owner(&store, &owner.namespace, &owner.name, &identity)?;
+    store.add_package_owner(&owner.namespace, &owner.name, &owner.user, &identity.user)?;
+    Ok(Json(upload::Receipt::ok()))
+}
+
+#[post("/owners/remove?<owner>")]
+fn remove_owner(
+    owner: OwnerQuery,
+    auth: Authenticate,
+    store: State<Store>,
+) -> Res<Json<upload::Receipt>> {
+    let identity = auth.validate(&store)?;
+    identity.check_scope(Scope::OwnerAdmin, &owner.namespace, &owner.name)?;
+    owner::check_package_owner(&store, &owner.namespace, &owner.name, &identity)?;
+    store.remove_package_owner(&owner.namespace, &owner.name, &owner.user, &identity.user)?;
+    Ok(Json(upload::Receipt::ok()))
+}
+
+#[derive(FromForm)]
+struct OrgOwnerQuery {
+    namespace: String,
+    name: String,
+    org: String,
+}
+
+#[post("/owners/orgs/add?<owner>")]
+fn add_org_owner(
+    owner: OrgOwnerQuery,
+    auth: Authenticate,
+    store: State<Store>,
+) -> Res<Json<upload::Receipt>> {
+    let identity = auth.validate(&store)?;
+    identity.check_scope(Scope::OwnerAdmin, &owner.namespace, &owner.name)?;
+    owner::check_package_owner(&store, &owner.namespace, &owner.name, &identity)?;
+    let org = owner::find_membership(&identity, &owner.org)?;
+    store.add_package_org_owner(&owner.namespace, &owner.name, &org, &identity.user)?;
+    Ok(Json(upload::Receipt::ok()))
+}
+
+#[post("/owners/orgs/remove?<owner>")]
+fn remove_org_owner(
+    owner: OrgOwnerQuery,
+    auth: Authenticate,
+    store: State<Store>,
+) -> Res<Json<upload::Receipt>> {
+    let identity = auth.validate(&store)?;
+    identity.check_scope(Scope::OwnerAdmin, &owner.namespace, &owner.name)?;
+    owner::check_package_owner(&store, &owner.namespace, &owner.name, &identity)?;
+    let org = match store
+        .get_package_org_owners(&owner.namespace, &owner.name)?
+        .into_iter()
+        .find(|org| org.name == owner.org || org.id.to_string() == owner.org)
+    {
+        Some(org) => org.id,
+        None => {
+            return Err(Error::NotOwner(
+                owner.namespace.clone(),
+                owner.name.clone(),
+                owner.org.clone(),
+            ))
+        }
+    };
+    store.remove_package_org_owner(&owner.namespace, &owner.name, &org, &identity.user)?;
+    Ok(Json(upload::Receipt::ok()))
+}
+
+#[derive(FromForm)]
+struct NamespaceOrgOwnerQuery {
+    namespace: String,
+    org: String,
+}
+
+#[post("/namespaces/orgs/add?<owner>")]
+fn add_namespace_org_owner(
+    owner: NamespaceOrgOwnerQuery,
+    auth: Authenticate,
+    store: State<Store>,
+) -> Res<Json<upload::Receipt>> {
+    let identity = auth.validate(&store)?;
+    identity.require_oauth()?;
+    owner::check_namespace_owner(&store.db()?, store.admins(), &owner.namespace, &identity)?;
+    let org = owner::find_membership(&identity, &owner.org)?;
+    store.add_namespace_org_owner(&owner.namespace, &org)?;
+    Ok(Json(upload::Receipt::ok()))
+}
+
+#[post("/namespaces/orgs/remove?<owner>")]
+fn remove_namespace_org_owner(
+    owner: NamespaceOrgOwnerQuery,
+    auth: Authenticate,
+    store: State<Store>,
+) -> Res<Json<upload::Receipt>> {
+    let identity = auth.validate(&store)?;
+    identity.require_oauth()?;
+    owner::check_namespace_owner(&store.db()?, store.admins(), &owner.namespace, &identity)?;
+    let org = match store
+        .get_namespace_org_owners(&owner.namespace)?
+        .into_iter()
+        .find(|org| org.name == owner.org || org.id.to_string() == owner.org)
+    {
+        Some(org) => org.id,
+        None => {
+            return Err(Error::NotNamespaceOwner(
+                owner.namespace.clone(),
+                owner.org.clone(),
+            ))
+        }
+    };
+    store.remove_namespace_org_owner(&owner.namespace, &org)?;
+    Ok(Json(upload::Receipt::ok()))
+}
+
+#[get("/namespaces")]
+fn list_namespaces(store: State<Store>) -> Res<Json<Vec<namespace::NamespaceInfo>>> {
+    Ok(Json(
+        store
+            .get_namespaces()?
+            .iter()
+            .map(namespace::NamespaceInfo::from)
+            .collect(),
+    ))
+}
+
+#[derive(FromForm)]
+struct NamespaceSettings {
+    name: String,
+    version_scheme: Option<VersionScheme>,
+    package_creation: Option<PackageCreation>,
+}
+
+#[post("/namespaces/create?<settings>")]
+fn create_namespace(
+    settings: NamespaceSettings,
+    auth: Authenticate,
+    store: State<Store>,
+) -> Res<Json<upload::Receipt>> {
+    let identity = auth.validate(&store)?;
+    identity.require_oauth()?;
+    store.check_admin(&identity.user)?;
+    store.insert_namespace(&namespace::Namespace {
+        name: settings.name.clone(),
+        version_scheme: settings
+            .version_scheme
+            .unwrap_or(VersionScheme::Any)
+            .to_string(),
+        package_creation: settings
+            .package_creation
+            .unwrap_or(PackageCreation::Anyone)
+            .to_string(),
+        created_by: Some(identity.user.to_string()),
+        created_on: SystemTime::now(),
+    })?;
+    Ok(Json(upload::Receipt::ok()))
+}
+
+#[post("/namespaces/update?<settings>")]
+fn update_namespace(
+    settings: NamespaceSettings,
+    auth: Authenticate,
+    store: State<Store>,
+) -> Res<Json<upload::Receipt>> {
+    let identity = auth.validate(&store)?;
+    identity.require_oauth()?;
+    store.check_admin(&identity.user)?;
+    store.update_namespace(
+        &settings.name,
+        settings.version_scheme,
+        settings.package_creation,
+    )?;
+    Ok(Json(upload::Receipt::ok()))
+}
+
+/// Split a comma separated query parameter.
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[derive(FromForm)]
+struct NewTokenQuery {
+    name: String,
+    scopes: String,
+    packages: Option<String>,
+    expires_in_days: Option<u64>,
+}
+
+#[post("/tokens/create?<query>")]
+fn create_token(
+    query: NewTokenQuery,
+    auth: Authenticate,
+    store: State<Store>,
+) -> Res<Json<token::NewToken>> {
+    let identity = auth.validate(&store)?;
+    identity.require_oauth()?;
+    let scopes = split_list(&query.scopes)
+        .iter()
+        .map(|scope| Scope::from_str(scope).map(|scope| scope.to_string()))
+        .collect::<Res<Vec<_>>>()?;
+    let packages = split_list(query.packages.as_ref().map(String::as_str).unwrap_or(""));
+    for package in &packages {
+        if PackageName::from_str(package).is_none() {
+            return Err(Error::InvalidTokenScope(package.clone()));
+        }
     }
+    let (id, secret) = token::generate();
+    store.insert_api_token(&ApiToken {
+        id: id.clone(),
+        user_id: identity.user.to_string(),
+        name: query.name.clone(),
+        token_hash: hash_token(&secret),
+        scopes,
+        packages,
+        created_on: SystemTime::now(),
+        expires_on: token::expiry(query.expires_in_days)?,
+        revoked_on: None,
+    })?;
+    Ok(Json(token::NewToken::new(id, secret)))
+}
+
+#[get("/tokens")]
+fn list_tokens(auth: Authenticate, store: State<Store>) -> Res<Json<Vec<token::TokenInfo>>> {
+    let identity = auth.validate(&store)?;
+    identity.require_oauth()?;
+    Ok(Json(
+        store
+            .get_api_tokens(&identity.user)?
+            .iter()
+            .map(token::TokenInfo::from)
+            .collect(),
+    ))
+}
+
+#[derive(FromForm)]
+struct TokenQuery {
+    id: String,
+}
+
+#[post("/tokens/revoke?<query>")]
+fn revoke_token(
+    query: TokenQuery,
+    auth: Authenticate,
+    store: State<Store>,
+) -> Res<Json<upload::Receipt>> {
+    let identity = auth.validate(&store)?;
+    identity.require_oauth()?;
+    store.revoke_api_token(&identity.user, &query.id)?;
+    Ok(Json(upload::Receipt::ok()))
 }
 
 #[get("/search?<query>")]
@@ -203,10 +575,10 @@ fn search(query: SearchQuery, store: State<Store>) -> Res<Json<Vec<search::Searc
 
 #[post("/publish", data = "<data>")]
 fn publish(data: Data, auth: Authenticate, store: State<Store>) -> Res<Json<upload::Receipt>> {
-    let token = auth.validate(&store)?;
+    let identity = auth.validate(&store)?;
     Ok(Json(upload::process_upload(
         &store,
-        &token.user,
+        &identity,
         data.open(),
     )?))
 }
@@ -231,14 +603,21 @@ struct Login {
 #[get("/login_client?<login>")]
 fn login_client(store: State<Store>, login: Login) -> Res<content::Html<String>> {
     store.register_login(&login.token, &login.callback)?;
+    provider_choice(&login.token)
+}
+
+/// The page letting the user pick an auth provider to log in with, passing
+/// `state` through the OAuth flow.
+fn provider_choice(state: &str) -> Res<content::Html<String>> {
     let github_url = format!(
         "https://github.com/login/oauth/authorize?scope=user:email&client_id={}&state={}",
-        GITHUB_CLIENT_ID, login.token
+        GITHUB_CLIENT_ID, state
     );
     let gitlab_url = format!(
-        "https://gitlab.com/oauth/authorize?client_id={}&state={}&response_type=code&redirect_uri=http://localhost:8000/gitlab/callback&scope=read_user",
+        "https://gitlab.com/oauth/authorize?client_id={}&state={}&response_type=code&redirect_uri={}&scope=read_user",
         GITLAB_CLIENT_ID,
-        login.token
+        state,
+        public_url("gitlab/callback")?
     );
     Ok(html_doc(&format!(
         "
@@ -255,42 +634,115 @@ fn login_client(store: State<Store>, login: Login) -> Res<content::Html<String>>
     )))
 }
 
+/// Login sessions started by `pm login --device` have a callback of this
+/// prefix followed by the user code, rather than a URL.
+static DEVICE_CALLBACK: &'static str = "device:";
+
+#[post("/login/device")]
+fn start_device_login(store: State<Store>) -> Res<Json<device::DeviceLogin>> {
+    let device_code = device::device_code();
+    let user_code = store.register_device_login(&device_code)?;
+    Ok(Json(device::DeviceLogin {
+        device_code,
+        user_code,
+        verification_url: public_url("device")?.to_string(),
+        interval: device::POLL_INTERVAL,
+    }))
+}
+
+#[derive(FromForm)]
+struct DevicePollQuery {
+    device_code: String,
+}
+
+#[get("/login/device/poll?<query>")]
+fn poll_device_login(store: State<Store>, query: DevicePollQuery) -> Res<Json<device::DevicePoll>> {
+    Ok(Json(device::DevicePoll {
+        token: store.poll_device_login(&query.device_code)?,
+    }))
+}
+
+#[get("/device")]
+fn device_form() -> Res<content::Html<String>> {
+    Ok(html_doc(
+        "
+<p>Enter the code shown by <code>pm login --device</code>:</p>
+<form class=\"pad\" action=\"/device/verify\" method=\"get\">
+  <input name=\"code\" autofocus autocomplete=\"off\">
+  <button class=\"btn\" type=\"submit\">Continue</button>
+</form>
+",
+    ))
+}
+
+#[derive(FromForm)]
+struct DeviceCode {
+    code: String,
+}
+
+#[get("/device/verify?<device>")]
+fn device_verify(store: State<Store>, device: DeviceCode) -> Res<content::Html<String>> {
+    let user_code = device::normalise_user_code(&device.code);
+    store.check_device_login(&user_code)?;
+    let state = device::device_code();
+    store.register_login(&state, &format!("{}{}", DEVICE_CALLBACK, user_code))?;
+    provider_choice(&state)
+}
+
+#[get("/device/done")]
+fn device_done() -> Res<content::Html<String>> {
+    Ok(html_doc(
+        "
+<p>You are authenticated!</p>
+<p>You may close this window and return to your terminal.</p>
+",
+    ))
+}
+
 #[derive(FromForm)]
 struct OAuthCallback {
     code: String,
     state: String,
 }
 
+/// Hand the credentials of a completed OAuth login back to the client which
+/// started it, either through the local web server of `pm login`, or by
+/// making them available to the polling `pm login --device`.
+fn finish_login(callback: &str, state: &str, auth: &AuthToken, store: &Store) -> Res<Redirect> {
+    if callback.starts_with(DEVICE_CALLBACK) {
+        store.complete_device_login(&callback[DEVICE_CALLBACK.len()..], &auth.encode()?)?;
+        return Ok(Redirect::to("/device/done"));
+    }
+    let mut redirect = Url::parse(callback)?;
+    redirect
+        .query_pairs_mut()
+        .append_pair("token", &auth.encode()?)
+        .append_pair("state", state);
+    Ok(Redirect::to(redirect.as_str()))
+}
+
 #[get("/github/callback?<callback>")]
 fn github_callback(store: State<Store>, callback: OAuthCallback) -> Res<Redirect> {
-    let mut redirect = Url::parse(&store.validate_login(&callback.state)?)?;
+    let client_callback = store.validate_login(&callback.state)?;
     let github = Github::new()?;
     let token = github.validate_callback(&callback.code)?;
     let user = github.user(&token.access_token)?;
     let auth = AuthToken::new(&user.user()?, &token.access_token);
     println!("User data: {:?}", user);
     store.update_user(&user)?;
-    redirect
-        .query_pairs_mut()
-        .append_pair("token", &auth.encode()?)
-        .append_pair("state", &callback.state);
-    Ok(Redirect::to(redirect.as_str()))
+    finish_login(&client_callback, &callback.state, &auth, &store)
 }
 
 #[get("/gitlab/callback?<callback>")]
 fn gitlab_callback(store: State<Store>, callback: OAuthCallback) -> Res<Redirect> {
-    let mut redirect = Url::parse(&store.validate_login(&callback.state)?)?;
+    let client_callback = store.validate_login(&callback.state)?;
     let gitlab = Gitlab::new()?;
     let token = gitlab.validate_callback(&callback.code)?;
     let user = gitlab.user(&token.access_token)?;
     let auth = AuthToken::new(&user.user()?, &token.access_token);
     println!("User data: {:?}", user);
     store.update_user(&user)?;
-    redirect
-        .query_pairs_mut()
-        .append_pair("token", &auth.encode()?)
-        .append_pair("state", &callback.state);
-    Ok(Redirect::to(redirect.as_str()))
+    finish_login(&client_callback, &callback.state, &auth, &store)
 }
 
 fn main() {
@@ -298,6 +750,9 @@ fn main() {
     dotenv::dotenv().ok();
 
     let store = Store::new().expect("couldn't connect to Postgres server");
+    store
+        .import_legacy_files()
+        .expect("couldn't move release artifacts into the blob store");
     rocket::ignite()
         .manage(store)
         .mount(
@@ -307,8 +762,30 @@ fn main() {
                 test,
                 search,
                 publish,
+                yank,
+                unyank,
+                list_owners,
+                add_owner,
+                remove_owner,
+                add_org_owner,
+                remove_org_owner,
+                add_namespace_org_owner,
+                remove_namespace_org_owner,
+                list_namespaces,
+                create_namespace,
+                update_namespace,
+                create_token,
+                list_tokens,
+                revoke_token,
+                package_index,
+                release_manifest,
                 files,
                 login_client,
+                start_device_login,
+                poll_device_login,
+                device_form,
+                device_verify,
+                device_done,
                 github_callback,
                 gitlab_callback,
             ],
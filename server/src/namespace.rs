@@ -0,0 +1,143 @@
+use std::fmt;
+use std::time::SystemTime;
+
+use rocket::http::RawStr;
+use rocket::request::FromFormValue;
+
+use pm_lib::version::Version;
+
+use error::{Error, Res};
+use schema::namespaces;
+
+/// Which version numbers may be published in a namespace.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionScheme {
+    /// Anything the version parser accepts.
+    Any,
+    /// Exactly three numeric fields, `major.minor.patch`, with optional
+    /// prerelease and build identifiers.
+    Semver,
+}
+
+impl VersionScheme {
+    pub fn from_str(name: &str) -> Res<Self> {
+        match name {
+            "any" => Ok(VersionScheme::Any),
+            "semver" => Ok(VersionScheme::Semver),
+            _ => Err(Error::InvalidNamespacePolicy(name.to_string())),
+        }
+    }
+
+    pub fn allows(&self, version: &Version) -> bool {
+        match self {
+            &VersionScheme::Any => true,
+            &VersionScheme::Semver => version.fields.len() == 3,
+        }
+    }
+}
+
+impl fmt::Display for VersionScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(match self {
+            &VersionScheme::Any => "any",
+            &VersionScheme::Semver => "semver",
+        })
+    }
+}
+
+impl<'v> FromFormValue<'v> for VersionScheme {
+    type Error = Error;
+
+    fn from_form_value(val: &'v RawStr) -> Res<Self> {
+        VersionScheme::from_str(&val.url_decode()?)
+    }
+}
+
+/// Who may create new packages in a namespace. Publishing new releases of
+/// existing packages is always up to the package's owners.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageCreation {
+    Anyone,
+    /// Only members of the organisations owning the namespace, and registry
+    /// administrators.
+    Members,
+}
+
+impl PackageCreation {
+    pub fn from_str(name: &str) -> Res<Self> {
+        match name {
+            "anyone" => Ok(PackageCreation::Anyone),
+            "members" => Ok(PackageCreation::Members),
+            _ => Err(Error::InvalidNamespacePolicy(name.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for PackageCreation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(match self {
+            &PackageCreation::Anyone => "anyone",
+            &PackageCreation::Members => "members",
+        })
+    }
+}
+
+impl<'v> FromFormValue<'v> for PackageCreation {
+    type Error = Error;
+
+    fn from_form_value(val: &'v RawStr) -> Res<Self> {
+        PackageCreation::from_str(&val.url_decode()?)
+    }
+}
+
+#[derive(Identifiable, Queryable, Insertable, Debug)]
+#[table_name = "namespaces"]
+#[primary_key(name)]
+pub struct Namespace {
+    pub name: String,
+    pub version_scheme: String,
+    pub package_creation: String,
+    pub created_by: Option<String>,
+    pub created_on: SystemTime,
+}
+
+impl Namespace {
+    pub fn version_scheme(&self) -> Res<VersionScheme> {
+        VersionScheme::from_str(&self.version_scheme)
+    }
+
+    pub fn package_creation(&self) -> Res<PackageCreation> {
+        PackageCreation::from_str(&self.package_creation)
+    }
+
+    /// Fail unless `version` may be published in this namespace.
+    pub fn check_version(&self, version: &Version) -> Res<()> {
+        let scheme = self.version_scheme()?;
+        if scheme.allows(version) {
+            Ok(())
+        } else {
+            Err(Error::VersionSchemeViolation(
+                self.name.clone(),
+                version.to_string(),
+                scheme.to_string(),
+            ))
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct NamespaceInfo {
+    name: String,
+    version_scheme: String,
+    package_creation: String,
+}
+
+impl<'a> From<&'a Namespace> for NamespaceInfo {
+    fn from(namespace: &'a Namespace) -> Self {
+        NamespaceInfo {
+            name: namespace.name.clone(),
+            version_scheme: namespace.version_scheme.clone(),
+            package_creation: namespace.package_creation.clone(),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use diesel::pg::PgConnection;
+
+use auth::Identity;
+use error::{Error, Res};
+use namespace::{Namespace, PackageCreation};
+use store::{self, Store};
+use user::{OrgRecord, User};
+
+#[derive(Serialize)]
+pub struct OrgOwner {
+    id: String,
+    name: String,
+    /// Whether the organisation owns the whole namespace rather than just
+    /// this package.
+    namespace: bool,
+}
+
+#[derive(Serialize)]
+pub struct Owners {
+    users: Vec<String>,
+    orgs: Vec<OrgOwner>,
+}
+
+pub fn list_owners(store: &Store, namespace: &str, name: &str) -> Res<Owners> {
+    store.get_package(namespace, name)?;
+    let package_orgs = store
+        .get_package_org_owners(namespace, name)?
+        .into_iter()
+        .map(|org| (org, false));
+    let namespace_orgs = store
+        .get_namespace_org_owners(namespace)?
+        .into_iter()
+        .map(|org| (org, true));
+    Ok(Owners {
+        users: store
+            .get_package_owners(namespace, name)?
+            .iter()
+            .map(|user| user.to_string())
+            .collect(),
+        orgs: package_orgs
+            .chain(namespace_orgs)
+            .map(|(org, namespace)| OrgOwner {
+                id: org.id.to_string(),
+                name: org.name,
+                namespace,
+            }).collect(),
+    })
+}
+
+fn is_member_of_any(identity: &Identity, orgs: &[OrgRecord]) -> Res<bool> {
+    if orgs.is_empty() {
+        return Ok(false);
+    }
+    let memberships = identity.orgs()?;
+    Ok(orgs
+        .iter()
+        .any(|org| memberships.iter().any(|member| member.id == org.id)))
+}
+
+/// Fail with `AccessDenied` unless the user owns the package, either directly
+/// or as a member of an organisation which owns the package or its namespace.
+pub fn check_package_owner(
+    store: &Store,
+    namespace: &str,
+    name: &str,
+    identity: &Identity,
+) -> Res<()> {
+    if store
+        .get_package_owners(namespace, name)?
+        .iter()
+        .any(|owner| *owner == identity.user)
+    {
+        return Ok(());
+    }
+    let mut orgs = store.get_package_org_owners(namespace, name)?;
+    orgs.extend(store.get_namespace_org_owners(namespace)?);
+    if is_member_of_any(identity, &orgs)? {
+        Ok(())
+    } else {
+        Err(Error::AccessDenied(
+            namespace.to_owned(),
+            name.to_owned(),
+            identity.user.clone(),
+        ))
+    }
+}
+
+/// Fail with `PackageCreationDenied` unless the user may create new packages
+/// in `namespace`, according to its policy.
+pub fn check_package_creation(
+    db: &PgConnection,
+    admins: &[User],
+    namespace: &Namespace,
+    identity: &Identity,
+) -> Res<()> {
+    let allowed = match namespace.package_creation()? {
+        PackageCreation::Anyone => true,
+        PackageCreation::Members => {
+            admins.contains(&identity.user) || is_member_of_any(
+                identity,
+                &store::get_namespace_org_owners(db, &namespace.name)?,
+            )?
+        }
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(Error::PackageCreationDenied(
+            namespace.name.clone(),
+            identity.user.clone(),
+        ))
+    }
+}
+
+/// Fail with `NamespaceAccessDenied` unless the user may manage which
+/// organisations own `namespace`: registry administrators, and members of an
+/// organisation which already owns it.
+pub fn check_namespace_owner(
+    db: &PgConnection,
+    admins: &[User],
+    namespace: &str,
+    identity: &Identity,
+) -> Res<()> {
+    if admins.contains(&identity.user)
+        || is_member_of_any(identity, &store::get_namespace_org_owners(db, namespace)?)?
+    {
+        Ok(())
+    } else {
+        Err(Error::NamespaceAccessDenied(
+            namespace.to_owned(),
+            identity.user.clone(),
+        ))
+    }
+}
+
+/// Find the organisation called `org` among those the user is a member of.
+/// Only members can hand packages over to an organisation.
+pub fn find_membership(identity: &Identity, org: &str) -> Res<OrgRecord> {
+    identity
+        .orgs()?
+        .into_iter()
+        .find(|record| record.name == org || record.id.to_string() == org)
+        .ok_or_else(|| Error::NotOrgMember(org.to_owned(), identity.user.clone()))
+}
@@ -1,6 +1,9 @@
 use std::time::SystemTime;
 
-use schema::{packages, package_owners, package_releases};
+use schema::{
+    namespace_org_owners, packages, package_org_owners, package_owners, package_owner_log,
+    package_releases,
+};
 use user::UserRecord;
 
 #[derive(Identifiable, Queryable, Insertable, Associations, Debug)]
@@ -24,6 +27,40 @@ pub struct PackageOwner {
     pub added_time: SystemTime
 }
 
+#[derive(Identifiable, Queryable, Insertable, Debug)]
+#[table_name = "package_org_owners"]
+#[primary_key(namespace, name, org_id)]
+pub struct PackageOrgOwner {
+    pub namespace: String,
+    pub name: String,
+    pub org_id: String,
+    pub org_name: String,
+    pub added_time: SystemTime
+}
+
+/// An organisation owning every package in a namespace.
+#[derive(Identifiable, Queryable, Insertable, Debug)]
+#[table_name = "namespace_org_owners"]
+#[primary_key(namespace, org_id)]
+pub struct NamespaceOrgOwner {
+    pub namespace: String,
+    pub org_id: String,
+    pub org_name: String,
+    pub added_time: SystemTime
+}
+
+/// A record of `actor` adding or removing `owner` as an owner of a package.
+#[derive(Insertable, Debug)]
+#[table_name = "package_owner_log"]
+pub struct OwnerLogEntry {
+    pub namespace: String,
+    pub name: String,
+    pub action: String,
+    pub owner: String,
+    pub actor: String,
+    pub time: SystemTime
+}
+
 #[derive(Insertable, Identifiable, Queryable, Associations, Debug)]
 #[belongs_to(UserRecord, foreign_key = "publisher")]
 #[table_name = "package_releases"]
@@ -46,5 +83,17 @@ pub struct Release {
     pub deprecated_by: Option<String>,
     pub deprecated_on: Option<SystemTime>,
     pub deleted: Option<String>,
-    pub deleted_on: Option<SystemTime>
+    pub deleted_on: Option<SystemTime>,
+    pub artifact_hash: String,
+    pub artifact_size: i64,
+    pub dependencies: String,
+    pub yanked: bool,
+    pub yanked_by: Option<String>,
+    pub yanked_on: Option<SystemTime>,
+    pub authors: Vec<String>,
+    pub homepage: Option<String>,
+    pub repository: Option<String>,
+    pub bugs: Option<String>,
+    /// The manifest as JSON, for releases published with it.
+    pub normalized_manifest: Option<String>
 }
@@ -9,6 +9,15 @@ table! {
     }
 }
 
+table! {
+    files (namespace, name) {
+        namespace -> Text,
+        name -> Text,
+        data -> Binary,
+        uploaded_on -> Timestamp,
+    }
+}
+
 table! {
     login_sessions (token) {
         token -> Text,
@@ -17,6 +26,42 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel::pg::types::sql_types::Array;
+
+    api_tokens (id) {
+        id -> Text,
+        user_id -> Text,
+        name -> Text,
+        token_hash -> Text,
+        scopes -> Array<Text>,
+        packages -> Array<Text>,
+        created_on -> Timestamp,
+        expires_on -> Nullable<Timestamp>,
+        revoked_on -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    namespaces (name) {
+        name -> Text,
+        version_scheme -> Text,
+        package_creation -> Text,
+        created_by -> Nullable<Text>,
+        created_on -> Timestamp,
+    }
+}
+
+table! {
+    device_logins (device_code) {
+        device_code -> Text,
+        user_code -> Text,
+        auth_token -> Nullable<Text>,
+        stamp -> Timestamp,
+    }
+}
+
 table! {
     packages (namespace, name) {
         namespace -> Text,
@@ -35,6 +80,37 @@ table! {
     }
 }
 
+table! {
+    package_org_owners (namespace, name, org_id) {
+        namespace -> Text,
+        name -> Text,
+        org_id -> Text,
+        org_name -> Text,
+        added_time -> Timestamp,
+    }
+}
+
+table! {
+    namespace_org_owners (namespace, org_id) {
+        namespace -> Text,
+        org_id -> Text,
+        org_name -> Text,
+        added_time -> Timestamp,
+    }
+}
+
+table! {
+    package_owner_log (id) {
+        id -> Integer,
+        namespace -> Text,
+        name -> Text,
+        action -> Text,
+        owner -> Text,
+        actor -> Text,
+        time -> Timestamp,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel::pg::types::sql_types::Array;
@@ -58,6 +134,16 @@ table! {
         deprecated_on -> Nullable<Timestamp>,
         deleted -> Nullable<Text>,
         deleted_on -> Nullable<Timestamp>,
-        tarBrBlob -> Binary,
+        artifact_hash -> Text,
+        artifact_size -> BigInt,
+        dependencies -> Text,
+        yanked -> Bool,
+        yanked_by -> Nullable<Text>,
+        yanked_on -> Nullable<Timestamp>,
+        authors -> Array<Text>,
+        homepage -> Nullable<Text>,
+        repository -> Nullable<Text>,
+        bugs -> Nullable<Text>,
+        normalized_manifest -> Nullable<Text>,
     }
 }
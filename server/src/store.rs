@@ -1,5 +1,6 @@
 use std::env;
 use std::str::FromStr;
+use std::time::SystemTime;
 
 use diesel;
 use diesel::expression::dsl::now;
@@ -11,12 +12,20 @@ use diesel::result::Error::DatabaseError;
 
 use data_encoding::BASE64;
 
+use blob::{self, BlobStore};
+use device;
 use error::{Error, Res};
-use file::File;
-use package::{Package, PackageOwner, Release};
-use user::{User, UserRecord};
+use namespace::{Namespace, PackageCreation, VersionScheme};
+use token::ApiToken;
+use package::{
+    NamespaceOrgOwner, OwnerLogEntry, Package, PackageOrgOwner, PackageOwner, Release,
+};
+use user::{Org, OrgRecord, User, UserRecord};
 
-use schema::{login_sessions, package_owners, package_releases, packages, users};
+use schema::{
+    api_tokens, device_logins, files, login_sessions, namespace_org_owners, namespaces,
+    package_org_owners, package_owner_log, package_owners, package_releases, packages, users,
+};
 
 #[allow(dead_code)]
 #[derive(Queryable)]
@@ -33,14 +42,266 @@ pub struct NewLoginSession {
     callback: String,
 }
 
+/// A release artifact stored in Postgres, from before they were kept in the
+/// blob store. `name` is the artifact's file name, `<name>-<version>.tar.br`.
+#[allow(dead_code)]
+#[derive(Queryable)]
+pub struct LegacyFile {
+    namespace: String,
+    name: String,
+    data: Vec<u8>,
+    uploaded_on: SystemTime,
+}
+
+fn log_owner_change(
+    db: &PgConnection,
+    namespace: &str,
+    name: &str,
+    action: &str,
+    owner: &str,
+    actor: &User,
+) -> Res<()> {
+    diesel::insert_into(package_owner_log::table)
+        .values(&OwnerLogEntry {
+            namespace: namespace.to_owned(),
+            name: name.to_owned(),
+            action: action.to_owned(),
+            owner: owner.to_owned(),
+            actor: actor.to_string(),
+            time: SystemTime::now(),
+        }).execute(db)?;
+    Ok(())
+}
+
+pub fn get_release(db: &PgConnection, namespace: &str, name: &str, version: &str) -> Res<Release> {
+    let results: Vec<Release> = package_releases::table
+        .filter(
+            package_releases::namespace.eq(namespace).and(
+                package_releases::name
+                    .eq(name)
+                    .and(package_releases::version.eq(version)),
+            ),
+        ).load(db)?;
+    match results.into_iter().next() {
+        None => Err(Error::UnknownRelease(
+            namespace.to_string(),
+            name.to_string(),
+            version.to_string(),
+        )),
+        Some(release) => Ok(release),
+    }
+}
+
+/// Like `get_release`, but fail with `UnknownRelease` for a deleted release
+/// too.
+pub fn get_live_release(
+    db: &PgConnection,
+    namespace: &str,
+    name: &str,
+    version: &str,
+) -> Res<Release> {
+    let release = get_release(db, namespace, name, version)?;
+    if release.deleted.is_some() {
+        return Err(Error::UnknownRelease(
+            namespace.to_string(),
+            name.to_string(),
+            version.to_string(),
+        ));
+    }
+    Ok(release)
+}
+
+pub fn add_package_owner(
+    db: &PgConnection,
+    namespace: &str,
+    name: &str,
+    owner: &User,
+    actor: &User,
+) -> Res<()> {
+    db.transaction(|| {
+        match diesel::insert_into(package_owners::table)
+            .values(&PackageOwner {
+                namespace: namespace.to_owned(),
+                name: name.to_owned(),
+                user_id: owner.to_string(),
+                added_time: SystemTime::now(),
+            }).execute(db)
+        {
+            Ok(_) => (),
+            Err(DatabaseError(UniqueViolation, _)) => {
+                return Err(Error::AlreadyOwner(
+                    namespace.to_owned(),
+                    name.to_owned(),
+                    owner.to_string(),
+                ))
+            }
+            Err(e) => return Err(Error::from(e)),
+        }
+        log_owner_change(db, namespace, name, "add", &owner.to_string(), actor)
+    })
+}
+
+/// Remove `owner` from the package's owners unless they're the last one. The
+/// caller runs this in a serializable transaction.
+pub fn remove_package_owner(
+    db: &PgConnection,
+    namespace: &str,
+    name: &str,
+    owner: &User,
+    actor: &User,
+) -> Res<()> {
+    let owners: Vec<PackageOwner> = package_owners::table
+        .filter(
+            package_owners::namespace
+                .eq(namespace)
+                .and(package_owners::name.eq(name)),
+        ).load(db)?;
+    if !owners.iter().any(|o| o.user_id == owner.to_string()) {
+        return Err(Error::NotOwner(
+            namespace.to_owned(),
+            name.to_owned(),
+            owner.to_string(),
+        ));
+    }
+    if owners.len() == 1 {
+        return Err(Error::LastOwner(
+            namespace.to_owned(),
+            name.to_owned(),
+            owner.clone(),
+        ));
+    }
+    diesel::delete(
+        package_owners::table.filter(
+            package_owners::namespace.eq(namespace).and(
+                package_owners::name
+                    .eq(name)
+                    .and(package_owners::user_id.eq(&owner.to_string())),
+            ),
+        ),
+    ).execute(db)?;
+    log_owner_change(db, namespace, name, "remove", &owner.to_string(), actor)
+}
+
+pub fn get_namespace(db: &PgConnection, name: &str) -> Res<Namespace> {
+    let results = namespaces::table
+        .filter(namespaces::name.eq(name))
+        .load(db)?;
+    match results.into_iter().next() {
+        None => Err(Error::UnknownNamespace(name.to_owned())),
+        Some(namespace) => Ok(namespace),
+    }
+}
+
+pub fn get_package(db: &PgConnection, namespace: &str, name: &str) -> Res<Package> {
+    let results = packages::table
+        .filter(
+            packages::namespace
+                .eq(&namespace)
+                .and(packages::name.eq(&name)),
+        ).load(db)?;
+    match results.into_iter().next() {
+        None => Err(Error::UnknownPackage(namespace.to_owned(), name.to_owned())),
+        Some(pkg) => Ok(pkg),
+    }
+}
+
+pub fn get_namespace_org_owners(db: &PgConnection, namespace: &str) -> Res<Vec<OrgRecord>> {
+    let results: Vec<NamespaceOrgOwner> = namespace_org_owners::table
+        .filter(namespace_org_owners::namespace.eq(namespace))
+        .load(db)?;
+    results
+        .into_iter()
+        .map(|o| {
+            Ok(OrgRecord {
+                id: Org::from_str(&o.org_id)?,
+                name: o.org_name,
+            })
+        }).collect()
+}
+
+/// Make the organisation `org` an owner of every package in `namespace`.
+pub fn add_namespace_org_owner(db: &PgConnection, namespace: &str, org: &OrgRecord) -> Res<()> {
+    get_namespace(db, namespace)?;
+    match diesel::insert_into(namespace_org_owners::table)
+        .values(&NamespaceOrgOwner {
+            namespace: namespace.to_owned(),
+            org_id: org.id.to_string(),
+            org_name: org.name.clone(),
+            added_time: SystemTime::now(),
+        }).execute(db)
+    {
+        Ok(_) => Ok(()),
+        Err(DatabaseError(UniqueViolation, _)) => Err(Error::AlreadyNamespaceOwner(
+            namespace.to_owned(),
+            org.name.clone(),
+        )),
+        Err(e) => Err(Error::from(e)),
+    }
+}
+
+/// Remove the organisation `org` as an owner of `namespace`. Namespaces can
+/// be left without owning organisations, as administrators manage them all.
+pub fn remove_namespace_org_owner(db: &PgConnection, namespace: &str, org: &Org) -> Res<()> {
+    let removed = diesel::delete(
+        namespace_org_owners::table.filter(
+            namespace_org_owners::namespace
+                .eq(namespace)
+                .and(namespace_org_owners::org_id.eq(&org.to_string())),
+        ),
+    ).execute(db)?;
+    if removed == 0 {
+        Err(Error::NotNamespaceOwner(
+            namespace.to_owned(),
+            org.to_string(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Parse the comma separated list of user IDs in the `REGISTRY_ADMINS`
+/// environment variable.
+fn admins_from_env() -> Res<Vec<User>> {
+    match env::var("REGISTRY_ADMINS") {
+        Err(env::VarError::NotPresent) => Ok(vec![]),
+        Err(err) => Err(Error::from(err)),
+        Ok(admins) => admins
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(User::from_str)
+            .collect(),
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Queryable)]
+pub struct DeviceLogin {
+    device_code: String,
+    user_code: String,
+    auth_token: Option<String>,
+    stamp: SystemTime,
+}
+
+#[derive(Insertable)]
+#[table_name = "device_logins"]
+pub struct NewDeviceLogin {
+    device_code: String,
+    user_code: String,
+}
+
 pub struct Store {
     db_url: String,
+    blobs: Box<BlobStore>,
+    admins: Vec<User>,
 }
 
 impl Store {
     pub fn new() -> Res<Store> {
         Ok(Store {
             db_url: env::var("DATABASE_URL")?,
+            blobs: blob::from_env()?,
+            admins: admins_from_env()?,
         })
     }
 
@@ -48,6 +309,87 @@ impl Store {
         Ok(PgConnection::establish(&self.db_url)?)
     }
 
+    pub fn blobs(&self) -> &BlobStore {
+        &*self.blobs
+    }
+
+    pub fn admins(&self) -> &[User] {
+        &self.admins
+    }
+
+    pub fn is_admin(&self, user: &User) -> bool {
+        self.admins.contains(user)
+    }
+
+    /// Fail with `NotAdmin` unless `user` is a registry administrator.
+    pub fn check_admin(&self, user: &User) -> Res<()> {
+        if self.is_admin(user) {
+            Ok(())
+        } else {
+            Err(Error::NotAdmin(user.clone()))
+        }
+    }
+
+    /// Move the artifacts left in the `files` table into the blob store, and
+    /// point their releases at them. A file is only deleted once its release
+    /// has been updated, so this can be interrupted and run again; files
+    /// matching no release are left where they are.
+    pub fn import_legacy_files(&self) -> Res<()> {
+        let db = self.db()?;
+        let keys: Vec<(String, String)> = files::table
+            .select((files::namespace, files::name))
+            .load(&db)?;
+        for (namespace, filename) in keys {
+            let releases: Vec<Release> = package_releases::table
+                .filter(
+                    package_releases::namespace
+                        .eq(&namespace)
+                        .and(package_releases::artifact_hash.eq("")),
+                ).load(&db)?;
+            let release = match releases
+                .into_iter()
+                .find(|release| format!("{}-{}.tar.br", release.name, release.version) == filename)
+            {
+                None => {
+                    println!("no release for legacy artifact {}/{}", namespace, filename);
+                    continue;
+                }
+                Some(release) => release,
+            };
+            let file: LegacyFile = files::table
+                .filter(files::namespace.eq(&namespace).and(files::name.eq(&filename)))
+                .first(&db)?;
+            let hash = self.blobs.put(&file.data)?;
+            db.transaction::<_, Error, _>(|| {
+                diesel::update(
+                    package_releases::table.filter(
+                        package_releases::namespace.eq(&namespace).and(
+                            package_releases::name
+                                .eq(&release.name)
+                                .and(package_releases::version.eq(&release.version)),
+                        ),
+                    ),
+                ).set((
+                    package_releases::artifact_hash.eq(&hash),
+                    package_releases::artifact_size.eq(file.data.len() as i64),
+                    package_releases::artifact_url.eq(format!(
+                        "/files/{}/{}/{}",
+                        namespace, release.name, release.version
+                    )),
+                )).execute(&db)?;
+                diesel::delete(
+                    files::table.filter(
+                        files::namespace
+                            .eq(&namespace)
+                            .and(files::name.eq(&filename)),
+                    ),
+                ).execute(&db)?;
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+
     pub fn register_login(&self, token: &str, callback: &str) -> Res<()> {
         let db = self.db()?;
         if BASE64.decode(token.as_bytes()).is_err() {
@@ -80,6 +422,84 @@ impl Store {
         }
     }
 
+    /// Register a device login polled for with `device_code`, and return the
+    /// user code it was given. User codes are short enough to now and then
+    /// collide with a pending login, so a taken one is replaced with another.
+    pub fn register_device_login(&self, device_code: &str) -> Res<String> {
+        let db = self.db()?;
+        let mut attempts = 0;
+        loop {
+            let user_code = device::user_code();
+            match diesel::insert_into(device_logins::table)
+                .values(&NewDeviceLogin {
+                    device_code: device_code.to_string(),
+                    user_code: user_code.clone(),
+                }).execute(&db)
+            {
+                Ok(_) => return Ok(user_code),
+                Err(DatabaseError(UniqueViolation, _)) if attempts < 10 => attempts += 1,
+                Err(e) => return Err(Error::from(e)),
+            }
+        }
+    }
+
+    /// Fail unless `user_code` belongs to a device login still waiting for
+    /// the user to authenticate.
+    pub fn check_device_login(&self, user_code: &str) -> Res<()> {
+        let db = self.db()?;
+        let results: Vec<DeviceLogin> = device_logins::table
+            .filter(device_logins::user_code.eq(user_code))
+            .filter(device_logins::auth_token.is_null())
+            .filter(device_logins::stamp.gt(now - 15.minutes()))
+            .load(&db)?;
+        match results.into_iter().next() {
+            None => Err(Error::UnknownDeviceLogin(user_code.to_string())),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Hand `auth_token` to the device login waiting on `user_code`. Checking
+    /// that it's still waiting is part of the update, so two logins can't both
+    /// complete it.
+    pub fn complete_device_login(&self, user_code: &str, auth_token: &str) -> Res<()> {
+        let db = self.db()?;
+        let updated = diesel::update(
+            device_logins::table
+                .filter(device_logins::user_code.eq(user_code))
+                .filter(device_logins::auth_token.is_null())
+                .filter(device_logins::stamp.gt(now - 15.minutes())),
+        ).set(device_logins::auth_token.eq(Some(auth_token)))
+        .execute(&db)?;
+        if updated == 0 {
+            Err(Error::UnknownDeviceLogin(user_code.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Return the auth token for a device login once the user has
+    /// authenticated, or `None` while we're still waiting for them. The token
+    /// can only be collected once.
+    pub fn poll_device_login(&self, device_code: &str) -> Res<Option<String>> {
+        let db = self.db()?;
+        let results: Vec<DeviceLogin> = device_logins::table
+            .filter(device_logins::device_code.eq(device_code))
+            .filter(device_logins::stamp.gt(now - 15.minutes()))
+            .load(&db)?;
+        match results.into_iter().next() {
+            None => Err(Error::InvalidLoginState(device_code.to_string())),
+            Some(DeviceLogin {
+                auth_token: None, ..
+            }) => Ok(None),
+            Some(login) => {
+                diesel::delete(
+                    device_logins::table.filter(device_logins::device_code.eq(device_code)),
+                ).execute(&db)?;
+                Ok(login.auth_token)
+            }
+        }
+    }
+
     pub fn update_user(&self, user: &UserRecord) -> Res<()> {
         let db = self.db()?;
         match self.get_user(&user.user()?) {
@@ -111,20 +531,100 @@ impl Store {
         }
     }
 
-    pub fn get_package(&self, namespace: &str, name: &str) -> Res<Package> {
+    pub fn insert_api_token(&self, token: &ApiToken) -> Res<()> {
         let db = self.db()?;
-        let results = packages::table
-            .filter(
-                packages::namespace
-                    .eq(&namespace)
-                    .and(packages::name.eq(&name)),
-            ).load(&db)?;
+        diesel::insert_into(api_tokens::table)
+            .values(token)
+            .execute(&db)?;
+        Ok(())
+    }
+
+    pub fn get_api_token_by_hash(&self, token_hash: &str) -> Res<ApiToken> {
+        let db = self.db()?;
+        let results = api_tokens::table
+            .filter(api_tokens::token_hash.eq(token_hash))
+            .load(&db)?;
         match results.into_iter().next() {
-            None => Err(Error::UnknownPackage(namespace.to_owned(), name.to_owned())),
-            Some(pkg) => Ok(pkg),
+            None => Err(Error::UnknownToken(token_hash.to_owned())),
+            Some(token) => Ok(token),
+        }
+    }
+
+    pub fn get_api_tokens(&self, user: &User) -> Res<Vec<ApiToken>> {
+        let db = self.db()?;
+        Ok(api_tokens::table
+            .filter(api_tokens::user_id.eq(user.to_string()))
+            .order(api_tokens::created_on)
+            .load(&db)?)
+    }
+
+    pub fn revoke_api_token(&self, user: &User, id: &str) -> Res<()> {
+        let db = self.db()?;
+        let updated = diesel::update(
+            api_tokens::table.filter(
+                api_tokens::id
+                    .eq(id)
+                    .and(api_tokens::user_id.eq(user.to_string()))
+                    .and(api_tokens::revoked_on.is_null()),
+            ),
+        ).set(api_tokens::revoked_on.eq(Some(SystemTime::now())))
+        .execute(&db)?;
+        if updated == 0 {
+            Err(Error::UnknownToken(id.to_owned()))
+        } else {
+            Ok(())
         }
     }
 
+    pub fn get_namespace(&self, name: &str) -> Res<Namespace> {
+        get_namespace(&self.db()?, name)
+    }
+
+    pub fn get_namespaces(&self) -> Res<Vec<Namespace>> {
+        let db = self.db()?;
+        Ok(namespaces::table.order(namespaces::name).load(&db)?)
+    }
+
+    pub fn insert_namespace(&self, namespace: &Namespace) -> Res<()> {
+        let db = self.db()?;
+        match diesel::insert_into(namespaces::table)
+            .values(namespace)
+            .execute(&db)
+        {
+            Ok(_) => Ok(()),
+            Err(DatabaseError(UniqueViolation, _)) => {
+                Err(Error::NamespaceAlreadyExists(namespace.name.clone()))
+            }
+            Err(e) => Err(Error::from(e)),
+        }
+    }
+
+    /// Change the policies of a namespace, leaving those given as `None` as
+    /// they are.
+    pub fn update_namespace(
+        &self,
+        name: &str,
+        version_scheme: Option<VersionScheme>,
+        package_creation: Option<PackageCreation>,
+    ) -> Res<()> {
+        let namespace = self.get_namespace(name)?;
+        let db = self.db()?;
+        diesel::update(namespaces::table.filter(namespaces::name.eq(name)))
+            .set((
+                namespaces::version_scheme.eq(version_scheme
+                    .map(|scheme| scheme.to_string())
+                    .unwrap_or(namespace.version_scheme)),
+                namespaces::package_creation.eq(package_creation
+                    .map(|policy| policy.to_string())
+                    .unwrap_or(namespace.package_creation)),
+            )).execute(&db)?;
+        Ok(())
+    }
+
+    pub fn get_package(&self, namespace: &str, name: &str) -> Res<Package> {
+        get_package(&self.db()?, namespace, name)
+    }
+
     pub fn insert_package(&self, namespace: &str, name: &str, owner: &User) -> Res<()> {
         let db = self.db()?;
         match diesel::insert_into(packages::table)
@@ -135,7 +635,7 @@ impl Store {
                 deleted_on: None,
             }).execute(&db)
         {
-            Ok(_) => self.add_package_owner(namespace, name, owner),
+            Ok(_) => self.add_package_owner(namespace, name, owner, owner),
             Err(_) => Ok(()),
         }
     }
@@ -151,30 +651,130 @@ impl Store {
         results.iter().map(|o| User::from_str(&o.user_id)).collect()
     }
 
-    pub fn add_package_owner(&self, namespace: &str, name: &str, owner: &User) -> Res<()> {
+    /// Make `owner` an owner of the package, recording that `actor` did so.
+    pub fn add_package_owner(
+        &self,
+        namespace: &str,
+        name: &str,
+        owner: &User,
+        actor: &User,
+    ) -> Res<()> {
+        self.get_user(owner)?;
+        add_package_owner(&self.db()?, namespace, name, owner, actor)
+    }
+
+    /// Remove `owner` as an owner of the package, recording that `actor` did
+    /// so. A package can't be left without owners, so removing the last one
+    /// fails.
+    pub fn remove_package_owner(
+        &self,
+        namespace: &str,
+        name: &str,
+        owner: &User,
+        actor: &User,
+    ) -> Res<()> {
         let db = self.db()?;
-        diesel::insert_into(package_owners::table)
-            .values(&PackageOwner {
-                namespace: namespace.to_owned(),
-                name: name.to_owned(),
-                user_id: owner.to_string(),
-                added_time: SystemTime::now(),
-            }).execute(&db)?;
-        Ok(())
+        // Serializable, so that two concurrent removals can't both see the
+        // other owner and leave the package with none.
+        db.build_transaction()
+            .serializable()
+            .run(|| remove_package_owner(&db, namespace, name, owner, actor))
     }
 
-    pub fn remove_package_owner(&self, namespace: &str, name: &str, owner: &User) -> Res<()> {
+    pub fn get_package_org_owners(&self, namespace: &str, name: &str) -> Res<Vec<OrgRecord>> {
         let db = self.db()?;
-        diesel::delete(
-            package_owners::table.filter(
-                package_owners::namespace.eq(namespace).and(
-                    package_owners::name
-                        .eq(name)
-                        .and(package_owners::user_id.eq(&owner.to_string())),
+        let results: Vec<PackageOrgOwner> = package_org_owners::table
+            .filter(
+                package_org_owners::namespace
+                    .eq(namespace)
+                    .and(package_org_owners::name.eq(name)),
+            ).load(&db)?;
+        results
+            .into_iter()
+            .map(|o| {
+                Ok(OrgRecord {
+                    id: Org::from_str(&o.org_id)?,
+                    name: o.org_name,
+                })
+            }).collect()
+    }
+
+    /// List the organisations which own every package in `namespace`.
+    pub fn get_namespace_org_owners(&self, namespace: &str) -> Res<Vec<OrgRecord>> {
+        get_namespace_org_owners(&self.db()?, namespace)
+    }
+
+    pub fn add_namespace_org_owner(&self, namespace: &str, org: &OrgRecord) -> Res<()> {
+        add_namespace_org_owner(&self.db()?, namespace, org)
+    }
+
+    pub fn remove_namespace_org_owner(&self, namespace: &str, org: &Org) -> Res<()> {
+        remove_namespace_org_owner(&self.db()?, namespace, org)
+    }
+
+    /// Make the organisation `org` an owner of the package, recording that
+    /// `actor` did so.
+    pub fn add_package_org_owner(
+        &self,
+        namespace: &str,
+        name: &str,
+        org: &OrgRecord,
+        actor: &User,
+    ) -> Res<()> {
+        let db = self.db()?;
+        db.transaction(|| {
+            match diesel::insert_into(package_org_owners::table)
+                .values(&PackageOrgOwner {
+                    namespace: namespace.to_owned(),
+                    name: name.to_owned(),
+                    org_id: org.id.to_string(),
+                    org_name: org.name.clone(),
+                    added_time: SystemTime::now(),
+                }).execute(&db)
+            {
+                Ok(_) => (),
+                Err(DatabaseError(UniqueViolation, _)) => {
+                    return Err(Error::AlreadyOwner(
+                        namespace.to_owned(),
+                        name.to_owned(),
+                        org.name.clone(),
+                    ))
+                }
+                Err(e) => return Err(Error::from(e)),
+            }
+            log_owner_change(&db, namespace, name, "add-org", &org.id.to_string(), actor)
+        })
+    }
+
+    /// Remove the organisation `org` as an owner of the package, recording
+    /// that `actor` did so.
+    pub fn remove_package_org_owner(
+        &self,
+        namespace: &str,
+        name: &str,
+        org: &Org,
+        actor: &User,
+    ) -> Res<()> {
+        let db = self.db()?;
+        db.transaction(|| {
+            let removed = diesel::delete(
+                package_org_owners::table.filter(
+                    package_org_owners::namespace.eq(namespace).and(
+                        package_org_owners::name
+                            .eq(name)
+                            .and(package_org_owners::org_id.eq(&org.to_string())),
+                    ),
                 ),
-            ),
-        ).execute(&db)?;
-        Ok(())
+            ).execute(&db)?;
+            if removed == 0 {
+                return Err(Error::NotOwner(
+                    namespace.to_owned(),
+                    name.to_owned(),
+                    org.to_string(),
+                ));
+            }
+            log_owner_change(&db, namespace, name, "remove-org", &org.to_string(), actor)
+        })
     }
 
     pub fn get_releases(&self, namespace: &str, name: &str) -> Res<Vec<Release>> {
@@ -203,14 +803,41 @@ impl Store {
         }
     }
 
-    pub fn get_file(&self, namespace: &str, name: &str) -> Res<File> {
+    /// Mark a release as yanked by `yanked_by`, or un-yank it if `yanked_by` is
+    /// `None`.
+    pub fn set_yanked(
+        &self,
+        namespace: &str,
+        name: &str,
+        version: &str,
+        yanked_by: Option<&User>,
+    ) -> Res<()> {
         let db = self.db()?;
-        let results: Vec<File> = releases::table
-            .filter(files::namespace.eq(namespace).and(files::name.eq(name)))
-            .load(&db)?;
-        match results.into_iter().next() {
-            None => Err(Error::UnknownFile(namespace.to_string(), name.to_string())),
-            Some(file) => Ok(file),
+        let updated = diesel::update(
+            package_releases::table.filter(
+                package_releases::namespace.eq(namespace).and(
+                    package_releases::name
+                        .eq(name)
+                        .and(package_releases::version.eq(version)),
+                ),
+            ),
+        ).set((
+            package_releases::yanked.eq(yanked_by.is_some()),
+            package_releases::yanked_by.eq(yanked_by.map(|user| user.to_string())),
+            package_releases::yanked_on.eq(yanked_by.map(|_| SystemTime::now())),
+        )).execute(&db)?;
+        if updated == 0 {
+            Err(Error::UnknownRelease(
+                namespace.to_string(),
+                name.to_string(),
+                version.to_string(),
+            ))
+        } else {
+            Ok(())
         }
     }
+
+    pub fn get_live_release(&self, namespace: &str, name: &str, version: &str) -> Res<Release> {
+        get_live_release(&self.db()?, namespace, name, version)
+    }
 }
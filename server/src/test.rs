@@ -3,14 +3,29 @@ use diesel::prelude::*;
 use diesel::{self, Connection};
 use dotenv;
 use std::env;
-use std::time::SystemTime;
+use std::io::Read;
+use std::time::{Duration, SystemTime};
 
-use auth::AuthSource;
+use pm_lib::package::PackageName;
+use pm_lib::version::Version;
+
+use auth::{public_url, AuthSource, Identity};
+use blob::{self, BlobStore, FsBlobStore, S3BlobStore};
+use device;
+use download::{parse_range, ByteRange};
 use error::Error;
+use index;
+use namespace::{Namespace, VersionScheme};
+use owner;
 use package::{Package, PackageOwner, Release};
-use schema::{package_owners, package_releases, packages, users};
+use schema::{
+    namespaces, package_owner_log, package_owners, package_releases, packages, users,
+};
 use search::{search_db, SearchResult};
-use user::{User, UserRecord};
+use store;
+use token::{self, ApiToken, Scope};
+use upload;
+use user::{Org, OrgRecord, User, UserRecord};
 
 embed_migrations!("migrations");
 
@@ -80,6 +95,17 @@ fn insert_package(
                 deprecated_on: None,
                 deleted: None,
                 deleted_on: None,
+                artifact_hash: blob::hash(name.as_bytes()),
+                artifact_size: name.len() as i64,
+                dependencies: "{}".to_string(),
+                yanked: false,
+                yanked_by: None,
+                yanked_on: None,
+                authors: vec![],
+                homepage: None,
+                repository: None,
+                bugs: None,
+                normalized_manifest: None,
             }).execute(db)?;
     }
     Ok(())
@@ -87,6 +113,14 @@ fn insert_package(
 
 fn packages_fixture(db: &PgConnection) -> Result<(), Error> {
     test_user_fixture(db)?;
+    diesel::insert_into(namespaces::table)
+        .values(&Namespace {
+            name: "test".to_string(),
+            version_scheme: "any".to_string(),
+            package_creation: "anyone".to_string(),
+            created_by: None,
+            created_on: SystemTime::now(),
+        }).execute(db)?;
     insert_package(db, "left-pad", "test:user", &["1.0", "1.1", "2.0"])?;
     insert_package(db, "right-pad", "test:user", &["1.0", "1.1", "2.0"])?;
     insert_package(db, "profunctor-optics", "test:user", &["1.0", "1.1", "2.0"])?;
@@ -123,3 +157,355 @@ fn test_package_search() {
         search_db(&db, "test", vec!["pad".to_string()]).unwrap()
     );
 }
+
+fn set_manifest(db: &PgConnection, version: &str, source: &str, normalized: Option<&str>) {
+    diesel::update(
+        package_releases::table.filter(
+            package_releases::name
+                .eq("left-pad")
+                .and(package_releases::version.eq(version)),
+        ),
+    ).set((
+        package_releases::manifest.eq(source),
+        package_releases::normalized_manifest.eq(normalized),
+    )).execute(db)
+    .unwrap();
+}
+
+#[test]
+fn test_release_manifest() {
+    let db = get_db(packages_fixture);
+    set_manifest(&db, "1.1", "name test/left-pad", Some("{\"lol\": 1}"));
+    set_manifest(&db, "2.0", "name test/left-pad", None);
+
+    // Releases published before manifests were stored have neither.
+    let manifest = index::release_manifest_db(&db, "test", "left-pad", "1.0").unwrap();
+    assert_eq!(None, manifest.source);
+    assert_eq!(None, manifest.manifest);
+    let manifest = index::release_manifest_db(&db, "test", "left-pad", "2.0").unwrap();
+    assert_eq!(Some("name test/left-pad".to_string()), manifest.source);
+
+    match index::release_manifest_db(&db, "test", "left-pad", "0.1") {
+        Err(Error::UnknownRelease(..)) => (),
+        other => panic!("expected UnknownRelease, got {:?}", other),
+    }
+    // A normalized manifest that can't be read is an error, not a missing
+    // release.
+    match index::release_manifest_db(&db, "test", "left-pad", "1.1") {
+        Err(Error::JSON(..)) => (),
+        other => panic!("expected a JSON error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_deleted_release() {
+    let db = get_db(packages_fixture);
+    diesel::update(
+        package_releases::table.filter(
+            package_releases::name
+                .eq("left-pad")
+                .and(package_releases::version.eq("1.1")),
+        ),
+    ).set(package_releases::deleted.eq(Some("malware")))
+    .execute(&db)
+    .unwrap();
+
+    assert!(store::get_live_release(&db, "test", "left-pad", "1.0").is_ok());
+    assert!(store::get_release(&db, "test", "left-pad", "1.1").is_ok());
+    match store::get_live_release(&db, "test", "left-pad", "1.1") {
+        Err(Error::UnknownRelease(..)) => (),
+        other => panic!("expected UnknownRelease, got {:?}", other),
+    }
+}
+
+fn owner_log(db: &PgConnection) -> Vec<(String, String, String)> {
+    package_owner_log::table
+        .filter(package_owner_log::name.eq("left-pad"))
+        .order(package_owner_log::id)
+        .select((
+            package_owner_log::action,
+            package_owner_log::owner,
+            package_owner_log::actor,
+        )).load(db)
+        .unwrap()
+}
+
+#[test]
+fn test_package_owners() {
+    let db = get_db(packages_fixture);
+    let user = User::new(AuthSource::Test, "user");
+    let other = User::new(AuthSource::Test, "other");
+
+    match store::remove_package_owner(&db, "test", "left-pad", &user, &user) {
+        Err(Error::LastOwner(..)) => (),
+        result => panic!("expected LastOwner, got {:?}", result),
+    }
+    match store::remove_package_owner(&db, "test", "left-pad", &other, &user) {
+        Err(Error::NotOwner(..)) => (),
+        result => panic!("expected NotOwner, got {:?}", result),
+    }
+    store::add_package_owner(&db, "test", "left-pad", &other, &user).unwrap();
+    match store::add_package_owner(&db, "test", "left-pad", &other, &user) {
+        Err(Error::AlreadyOwner(..)) => (),
+        result => panic!("expected AlreadyOwner, got {:?}", result),
+    }
+    store::remove_package_owner(&db, "test", "left-pad", &user, &user).unwrap();
+    match store::remove_package_owner(&db, "test", "left-pad", &other, &user) {
+        Err(Error::LastOwner(..)) => (),
+        result => panic!("expected LastOwner, got {:?}", result),
+    }
+
+    // Only the changes which happened are logged.
+    let entry = |action: &str, owner: &str| {
+        (action.to_string(), owner.to_string(), "test:user".to_string())
+    };
+    assert_eq!(
+        vec![entry("add", "test:other"), entry("remove", "test:user")],
+        owner_log(&db)
+    );
+}
+
+fn policy_fixture(db: &PgConnection) -> Result<(), Error> {
+    packages_fixture(db)?;
+    diesel::insert_into(namespaces::table)
+        .values(&Namespace {
+            name: "strict".to_string(),
+            version_scheme: "semver".to_string(),
+            package_creation: "members".to_string(),
+            created_by: None,
+            created_on: SystemTime::now(),
+        }).execute(db)?;
+    Ok(())
+}
+
+#[test]
+fn test_namespace_policy() {
+    let db = get_db(policy_fixture);
+    let identity = Identity::api(&api_token(&["publish"], &[])).unwrap();
+    let check = |package: &str, version: &str, admins: &[User]| {
+        upload::check_namespace_policy(
+            &db,
+            admins,
+            &identity,
+            &PackageName::from_str(package).unwrap(),
+            &Version::from_str(version).unwrap(),
+        )
+    };
+    let admin = vec![User::new(AuthSource::Test, "user")];
+
+    assert!(check("test/left-pad", "3.0", &[]).is_ok());
+    assert!(check("test/centre-pad", "1.0", &[]).is_ok());
+    match check("lol/left-pad", "1.0.0", &[]) {
+        Err(Error::UnknownNamespace(..)) => (),
+        result => panic!("expected UnknownNamespace, got {:?}", result),
+    }
+    match check("strict/left-pad", "1.0", &admin) {
+        Err(Error::VersionSchemeViolation(..)) => (),
+        result => panic!("expected VersionSchemeViolation, got {:?}", result),
+    }
+    match check("strict/left-pad", "1.0.0", &[]) {
+        Err(Error::PackageCreationDenied(..)) => (),
+        result => panic!("expected PackageCreationDenied, got {:?}", result),
+    }
+    assert!(check("strict/left-pad", "1.0.0", &admin).is_ok());
+}
+
+#[test]
+fn test_namespace_org_owners() {
+    let db = get_db(policy_fixture);
+    let org = OrgRecord {
+        id: Org {
+            provider: AuthSource::Test,
+            id: "comrades".to_string(),
+        },
+        name: "comrades".to_string(),
+    };
+    let member = Identity::test(&User::new(AuthSource::Test, "member"), &[org.clone()]);
+    let outsider = Identity::test(&User::new(AuthSource::Test, "outsider"), &[]);
+    let check = |identity: &Identity| {
+        upload::check_namespace_policy(
+            &db,
+            &[],
+            identity,
+            &PackageName::from_str("strict/left-pad").unwrap(),
+            &Version::from_str("1.0.0").unwrap(),
+        )
+    };
+
+    match check(&member) {
+        Err(Error::PackageCreationDenied(..)) => (),
+        result => panic!("expected PackageCreationDenied, got {:?}", result),
+    }
+    match owner::check_namespace_owner(&db, &[], "strict", &member) {
+        Err(Error::NamespaceAccessDenied(..)) => (),
+        result => panic!("expected NamespaceAccessDenied, got {:?}", result),
+    }
+    match store::add_namespace_org_owner(&db, "lol", &org) {
+        Err(Error::UnknownNamespace(..)) => (),
+        result => panic!("expected UnknownNamespace, got {:?}", result),
+    }
+
+    // Once the organisation owns the namespace, its members may create
+    // packages in it, and manage its owners.
+    store::add_namespace_org_owner(&db, "strict", &org).unwrap();
+    match store::add_namespace_org_owner(&db, "strict", &org) {
+        Err(Error::AlreadyNamespaceOwner(..)) => (),
+        result => panic!("expected AlreadyNamespaceOwner, got {:?}", result),
+    }
+    assert!(check(&member).is_ok());
+    assert!(owner::check_namespace_owner(&db, &[], "strict", &member).is_ok());
+    match check(&outsider) {
+        Err(Error::PackageCreationDenied(..)) => (),
+        result => panic!("expected PackageCreationDenied, got {:?}", result),
+    }
+
+    store::remove_namespace_org_owner(&db, "strict", &org.id).unwrap();
+    match store::remove_namespace_org_owner(&db, "strict", &org.id) {
+        Err(Error::NotNamespaceOwner(..)) => (),
+        result => panic!("expected NotNamespaceOwner, got {:?}", result),
+    }
+    match check(&member) {
+        Err(Error::PackageCreationDenied(..)) => (),
+        result => panic!("expected PackageCreationDenied, got {:?}", result),
+    }
+}
+
+fn assert_blob_roundtrip(blobs: &BlobStore) {
+    let data = b"these are the contents of a release artifact";
+    let hash = blobs.put(data).unwrap();
+    assert_eq!(hash, blob::hash(data));
+    // Storing the same blob again is fine.
+    assert_eq!(blobs.put(data).unwrap(), hash);
+    let mut stored = Vec::new();
+    blobs.get(&hash).unwrap().read_to_end(&mut stored).unwrap();
+    assert_eq!(&stored[..], &data[..]);
+    assert!(blobs.get(&blob::hash(b"not stored")).is_err());
+    assert!(blobs.get("../../etc/passwd").is_err());
+}
+
+#[test]
+fn test_fs_blob_store() {
+    let mut root = env::temp_dir();
+    root.push(format!("pm-blobs-{}", ::std::process::id()));
+    assert_blob_roundtrip(&FsBlobStore::new(root.clone()).unwrap());
+    ::std::fs::remove_dir_all(root).unwrap();
+}
+
+// Runs against an S3 stand-in such as Minio, configured through the `S3_*`
+// environment variables, e.g.:
+//
+// $ minio server /tmp/minio
+// $ S3_ENDPOINT=http://localhost:9000 S3_BUCKET=test S3_ACCESS_KEY=... S3_SECRET_KEY=... \
+//     cargo test -- --ignored test_s3_blob_store
+#[test]
+#[ignore]
+fn test_s3_blob_store() {
+    dotenv::from_filename(".env").ok();
+    assert_blob_roundtrip(&S3BlobStore::from_env().expect("S3_* env vars not defined"));
+}
+
+#[test]
+fn test_parse_range() {
+    assert_eq!(parse_range("bytes=0-99", 1000), ByteRange::Partial(0, 100));
+    assert_eq!(parse_range("bytes=100-", 1000), ByteRange::Partial(100, 900));
+    assert_eq!(parse_range("bytes=-100", 1000), ByteRange::Partial(900, 100));
+    assert_eq!(parse_range("bytes=-2000", 1000), ByteRange::Partial(0, 1000));
+    assert_eq!(parse_range("bytes=900-2000", 1000), ByteRange::Partial(900, 100));
+    assert_eq!(parse_range("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+    assert_eq!(parse_range("bytes=-0", 1000), ByteRange::Unsatisfiable);
+    // Multiple ranges, other units and malformed ranges are ignored.
+    assert_eq!(parse_range("bytes=0-1,5-6", 1000), ByteRange::Full);
+    assert_eq!(parse_range("items=0-1", 1000), ByteRange::Full);
+    assert_eq!(parse_range("bytes=5-1", 1000), ByteRange::Full);
+    assert_eq!(parse_range("bytes=lol", 1000), ByteRange::Full);
+}
+
+#[test]
+fn test_version_scheme() {
+    let version = |s| Version::from_str(s).unwrap();
+    assert!(VersionScheme::Any.allows(&version("1.2")));
+    assert!(VersionScheme::Any.allows(&version("1.2.3.4")));
+    assert!(VersionScheme::Semver.allows(&version("1.2.3")));
+    assert!(VersionScheme::Semver.allows(&version("1.2.3-beta.1+build")));
+    assert!(!VersionScheme::Semver.allows(&version("1.2")));
+    assert!(!VersionScheme::Semver.allows(&version("1.2.3.4")));
+}
+
+fn api_token(scopes: &[&str], packages: &[&str]) -> ApiToken {
+    ApiToken {
+        id: "0123456789abcdef".to_string(),
+        user_id: "test:user".to_string(),
+        name: "ci".to_string(),
+        token_hash: token::hash_token("pm_lol"),
+        scopes: scopes.iter().map(|s| s.to_string()).collect(),
+        packages: packages.iter().map(|s| s.to_string()).collect(),
+        created_on: SystemTime::now(),
+        expires_on: None,
+        revoked_on: None,
+    }
+}
+
+#[test]
+fn test_api_token_scopes() {
+    let identity = Identity::api(&api_token(&["publish"], &[])).unwrap();
+    assert_eq!(User::new(AuthSource::Test, "user"), identity.user);
+    assert!(identity.check_scope(Scope::Publish, "test", "left-pad").is_ok());
+    assert!(identity.check_scope(Scope::Yank, "test", "left-pad").is_err());
+    assert!(identity.require_oauth().is_err());
+    assert!(identity.orgs().is_err());
+
+    let identity = Identity::api(&api_token(&["publish", "yank"], &["test/left-pad"])).unwrap();
+    assert!(identity.check_scope(Scope::Yank, "test", "left-pad").is_ok());
+    assert!(identity.check_scope(Scope::Publish, "test", "right-pad").is_err());
+    assert!(identity.check_scope(Scope::OwnerAdmin, "test", "left-pad").is_err());
+
+    assert!(Identity::api(&api_token(&["lol"], &[])).is_err());
+}
+
+#[test]
+fn test_api_token_expiry() {
+    let mut token = api_token(&["publish"], &[]);
+    assert!(token.is_live());
+    token.expires_on = Some(SystemTime::now() + Duration::from_secs(60));
+    assert!(token.is_live());
+    token.expires_on = Some(SystemTime::now() - Duration::from_secs(60));
+    assert!(!token.is_live());
+    token.expires_on = None;
+    token.revoked_on = Some(SystemTime::now());
+    assert!(!token.is_live());
+
+    assert_eq!(None, token::expiry(None).unwrap());
+    assert!(token::expiry(Some(30)).unwrap().unwrap() > SystemTime::now());
+    assert!(token::expiry(Some(token::MAX_EXPIRY_DAYS)).is_ok());
+    assert!(token::expiry(Some(token::MAX_EXPIRY_DAYS + 1)).is_err());
+    assert!(token::expiry(Some(u64::max_value())).is_err());
+}
+
+#[test]
+fn test_device_user_codes() {
+    let code = device::user_code();
+    assert_eq!(9, code.len());
+    assert_eq!(Some('-'), code.chars().nth(4));
+    assert_eq!(code, device::normalise_user_code(&code));
+    assert_eq!(code, device::normalise_user_code(&code.replace("-", "").to_lowercase()));
+    assert_eq!("BCDF-GHJK", device::normalise_user_code(" bcdf ghjk "));
+}
+
+#[test]
+fn test_public_url() {
+    env::set_var("PUBLIC_URL", "https://example.com/registry");
+    assert_eq!(
+        "https://example.com/registry/device",
+        public_url("device").unwrap().as_str()
+    );
+    env::set_var("PUBLIC_URL", "https://example.com/");
+    assert_eq!(
+        "https://example.com/gitlab/callback",
+        public_url("gitlab/callback").unwrap().as_str()
+    );
+    env::remove_var("PUBLIC_URL");
+    assert_eq!(
+        "http://localhost:8000/device",
+        public_url("device").unwrap().as_str()
+    );
+}
@@ -0,0 +1,147 @@
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Utc};
+use data_encoding::{BASE64URL_NOPAD, HEXLOWER};
+use rand::prelude::random;
+use sha2::{Digest, Sha256};
+
+use error::{Error, Res};
+use schema::api_tokens;
+
+/// Registry issued API tokens are told apart from OAuth credentials by this
+/// prefix.
+pub static TOKEN_PREFIX: &'static str = "pm_";
+
+/// What an API token may be used for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Publish,
+    Yank,
+    OwnerAdmin,
+}
+
+impl Scope {
+    pub fn from_str(name: &str) -> Res<Self> {
+        match name {
+            "publish" => Ok(Scope::Publish),
+            "yank" => Ok(Scope::Yank),
+            "owner-admin" => Ok(Scope::OwnerAdmin),
+            _ => Err(Error::InvalidTokenScope(name.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(match self {
+            &Scope::Publish => "publish",
+            &Scope::Yank => "yank",
+            &Scope::OwnerAdmin => "owner-admin",
+        })
+    }
+}
+
+/// An API token as stored in the database. Only the hash of the secret token
+/// is kept; the token itself is shown to the user once, when it's created.
+#[derive(Identifiable, Queryable, Insertable, Debug)]
+#[table_name = "api_tokens"]
+pub struct ApiToken {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub token_hash: String,
+    pub scopes: Vec<String>,
+    /// `namespace/name` of the packages the token is limited to, or empty if
+    /// it applies to all of the user's packages.
+    pub packages: Vec<String>,
+    pub created_on: SystemTime,
+    pub expires_on: Option<SystemTime>,
+    pub revoked_on: Option<SystemTime>,
+}
+
+impl ApiToken {
+    pub fn scopes(&self) -> Res<Vec<Scope>> {
+        self.scopes.iter().map(|s| Scope::from_str(s)).collect()
+    }
+
+    pub fn is_live(&self) -> bool {
+        self.revoked_on.is_none() && self
+            .expires_on
+            .map(|expiry| expiry > SystemTime::now())
+            .unwrap_or(true)
+    }
+}
+
+/// Generate a new token, returning its ID and the secret token itself.
+pub fn generate() -> (String, String) {
+    let id = HEXLOWER.encode(&random::<[u8; 8]>());
+    let token = format!(
+        "{}{}_{}",
+        TOKEN_PREFIX,
+        id,
+        BASE64URL_NOPAD.encode(&random::<[u8; 32]>())
+    );
+    (id, token)
+}
+
+pub fn hash_token(token: &str) -> String {
+    HEXLOWER.encode(Sha256::digest(token.as_bytes()).as_slice())
+}
+
+/// The longest an API token can be valid for, in days.
+pub const MAX_EXPIRY_DAYS: u64 = 10 * 366;
+
+/// When a token created now and valid for `days` should expire. Fails with
+/// `InvalidTokenExpiry` beyond `MAX_EXPIRY_DAYS`, which also keeps the
+/// arithmetic from overflowing.
+pub fn expiry(days: Option<u64>) -> Res<Option<SystemTime>> {
+    match days {
+        None => Ok(None),
+        Some(days) if days > MAX_EXPIRY_DAYS => Err(Error::InvalidTokenExpiry(days)),
+        Some(days) => Ok(Some(
+            SystemTime::now() + Duration::from_secs(days * 24 * 60 * 60),
+        )),
+    }
+}
+
+fn format_time(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339()
+}
+
+#[derive(Serialize)]
+pub struct NewToken {
+    id: String,
+    token: String,
+}
+
+impl NewToken {
+    pub fn new(id: String, token: String) -> Self {
+        NewToken { id, token }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TokenInfo {
+    id: String,
+    name: String,
+    scopes: Vec<String>,
+    packages: Vec<String>,
+    created_on: String,
+    expires_on: Option<String>,
+    revoked_on: Option<String>,
+}
+
+impl<'a> From<&'a ApiToken> for TokenInfo {
+    fn from(token: &'a ApiToken) -> Self {
+        TokenInfo {
+            id: token.id.clone(),
+            name: token.name.clone(),
+            scopes: token.scopes.clone(),
+            packages: token.packages.clone(),
+            created_on: format_time(token.created_on),
+            expires_on: token.expires_on.map(format_time),
+            revoked_on: token.revoked_on.map(format_time),
+        }
+    }
+}
@@ -2,21 +2,35 @@ use std::io::Read;
 use std::time::SystemTime;
 
 use rmp_serde::decode;
+use serde_json;
 use tar;
 use brotli;
 
+use diesel::pg::PgConnection;
+
 use pm_lib::manifest::{License, Manifest};
+use pm_lib::package::PackageName;
+use pm_lib::version::Version;
 
-use store::Store;
-use user::User;
+use store::{self, Store};
+use auth::Identity;
+use owner::{check_package_creation, check_package_owner};
 use error::{Res, Error};
 use package::Release;
+use token::Scope;
+use user::User;
 
 #[derive(Serialize)]
 pub struct Receipt {
     ok: bool
 }
 
+impl Receipt {
+    pub fn ok() -> Receipt {
+        Receipt { ok: true }
+    }
+}
+
 fn validate_manifest(_manifest: &Manifest) -> Res<()> {
     // TODO pls to validate manifest here
     Ok(())
@@ -34,66 +48,96 @@ fn validate_archive<R: Read>(mut reader: R) -> Res<()> {
     Ok(())
 }
 
-pub fn process_upload<R: Read>(store: &Store, user: &User, reader: R) -> Res<Receipt> {
+/// Fail unless the policy of the release's namespace allows it: its version
+/// scheme, and, for a new package, who may create packages there.
+pub fn check_namespace_policy(
+    db: &PgConnection,
+    admins: &[User],
+    identity: &Identity,
+    package: &PackageName,
+    version: &Version,
+) -> Res<()> {
+    let namespace = store::get_namespace(db, &package.namespace)?;
+    namespace.check_version(version)?;
+    if store::get_package(db, &package.namespace, &package.name).is_err() {
+        check_package_creation(db, admins, &namespace, identity)?;
+    }
+    Ok(())
+}
+
+pub fn process_upload<R: Read>(store: &Store, identity: &Identity, reader: R) -> Res<Receipt> {
     let manifest: Manifest = decode::from_read(reader)?;
+    let user = &identity.user;
+    identity.check_scope(Scope::Publish, &manifest.namespace, &manifest.name)?;
+    check_namespace_policy(
+        &store.db()?,
+        store.admins(),
+        identity,
+        &PackageName {
+            namespace: manifest.namespace.clone(),
+            name: manifest.name.clone(),
+        },
+        &manifest.version,
+    )?;
     store.insert_package(
         &manifest.namespace,
         &manifest.name,
         user,
     )?;
-    let owners = store.get_package_owners(
-        &manifest.namespace,
-        &manifest.name,
-    )?;
-    let filename = format!("{}-{}.tar.br", manifest.name, manifest.version);
-    let url = format!("/files/{}/{}", manifest.namespace, filename);
-    match owners.iter().find(|o| *o == user) {
-        None => Err(Error::AccessDenied(
-            manifest.namespace.clone(),
-            manifest.name.clone(),
-            user.clone(),
-        )),
-        Some(_) => {
-            validate_manifest(&manifest)?;
-            validate_archive(&mut manifest.data.as_slice())?;
-            store.add_release(&Release {
-                namespace: manifest.namespace.clone(),
-                name: manifest.name.clone(),
-                version: manifest.version.to_string(),
-                publisher: user.to_string(),
-                publish_time: SystemTime::now(),
-                artifact_url: url.clone(),
-                description: manifest.description.to_string(),
-                license: match manifest.license {
-                    License::SPDX(ref tag) => Some(tag.clone()),
-                    License::SPDXAndFile(ref tag, _) => Some(tag.clone()),
-                    _ => None,
-                },
-                license_file: match manifest.license {
-                    License::File(ref file) => Some(file.clone()),
-                    License::SPDXAndFile(_, ref file) => Some(file.clone()),
-                    _ => None,
-                },
-                keywords: manifest.keywords.clone(),
-                manifest: manifest.manifest.clone(),
-                readme_filename: match manifest.readme {
-                    Some((ref filename, _)) => Some(filename.clone()),
-                    None => None,
-                },
-                readme: match manifest.readme {
-                    Some((_, ref content)) => Some(content.clone()),
-                    None => None,
-                },
-                deprecated: false,
-                deprecated_by: None,
-                deprecated_on: None,
-                deleted: None,
-                deleted_on: None,
-                tarBrBlob: manifest.data.as_slice().to_owned(), //clone(),
-            })?;
-            Ok(Receipt {
-                ok: true
-            })
-        }
-    }
+    check_package_owner(store, &manifest.namespace, &manifest.name, identity)?;
+    let url = format!(
+        "/files/{}/{}/{}",
+        manifest.namespace, manifest.name, manifest.version
+    );
+    validate_manifest(&manifest)?;
+    validate_archive(&mut manifest.data.as_slice())?;
+    let artifact_hash = store.blobs().put(&manifest.data)?;
+    store.add_release(&Release {
+        namespace: manifest.namespace.clone(),
+        name: manifest.name.clone(),
+        version: manifest.version.to_string(),
+        publisher: user.to_string(),
+        publish_time: SystemTime::now(),
+        artifact_url: url.clone(),
+        description: manifest.description.to_string(),
+        license: match manifest.license {
+            License::SPDX(ref tag) => Some(tag.clone()),
+            License::SPDXAndFile(ref tag, _) => Some(tag.clone()),
+            _ => None,
+        },
+        license_file: match manifest.license {
+            License::File(ref file) => Some(file.clone()),
+            License::SPDXAndFile(_, ref file) => Some(file.clone()),
+            _ => None,
+        },
+        keywords: manifest.keywords.clone(),
+        manifest: manifest.manifest.clone(),
+        readme_filename: match manifest.readme {
+            Some((ref filename, _)) => Some(filename.clone()),
+            None => None,
+        },
+        readme: match manifest.readme {
+            Some((_, ref content)) => Some(content.clone()),
+            None => None,
+        },
+        deprecated: false,
+        deprecated_by: None,
+        deprecated_on: None,
+        deleted: None,
+        deleted_on: None,
+        artifact_hash: artifact_hash,
+        artifact_size: manifest.data.len() as i64,
+        dependencies: serde_json::to_string(&manifest.dependencies)?,
+        yanked: false,
+        yanked_by: None,
+        yanked_on: None,
+        authors: manifest.authors.clone(),
+        homepage: manifest.homepage.clone(),
+        repository: manifest.repository.clone(),
+        bugs: manifest.bugs.clone(),
+        // From the fields the client sent, like the columns above; the
+        // manifest source isn't parsed here.
+        normalized_manifest: Some(serde_json::to_string(&manifest.normalized())?),
+    })?;
+    Ok(Receipt::ok())
 }
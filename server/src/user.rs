@@ -108,6 +108,22 @@ impl fmt::Debug for Org {
     }
 }
 
+impl FromStr for Org {
+    type Err = Error;
+    fn from_str(s: &str) -> Res<Org> {
+        let mut it = s.split(':');
+        let source = it.next().ok_or(Error::InvalidOrgID(s.to_string()))?;
+        let id = it.next().ok_or(Error::InvalidOrgID(s.to_string()))?;
+        if it.next() != None {
+            return Err(Error::InvalidOrgID(s.to_string()));
+        }
+        Ok(Org {
+            provider: AuthSource::from_str(source)?,
+            id: id.to_string(),
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct OrgRecord {
     pub id: Org,